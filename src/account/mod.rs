@@ -0,0 +1,128 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::money::{Currency, Money};
+
+/// Number of trailing digits `mask_number` leaves visible
+const VISIBLE_DIGITS: usize = 4;
+
+/// Hides all but the last digits of a card or account number, e.g. `'6007620712733055'` becomes
+/// `************3055`
+///
+/// Quotes, spaces and dashes are dropped first. Returns `None` when nothing is left.
+pub fn mask_number(raw: &str) -> Option<String> {
+    let number: Vec<char> = raw
+        .chars()
+        .filter(|c| !(c.is_whitespace() || *c == '-' || *c == '\'' || *c == '"'))
+        .collect();
+    if number.is_empty() {
+        return None;
+    }
+    let hidden = number.len().saturating_sub(VISIBLE_DIGITS);
+    Some(
+        std::iter::repeat_n('*', hidden)
+            .chain(number[hidden..].iter().copied())
+            .collect(),
+    )
+}
+
+/// The trailing digits of a masked number that `mask_number` leaves visible
+fn visible_digits(masked: &str) -> &str {
+    let start = masked
+        .char_indices()
+        .rev()
+        .nth(VISIBLE_DIGITS - 1)
+        .map_or(0, |(index, _)| index);
+    &masked[start..]
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize, clap::ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountType {
+    Chequing,
+    Savings,
+    CreditCard,
+    LineOfCredit,
+    Investment,
+    #[default]
+    Other,
+}
+
+impl AccountType {
+    pub const ALL: [AccountType; 6] = [
+        AccountType::Chequing,
+        AccountType::Savings,
+        AccountType::CreditCard,
+        AccountType::LineOfCredit,
+        AccountType::Investment,
+        AccountType::Other,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountType::Chequing => "chequing",
+            AccountType::Savings => "savings",
+            AccountType::CreditCard => "credit_card",
+            AccountType::LineOfCredit => "line_of_credit",
+            AccountType::Investment => "investment",
+            AccountType::Other => "other",
+        }
+    }
+
+    pub fn parse(str: &str) -> Option<AccountType> {
+        AccountType::ALL
+            .into_iter()
+            .find(|account_type| account_type.as_str().eq_ignore_ascii_case(str.trim()))
+    }
+}
+
+impl fmt::Display for AccountType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A bank account or card that transactions are imported into
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Account {
+    /// Short name chosen by the user, e.g. `chequing`
+    pub id: String,
+    /// As produced by `mask_number`, matched against `Data::account_number` on import
+    pub masked_number: Option<String>,
+    pub institution: Option<String>,
+    pub account_type: AccountType,
+    pub currency: Currency,
+    /// Balance before the first stored transaction
+    pub opening_balance: Money,
+}
+
+impl Account {
+    /// An account of type `AccountType::Other` with a zero opening balance
+    pub fn new(id: &str, currency: Currency) -> Account {
+        Account {
+            id: id.to_string(),
+            masked_number: None,
+            institution: None,
+            account_type: AccountType::Other,
+            currency,
+            opening_balance: Money::zero(currency),
+        }
+    }
+
+    /// Whether the `number` of a statement row belongs to the account, comparing the visible
+    /// digits only so that `3055` matches `************3055`. An account or a row without a
+    /// number never matches.
+    pub fn matches_number(&self, number: &str) -> bool {
+        match (self.masked_number.as_deref(), mask_number(number)) {
+            (Some(stored), Some(number)) => visible_digits(stored) == visible_digits(&number),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./tests/test.rs"]
+mod test;
@@ -0,0 +1,37 @@
+use super::*;
+use rstest::rstest;
+
+#[rstest]
+#[case("'6007620712733055'", Some("************3055"))]
+#[case("4520 1234 5678 9012", Some("************9012"))]
+#[case("123", Some("123"))]
+#[case("''", None)]
+fn test_mask_number(#[case] raw: &str, #[case] expected: Option<&str>) {
+    assert_eq!(expected, mask_number(raw).as_deref());
+}
+
+#[test]
+fn test_matches_number() {
+    let account = Account {
+        masked_number: mask_number("6007620712733055"),
+        ..Account::new("chequing", Currency::CAD)
+    };
+
+    assert!(account.matches_number("************3055"));
+    assert!(account.matches_number("'6007620712733055'"));
+    assert!(account.matches_number("3055"));
+    assert!(account.matches_number("*3055"));
+    assert!(!account.matches_number("************1234"));
+    assert!(!account.matches_number(""));
+    let savings = Account::new("savings", Currency::CAD);
+    assert!(!savings.matches_number("************3055"));
+    assert!(!savings.matches_number(""));
+}
+
+#[rstest]
+#[case("credit_card", Some(AccountType::CreditCard))]
+#[case("Chequing", Some(AccountType::Chequing))]
+#[case("brokerage", None)]
+fn test_account_type_parse(#[case] str: &str, #[case] expected: Option<AccountType>) {
+    assert_eq!(expected, AccountType::parse(str));
+}
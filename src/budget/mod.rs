@@ -0,0 +1,419 @@
+use std::{error::Error, fmt, fs, io, path::Path};
+
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::category::{CategoryTree, TransactionCategory};
+use crate::csv_parser::Data;
+use crate::money::{Currency, Money, MoneyError};
+use crate::output::Tabular;
+use crate::report::YearMonth;
+
+/// Percentage of the budget spent at which `BudgetState::Alert` is raised, unless set per budget
+pub const DEFAULT_ALERT_AT: u32 = 80;
+
+/// The span of time a budget limit applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetPeriod {
+    /// Monday to Sunday
+    Weekly,
+    #[default]
+    Monthly,
+    Annual,
+}
+
+impl BudgetPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BudgetPeriod::Weekly => "weekly",
+            BudgetPeriod::Monthly => "monthly",
+            BudgetPeriod::Annual => "annual",
+        }
+    }
+
+    /// First day of the period containing `date`
+    pub fn start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            BudgetPeriod::Weekly => {
+                date - Duration::days(date.weekday().num_days_from_monday().into())
+            }
+            BudgetPeriod::Monthly => YearMonth::of(date).first_day(),
+            BudgetPeriod::Annual => {
+                NaiveDate::from_ymd_opt(date.year(), 1, 1).expect("years always start")
+            }
+        }
+    }
+
+    /// Last day of the period containing `date`
+    pub fn end(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            BudgetPeriod::Weekly => self.start(date) + Duration::days(6),
+            BudgetPeriod::Monthly => YearMonth::of(date).last_day(),
+            BudgetPeriod::Annual => {
+                NaiveDate::from_ymd_opt(date.year(), 12, 31).expect("years always end")
+            }
+        }
+    }
+}
+
+/// A budget as written in the budgets file, see `Budgets`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BudgetConfig {
+    category: TransactionCategory,
+    limit: String,
+    #[serde(default)]
+    period: BudgetPeriod,
+    currency: Option<Currency>,
+    #[serde(default)]
+    rollover: bool,
+    alert_at: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BudgetsFile {
+    #[serde(default)]
+    budget: Vec<BudgetConfig>,
+}
+
+/// A spending limit for a category and its subcategories
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Budget {
+    pub category: TransactionCategory,
+    /// Positive amount that may be spent every period
+    pub limit: Money,
+    pub period: BudgetPeriod,
+    /// Whether the unspent part of a period's budget is added to the next period. Overspending is
+    /// never carried over.
+    pub rollover: bool,
+    /// Percentage of the available budget at which to raise `BudgetState::Alert`
+    pub alert_at: u32,
+}
+
+/// How a budget is doing in the current period, from best to worst
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetState {
+    Ok,
+    /// Spending so far is below the alert threshold but the projection exceeds the budget
+    Projected,
+    /// Spending reached the budget's `alert_at` percentage
+    Alert,
+    /// Spending exceeds the budget
+    Over,
+}
+
+impl BudgetState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BudgetState::Ok => "ok",
+            BudgetState::Projected => "projected",
+            BudgetState::Alert => "alert",
+            BudgetState::Over => "over",
+        }
+    }
+}
+
+/// Actual spending compared to a budget, for the period containing the evaluation date
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BudgetStatus {
+    pub category: TransactionCategory,
+    /// Display path in the category tree, e.g. `Housing > Mortgage`
+    pub name: String,
+    pub period: BudgetPeriod,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub limit: Money,
+    /// Unspent budget of the previous periods, zero without rollover
+    pub carried: Money,
+    /// `limit` plus `carried`
+    pub available: Money,
+    /// Debits minus refunds from the start of the period up to the evaluation date
+    pub spent: Money,
+    /// Negative once the budget is exceeded
+    pub remaining: Money,
+    /// `spent` as a percentage of `available`, `None` when nothing is available
+    pub percent_used: Option<f64>,
+    /// Spending at the end of the period if it continues at the same daily rate
+    pub projected: Money,
+    pub state: BudgetState,
+}
+
+/// A set of budgets, read from a TOML file:
+///
+/// ```toml
+/// [[budget]]
+/// category = "food"
+/// limit = "600.00"
+///
+/// [[budget]]
+/// category = "entertainment"
+/// limit = "50.00"
+/// period = "weekly"
+/// rollover = true
+/// alert_at = 90
+/// ```
+///
+/// `period` is one of `weekly`, `monthly` (the default) or `annual`, `currency` defaults to CAD.
+#[derive(Debug, Clone, Default)]
+pub struct Budgets {
+    budgets: Vec<Budget>,
+}
+
+impl Budgets {
+    pub fn new(budgets: Vec<Budget>) -> Budgets {
+        Budgets { budgets }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Budgets, BudgetError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|source| BudgetError::Io {
+            file: path.display().to_string(),
+            source,
+        })?;
+        Budgets::from_toml(&text)
+    }
+
+    pub fn from_toml(text: &str) -> Result<Budgets, BudgetError> {
+        let file: BudgetsFile = toml::from_str(text).map_err(BudgetError::Toml)?;
+        let mut budgets: Vec<Budget> = Vec::new();
+        for config in file.budget {
+            let currency = config.currency.unwrap_or(Currency::CAD);
+            let limit = Money::parse(&config.limit, currency)
+                .and_then(Money::checked_abs)
+                .map_err(|source| BudgetError::InvalidAmount {
+                    category: config.category.clone(),
+                    source,
+                })?;
+            let budget = Budget {
+                category: config.category,
+                limit,
+                period: config.period,
+                rollover: config.rollover,
+                alert_at: config.alert_at.unwrap_or(DEFAULT_ALERT_AT),
+            };
+            if budgets.iter().any(|existing| {
+                existing.category == budget.category && existing.period == budget.period
+            }) {
+                return Err(BudgetError::Duplicate {
+                    category: budget.category,
+                    period: budget.period,
+                });
+            }
+            budgets.push(budget);
+        }
+        Ok(Budgets::new(budgets))
+    }
+
+    pub fn budgets(&self) -> &[Budget] {
+        &self.budgets
+    }
+
+    /// Checks that every budget refers to a category of `tree`
+    pub fn validate(&self, tree: &CategoryTree) -> Result<(), BudgetError> {
+        match self
+            .budgets
+            .iter()
+            .find(|budget| !tree.contains(&budget.category))
+        {
+            Some(budget) => Err(BudgetError::UnknownCategory(budget.category.clone())),
+            None => Ok(()),
+        }
+    }
+
+    /// Compares the spending in `rows` to every budget, for the periods containing `as_of`
+    ///
+    /// Transactions after `as_of` and transactions in another currency than the budget's are
+    /// ignored. With rollover, unspent budget is carried over from every period since the first
+    /// transaction of the category.
+    pub fn evaluate(
+        &self,
+        rows: &[Data],
+        tree: &CategoryTree,
+        as_of: NaiveDate,
+    ) -> Result<Vec<BudgetStatus>, MoneyError> {
+        self.budgets
+            .iter()
+            .map(|budget| budget.evaluate(rows, tree, as_of))
+            .collect()
+    }
+}
+
+impl Budget {
+    /// Money spent on the budget's category between `from` and `to` inclusive
+    fn spent(&self, rows: &[&Data], from: NaiveDate, to: NaiveDate) -> Result<Money, MoneyError> {
+        let amounts = rows
+            .iter()
+            .filter(|data| data.date >= from && data.date <= to)
+            .map(|data| data.amount);
+        Money::checked_sum(amounts, self.limit.currency())?.checked_neg()
+    }
+
+    pub fn evaluate(
+        &self,
+        rows: &[Data],
+        tree: &CategoryTree,
+        as_of: NaiveDate,
+    ) -> Result<BudgetStatus, MoneyError> {
+        let currency = self.limit.currency();
+        let rows: Vec<&Data> = rows
+            .iter()
+            .filter(|data| {
+                data.amount.currency() == currency
+                    && data.date <= as_of
+                    && (data.category == self.category
+                        || tree.is_within(&data.category, &self.category))
+            })
+            .collect();
+
+        let start = self.period.start(as_of);
+        let end = self.period.end(as_of);
+        let mut carried = Money::zero(currency);
+        if self.rollover {
+            if let Some(first) = rows.iter().map(|data| data.date).min() {
+                let mut period = self.period.start(first);
+                while period < start {
+                    let period_end = self.period.end(period);
+                    let left = self
+                        .limit
+                        .checked_add(carried)?
+                        .checked_sub(self.spent(&rows, period, period_end)?)?;
+                    carried = if left.is_positive() {
+                        left
+                    } else {
+                        Money::zero(currency)
+                    };
+                    period = period_end + Duration::days(1);
+                }
+            }
+        }
+
+        let available = self.limit.checked_add(carried)?;
+        let spent = self.spent(&rows, start, as_of)?;
+        let elapsed = (as_of - start).num_days() + 1;
+        let length = (end - start).num_days() + 1;
+        let projected = spent.checked_mul(length)?.checked_div(elapsed)?;
+        let percent_used = (available.is_positive())
+            .then(|| spent.minor_units() as f64 * 100.0 / available.minor_units() as f64);
+
+        let state = if spent > available {
+            BudgetState::Over
+        } else if percent_used.is_some_and(|percent| percent >= f64::from(self.alert_at)) {
+            BudgetState::Alert
+        } else if projected > available {
+            BudgetState::Projected
+        } else {
+            BudgetState::Ok
+        };
+
+        Ok(BudgetStatus {
+            category: self.category.clone(),
+            name: tree.path(&self.category),
+            period: self.period,
+            start,
+            end,
+            limit: self.limit,
+            carried,
+            available,
+            spent,
+            remaining: available.checked_sub(spent)?,
+            percent_used,
+            projected,
+            state,
+        })
+    }
+}
+
+impl Tabular for BudgetStatus {
+    const HEADERS: &'static [&'static str] = &[
+        "Category",
+        "Name",
+        "Period",
+        "Start",
+        "End",
+        "Currency",
+        "Available",
+        "Spent",
+        "Remaining",
+        "Used",
+        "Projected",
+        "State",
+    ];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.category.to_string(),
+            self.name.clone(),
+            self.period.as_str().to_string(),
+            self.start.to_string(),
+            self.end.to_string(),
+            self.limit.currency().to_string(),
+            self.available.to_string(),
+            self.spent.to_string(),
+            self.remaining.to_string(),
+            self.percent_used
+                .map(|percent| format!("{percent:.0}%"))
+                .unwrap_or_default(),
+            self.projected.to_string(),
+            self.state.as_str().to_string(),
+        ]
+    }
+}
+
+#[derive(Debug)]
+pub enum BudgetError {
+    Io {
+        file: String,
+        source: io::Error,
+    },
+    Toml(toml::de::Error),
+    InvalidAmount {
+        category: TransactionCategory,
+        source: MoneyError,
+    },
+    /// Two budgets of the same period for one category
+    Duplicate {
+        category: TransactionCategory,
+        period: BudgetPeriod,
+    },
+    UnknownCategory(TransactionCategory),
+}
+
+impl fmt::Display for BudgetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BudgetError::Io { file, source } => write!(f, "{file}: {source}"),
+            BudgetError::Toml(source) => write!(f, "invalid budgets file: {source}"),
+            BudgetError::InvalidAmount { category, source } => {
+                write!(f, "budget for {category:?}: {source}")
+            }
+            BudgetError::Duplicate { category, period } => {
+                write!(
+                    f,
+                    "more than one {} budget for {category:?}",
+                    period.as_str()
+                )
+            }
+            BudgetError::UnknownCategory(category) => {
+                write!(f, "budget for unknown category {category:?}")
+            }
+        }
+    }
+}
+
+impl Error for BudgetError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BudgetError::Io { source, .. } => Some(source),
+            BudgetError::Toml(source) => Some(source),
+            BudgetError::InvalidAmount { source, .. } => Some(source),
+            BudgetError::Duplicate { .. } | BudgetError::UnknownCategory(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./tests/test.rs"]
+mod test;
@@ -0,0 +1,131 @@
+use super::*;
+use rstest::rstest;
+
+use crate::testing::{cad, date};
+
+fn data(day: &str, amount: Money, category: TransactionCategory) -> Data {
+    Data {
+        category,
+        ..Data::new(date(day), amount, "TEST")
+    }
+}
+
+fn rows() -> Vec<Data> {
+    vec![
+        data("2024-04-08", cad("-400.00"), TransactionCategory::FOOD),
+        data("2024-05-05", cad("-100.00"), TransactionCategory::FOOD),
+        data("2024-06-03", cad("-100.00"), TransactionCategory::FOOD),
+        data("2024-06-10", cad("-150.00"), TransactionCategory::FOOD),
+        data("2024-06-12", cad("20.00"), TransactionCategory::FOOD),
+        data(
+            "2024-06-13",
+            Money::new(-5000, Currency::USD),
+            TransactionCategory::FOOD,
+        ),
+        data("2024-06-14", cad("-75.00"), TransactionCategory::BILLS),
+        data("2024-06-20", cad("-900.00"), TransactionCategory::FOOD),
+    ]
+}
+
+fn food_budget(toml: &str) -> BudgetStatus {
+    let budgets = Budgets::from_toml(toml).unwrap();
+    let tree = CategoryTree::defaults();
+    budgets.validate(&tree).unwrap();
+    budgets
+        .evaluate(&rows(), &tree, date("2024-06-15"))
+        .unwrap()
+        .remove(0)
+}
+
+#[test]
+fn test_monthly_budget() {
+    let status = food_budget("[[budget]]\ncategory = \"food\"\nlimit = \"600\"");
+
+    assert_eq!(date("2024-06-01"), status.start);
+    assert_eq!(date("2024-06-30"), status.end);
+    assert_eq!(cad("0.00"), status.carried);
+    assert_eq!(cad("230.00"), status.spent);
+    assert_eq!(cad("370.00"), status.remaining);
+    assert_eq!(cad("460.00"), status.projected);
+    assert_eq!("38%", status.cells()[9]);
+    assert_eq!(BudgetState::Ok, status.state);
+}
+
+#[rstest]
+#[case("600", BudgetState::Ok)]
+#[case("400", BudgetState::Projected)]
+#[case("280", BudgetState::Alert)]
+#[case("229.99", BudgetState::Over)]
+fn test_budget_state(#[case] limit: &str, #[case] expected: BudgetState) {
+    let status = food_budget(&format!(
+        "[[budget]]\ncategory = \"food\"\nlimit = \"{limit}\""
+    ));
+
+    assert_eq!(expected, status.state);
+}
+
+#[test]
+fn test_rollover_carries_unspent_budget_only() {
+    // April is overspent by 100.00, which is not carried, May leaves 200.00 unspent
+    let status = food_budget("[[budget]]\ncategory = \"food\"\nlimit = \"300\"\nrollover = true");
+
+    assert_eq!(cad("200.00"), status.carried);
+    assert_eq!(cad("500.00"), status.available);
+    assert_eq!(cad("270.00"), status.remaining);
+}
+
+#[test]
+fn test_weekly_budget() {
+    let budgets =
+        Budgets::from_toml("[[budget]]\ncategory = \"food\"\nlimit = \"200\"\nperiod = \"weekly\"")
+            .unwrap();
+    let status = budgets
+        .evaluate(&rows(), &CategoryTree::defaults(), date("2024-06-12"))
+        .unwrap()
+        .remove(0);
+
+    assert_eq!(date("2024-06-10"), status.start);
+    assert_eq!(date("2024-06-16"), status.end);
+    assert_eq!(cad("130.00"), status.spent);
+    assert_eq!(cad("303.33"), status.projected);
+    assert_eq!(BudgetState::Projected, status.state);
+}
+
+#[test]
+fn test_budget_includes_subcategories() {
+    let tree = CategoryTree::from_toml(
+        "[[category]]\nid = \"groceries\"\nname = \"Groceries\"\nparent = \"food\"",
+    )
+    .unwrap();
+    let mut rows = rows();
+    rows.push(data(
+        "2024-06-15",
+        cad("-70.00"),
+        TransactionCategory::new("groceries"),
+    ));
+    let budgets = Budgets::from_toml("[[budget]]\ncategory = \"food\"\nlimit = \"600\"").unwrap();
+
+    let status = budgets.evaluate(&rows, &tree, date("2024-06-15")).unwrap();
+
+    assert_eq!(cad("300.00"), status[0].spent);
+}
+
+#[test]
+fn test_budget_errors() {
+    assert!(matches!(
+        Budgets::from_toml("[[budget]]\ncategory = \"food\"\nlimit = \"ten\""),
+        Err(BudgetError::InvalidAmount { .. })
+    ));
+    assert!(matches!(
+        Budgets::from_toml(
+            "[[budget]]\ncategory = \"food\"\nlimit = \"10\"\n\
+             [[budget]]\ncategory = \"food\"\nlimit = \"20\""
+        ),
+        Err(BudgetError::Duplicate { .. })
+    ));
+    let unknown = Budgets::from_toml("[[budget]]\ncategory = \"pets\"\nlimit = \"10\"").unwrap();
+    assert!(matches!(
+        unknown.validate(&CategoryTree::defaults()),
+        Err(BudgetError::UnknownCategory(_))
+    ));
+}
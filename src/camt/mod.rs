@@ -0,0 +1,365 @@
+use std::{error::Error, fmt, fs, io};
+
+use chrono::{NaiveDate, NaiveDateTime};
+use roxmltree::{Document, Node};
+
+use crate::account::mask_number;
+use crate::csv_parser::{Data, ParseOutput, StatementBalance};
+use crate::money::{Currency, Money, MoneyError};
+
+/// Name of the CAMT.053 format in import summaries and `import --format`
+pub const CAMT_FORMAT: &str = "camt.053";
+
+/// Whether `contents` looks like an ISO 20022 bank to customer statement
+pub fn is_camt(contents: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(contents);
+    text.contains("<BkToCstmrStmt") || text.contains("camt.053")
+}
+
+/// Whether the file at `file_path` is a CAMT.053 statement, judged by its contents
+pub fn is_camt_file(file_path: &str) -> bool {
+    fs::read(file_path).is_ok_and(|contents| is_camt(&contents))
+}
+
+/// Reads the booked entries of every statement of a CAMT.053 file
+///
+/// Each `Ntry` becomes a row dated by its booking date, with the value date as its `user_date`
+/// when they differ and the account servicer reference as its `transaction_id`. The amount is
+/// negative for `DBIT` entries and keeps the currency of the entry. The description is the
+/// counterparty, i.e. the creditor of a debit or the debtor of a credit, followed by the
+/// unstructured remittance information or, lacking both, the additional entry information.
+/// Pending and informational entries are skipped. The `CLBD` and `CLAV` balances of the last
+/// statement are kept in the metadata.
+pub fn parse_camt(file_path: &str) -> Result<ParseOutput, CamtError> {
+    let contents = fs::read_to_string(file_path).map_err(|source| CamtError::Io {
+        file: file_path.to_string(),
+        source,
+    })?;
+    parse_str(file_path, &contents)
+}
+
+fn parse_str(file_path: &str, contents: &str) -> Result<ParseOutput, CamtError> {
+    let document = Document::parse(contents).map_err(|source| CamtError::Xml {
+        file: file_path.to_string(),
+        source,
+    })?;
+    let report =
+        child(document.root_element(), "BkToCstmrStmt").ok_or_else(|| CamtError::NotCamt {
+            file: file_path.to_string(),
+        })?;
+
+    let mut output = ParseOutput::default();
+    output.metadata.valid_as_of =
+        descendant_text(report, &["GrpHdr", "CreDtTm"]).and_then(parse_date_time);
+    for statement in children(report, "Stmt") {
+        let account_number = descendant_text(statement, &["Acct", "Id", "IBAN"])
+            .or_else(|| descendant_text(statement, &["Acct", "Id", "Othr", "Id"]))
+            .and_then(mask_number);
+        for (index, entry) in children(statement, "Ntry").enumerate() {
+            let entry = Entry {
+                file: file_path,
+                node: entry,
+                index: index + 1,
+            };
+            if !entry.is_booked() {
+                continue;
+            }
+            let mut data = entry.transaction()?;
+            data.account_number = account_number.clone();
+            output.metadata.cover(data.date);
+            output.rows.push(data);
+        }
+        for node in children(statement, "Bal") {
+            let balance = Entry {
+                file: file_path,
+                node,
+                index: 0,
+            };
+            match descendant_text(node, &["Tp", "CdOrPrtry", "Cd"]) {
+                Some("CLBD") => output.metadata.ledger_balance = Some(balance.balance()?),
+                Some("CLAV") => output.metadata.available_balance = Some(balance.balance()?),
+                _ => {}
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// The child elements of `node` named `name`, ignoring namespaces
+fn children<'a, 'input>(
+    node: Node<'a, 'input>,
+    name: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children()
+        .filter(move |child| child.is_element() && child.tag_name().name() == name)
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &'static str) -> Option<Node<'a, 'input>> {
+    children(node, name).next()
+}
+
+/// The element at `path` below `node`, following the first child of each name
+fn descendant<'a, 'input>(
+    node: Node<'a, 'input>,
+    path: &[&'static str],
+) -> Option<Node<'a, 'input>> {
+    path.iter().try_fold(node, |node, name| child(node, name))
+}
+
+/// The trimmed, non-empty text of the element at `path` below `node`
+fn descendant_text<'a>(node: Node<'a, '_>, path: &[&'static str]) -> Option<&'a str> {
+    descendant(node, path)?
+        .text()
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+}
+
+/// The date of an ISO date or date time, e.g. `2024-06-03` or `2024-06-03T10:15:00+02:00`
+fn parse_date(text: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(text.get(..10)?, "%Y-%m-%d").ok()
+}
+
+/// An ISO date time without its fraction and offset, the bank's local time
+fn parse_date_time(text: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(text.get(..19)?, "%Y-%m-%dT%H:%M:%S").ok()
+}
+
+/// An `Ntry` or `Bal` element, with its position for errors
+struct Entry<'a, 'input> {
+    file: &'a str,
+    node: Node<'a, 'input>,
+    /// 1-based position of the entry in its statement, 0 for balances
+    index: usize,
+}
+
+impl<'a> Entry<'a, '_> {
+    fn context(&self) -> String {
+        if self.index == 0 {
+            return "Bal".to_string();
+        }
+        match self.reference() {
+            Some(reference) => format!("Ntry {reference:?}"),
+            None => format!("Ntry #{}", self.index),
+        }
+    }
+
+    /// The bank's reference of the entry, or of its single transaction
+    fn reference(&self) -> Option<&'a str> {
+        descendant_text(self.node, &["AcctSvcrRef"])
+            .or_else(|| descendant_text(self.node, &["NtryDtls", "TxDtls", "Refs", "AcctSvcrRef"]))
+    }
+
+    fn text(&self, path: &[&'static str]) -> Result<&'a str, CamtError> {
+        descendant_text(self.node, path).ok_or_else(|| CamtError::MissingElement {
+            file: self.file.to_string(),
+            element: path.last().copied().unwrap_or_default(),
+            context: self.context(),
+        })
+    }
+
+    /// Whether the entry is booked, as opposed to pending or informational. Older versions of
+    /// the schema have the status as text, newer ones in a `Cd` element.
+    fn is_booked(&self) -> bool {
+        let status = descendant_text(self.node, &["Sts", "Cd"])
+            .or_else(|| descendant_text(self.node, &["Sts"]));
+        status.is_none_or(|status| status == "BOOK")
+    }
+
+    /// A date given as either `Dt` or `DtTm` below `element`
+    fn date(&self, element: &'static str) -> Result<NaiveDate, CamtError> {
+        let raw = descendant_text(self.node, &[element, "Dt"])
+            .or_else(|| descendant_text(self.node, &[element, "DtTm"]))
+            .ok_or_else(|| CamtError::MissingElement {
+                file: self.file.to_string(),
+                element,
+                context: self.context(),
+            })?;
+        parse_date(raw).ok_or_else(|| CamtError::InvalidDate {
+            file: self.file.to_string(),
+            element,
+            context: self.context(),
+            raw: raw.to_string(),
+        })
+    }
+
+    /// The signed amount of the `Amt` and `CdtDbtInd` elements, in the `Ccy` of the amount
+    fn amount(&self) -> Result<Money, CamtError> {
+        let node = child(self.node, "Amt").ok_or_else(|| CamtError::MissingElement {
+            file: self.file.to_string(),
+            element: "Amt",
+            context: self.context(),
+        })?;
+        let raw = node.text().unwrap_or_default().trim();
+        let code = node.attribute("Ccy").unwrap_or_default();
+        let currency = Currency::new(code).map_err(|source| CamtError::InvalidAmount {
+            file: self.file.to_string(),
+            context: self.context(),
+            raw: code.to_string(),
+            source,
+        })?;
+        let amount = Money::parse(raw, currency).map_err(|source| CamtError::InvalidAmount {
+            file: self.file.to_string(),
+            context: self.context(),
+            raw: raw.to_string(),
+            source,
+        })?;
+        match self.text(&["CdtDbtInd"])? {
+            "CRDT" => Ok(amount),
+            "DBIT" => amount
+                .checked_neg()
+                .map_err(|source| CamtError::InvalidAmount {
+                    file: self.file.to_string(),
+                    context: self.context(),
+                    raw: raw.to_string(),
+                    source,
+                }),
+            other => Err(CamtError::InvalidIndicator {
+                file: self.file.to_string(),
+                context: self.context(),
+                raw: other.to_string(),
+            }),
+        }
+    }
+
+    /// The name of the other party, the creditor of a debit or the debtor of a credit. Newer
+    /// versions of the schema wrap the name in a `Pty` element.
+    fn counterparty(&self, debit: bool) -> Option<&'a str> {
+        let party = if debit { "Cdtr" } else { "Dbtr" };
+        let parties = descendant(self.node, &["NtryDtls", "TxDtls", "RltdPties", party])?;
+        descendant_text(parties, &["Nm"]).or_else(|| descendant_text(parties, &["Pty", "Nm"]))
+    }
+
+    fn remittance(&self) -> Vec<&'a str> {
+        descendant(self.node, &["NtryDtls", "TxDtls", "RmtInf"])
+            .map(|info| {
+                children(info, "Ustrd")
+                    .filter_map(|line| line.text())
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn transaction(&self) -> Result<Data, CamtError> {
+        let date = self.date("BookgDt")?;
+        let amount = self.amount()?;
+        let mut parts: Vec<&str> = self
+            .counterparty(amount.is_negative())
+            .into_iter()
+            .chain(self.remittance())
+            .collect();
+        if parts.is_empty() {
+            parts.extend(descendant_text(self.node, &["AddtlNtryInf"]));
+        }
+
+        let mut data = Data::new(date, amount, &parts.join(" "));
+        data.user_date = match child(self.node, "ValDt") {
+            Some(_) => Some(self.date("ValDt")?).filter(|value_date| *value_date != date),
+            None => None,
+        };
+        data.transaction_id = self.reference().map(str::to_string);
+        Ok(data)
+    }
+
+    fn balance(&self) -> Result<StatementBalance, CamtError> {
+        Ok(StatementBalance {
+            amount: self.amount()?,
+            date: self.date("Dt")?,
+        })
+    }
+}
+
+/// Everything that can go wrong while reading a CAMT.053 file
+///
+/// `context` names the entry holding the offending element, e.g. `Ntry "2024060300001"`, or `Bal`
+/// for balances.
+#[derive(Debug)]
+pub enum CamtError {
+    /// The file could not be opened or read
+    Io { file: String, source: io::Error },
+    Xml {
+        file: String,
+        source: roxmltree::Error,
+    },
+    /// The document has no `BkToCstmrStmt` element
+    NotCamt { file: String },
+    MissingElement {
+        file: String,
+        element: &'static str,
+        context: String,
+    },
+    InvalidDate {
+        file: String,
+        element: &'static str,
+        context: String,
+        raw: String,
+    },
+    /// The amount or its currency can't be read
+    InvalidAmount {
+        file: String,
+        context: String,
+        raw: String,
+        source: MoneyError,
+    },
+    /// `CdtDbtInd` is neither `CRDT` nor `DBIT`
+    InvalidIndicator {
+        file: String,
+        context: String,
+        raw: String,
+    },
+}
+
+impl fmt::Display for CamtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CamtError::Io { file, source } => write!(f, "{file}: {source}"),
+            CamtError::Xml { file, source } => write!(f, "{file}: invalid XML: {source}"),
+            CamtError::NotCamt { file } => {
+                write!(
+                    f,
+                    "{file}: not a CAMT.053 statement, no BkToCstmrStmt element"
+                )
+            }
+            CamtError::MissingElement {
+                file,
+                element,
+                context,
+            } => write!(f, "{file}: {context}: missing {element}"),
+            CamtError::InvalidDate {
+                file,
+                element,
+                context,
+                raw,
+            } => write!(
+                f,
+                "{file}: {context}: {element}: expected a date like 2024-06-03, got {raw:?}"
+            ),
+            CamtError::InvalidAmount {
+                file,
+                context,
+                raw,
+                source,
+            } => write!(f, "{file}: {context}: {source} (got {raw:?})"),
+            CamtError::InvalidIndicator { file, context, raw } => write!(
+                f,
+                "{file}: {context}: expected CdtDbtInd 'CRDT' or 'DBIT', got {raw:?}"
+            ),
+        }
+    }
+}
+
+impl Error for CamtError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CamtError::Io { source, .. } => Some(source),
+            CamtError::Xml { source, .. } => Some(source),
+            CamtError::InvalidAmount { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./tests/test.rs"]
+mod test;
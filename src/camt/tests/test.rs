@@ -0,0 +1,124 @@
+use super::*;
+
+use crate::csv_parser::TransactionType;
+use crate::testing::{date, eur};
+
+const TEST_FILE_PATH: &str = "src/camt/tests/test_statement.xml";
+
+/// A statement with one entry made of `entry`
+fn statement(entry: &str) -> String {
+    format!("<Document><BkToCstmrStmt><Stmt><Ntry>{entry}</Ntry></Stmt></BkToCstmrStmt></Document>")
+}
+
+#[test]
+fn test_parse_camt() {
+    let output = parse_camt(TEST_FILE_PATH).unwrap();
+
+    // The pending entry is skipped
+    assert_eq!(3, output.rows.len());
+    let rent = &output.rows[0];
+    assert_eq!(date("2024-06-03"), rent.date);
+    assert_eq!(Some(date("2024-06-01")), rent.user_date);
+    assert_eq!(eur("-1850.00"), rent.amount);
+    assert_eq!(TransactionType::DEBIT, rent.transaction_type);
+    assert_eq!("Hausverwaltung GmbH Miete Juni Wohnung 3", rent.description);
+    assert_eq!(Some("2024060300001"), rent.transaction_id.as_deref());
+    assert_eq!(
+        Some("******************3000"),
+        rent.account_number.as_deref()
+    );
+
+    let refund = &output.rows[1];
+    assert_eq!(date("2024-06-12"), refund.date);
+    assert_eq!(None, refund.user_date);
+    assert_eq!(TransactionType::CREDIT, refund.transaction_type);
+    assert_eq!("Online Shop AG Erstattung 4711", refund.description);
+    assert_eq!(Some("2024061200002"), refund.transaction_id.as_deref());
+
+    let fee = &output.rows[2];
+    assert_eq!("Kontofuehrungsgebuehr", fee.description);
+    assert_eq!(None, fee.transaction_id);
+
+    let metadata = output.metadata;
+    assert_eq!(
+        date("2024-07-01").and_hms_opt(6, 30, 0),
+        metadata.valid_as_of
+    );
+    assert_eq!(
+        Some(StatementBalance {
+            amount: eur("-250.50"),
+            date: date("2024-06-30"),
+        }),
+        metadata.ledger_balance
+    );
+    assert_eq!(None, metadata.available_balance);
+    assert_eq!(
+        Some((date("2024-06-03"), date("2024-06-30"))),
+        metadata.covered()
+    );
+}
+
+#[test]
+fn test_entry_currency_is_kept() {
+    let output = parse_str(
+        "usd.xml",
+        &statement(
+            "<Amt Ccy=\"USD\">12.00</Amt><CdtDbtInd>CRDT</CdtDbtInd>\
+             <BookgDt><Dt>2024-06-03</Dt></BookgDt><Sts><Cd>BOOK</Cd></Sts>",
+        ),
+    )
+    .unwrap();
+
+    assert_eq!(
+        Money::parse("12.00", Currency::USD).unwrap(),
+        output.rows[0].amount
+    );
+}
+
+#[test]
+fn test_parse_errors() {
+    let missing_date = parse_str(
+        "bad.xml",
+        &statement("<Amt Ccy=\"EUR\">1.00</Amt><CdtDbtInd>CRDT</CdtDbtInd>"),
+    );
+    let bad_indicator = parse_str(
+        "bad.xml",
+        &statement(
+            "<Amt Ccy=\"EUR\">1.00</Amt><CdtDbtInd>CR</CdtDbtInd>\
+             <BookgDt><Dt>2024-06-03</Dt></BookgDt><AcctSvcrRef>R1</AcctSvcrRef>",
+        ),
+    );
+    let bad_currency = parse_str(
+        "bad.xml",
+        &statement(
+            "<Amt>1.00</Amt><CdtDbtInd>CRDT</CdtDbtInd><BookgDt><Dt>2024-06-03</Dt></BookgDt>",
+        ),
+    );
+
+    assert_eq!(
+        "bad.xml: Ntry #1: missing BookgDt",
+        missing_date.unwrap_err().to_string()
+    );
+    assert_eq!(
+        "bad.xml: Ntry \"R1\": expected CdtDbtInd 'CRDT' or 'DBIT', got \"CR\"",
+        bad_indicator.unwrap_err().to_string()
+    );
+    assert!(matches!(
+        bad_currency,
+        Err(CamtError::InvalidAmount { raw, .. }) if raw.is_empty()
+    ));
+    assert!(matches!(
+        parse_str("other.xml", "<Document><Other/></Document>"),
+        Err(CamtError::NotCamt { .. })
+    ));
+    assert!(matches!(
+        parse_str("broken.xml", "<Document>"),
+        Err(CamtError::Xml { .. })
+    ));
+}
+
+#[test]
+fn test_is_camt_file() {
+    assert!(is_camt_file(TEST_FILE_PATH));
+    assert!(!is_camt_file("src/ofx/tests/test_statement.ofx"));
+}
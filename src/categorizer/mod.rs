@@ -0,0 +1,282 @@
+use std::{error::Error, fmt, fs, io, path::Path};
+
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+
+use crate::category::{CategoryTree, TransactionCategory};
+use crate::csv_parser::{Data, TransactionType};
+use crate::money::{Currency, Money, MoneyError};
+use crate::payee::{same_payee, Description};
+
+/// A rule as written in the rules file, see `Categorizer`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleConfig {
+    name: String,
+    category: TransactionCategory,
+    #[serde(default)]
+    description_contains: Vec<String>,
+    description_regex: Option<String>,
+    #[serde(default)]
+    channel: Vec<String>,
+    #[serde(default)]
+    payee: Vec<String>,
+    transaction_type: Option<TransactionType>,
+    min_amount: Option<String>,
+    max_amount: Option<String>,
+    currency: Option<Currency>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    #[serde(default)]
+    rule: Vec<RuleConfig>,
+}
+
+/// One categorization rule. Every condition that is set must hold for the rule to match.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub name: String,
+    pub category: TransactionCategory,
+    /// Case insensitive substrings, any of which must appear in the description
+    pub description_contains: Vec<String>,
+    pub description_regex: Option<Regex>,
+    /// Channel codes, e.g. `DS` for `[DS]STRATA FEE`, any of which must match
+    pub channel: Vec<String>,
+    /// Cleaned payees, e.g. `INTERAC ETRNSFR SENT BROTHER`, any of which must match ignoring
+    /// case and spacing. See `Description::parse`.
+    pub payee: Vec<String>,
+    pub transaction_type: Option<TransactionType>,
+    /// Inclusive bounds on the signed amount, debits being negative. Amounts in another currency
+    /// never match.
+    pub min_amount: Option<Money>,
+    pub max_amount: Option<Money>,
+}
+
+impl Rule {
+    pub fn matches(&self, data: &Data) -> bool {
+        let description = data.description.to_lowercase();
+        let contains = self.description_contains.is_empty()
+            || self
+                .description_contains
+                .iter()
+                .any(|needle| description.contains(&needle.to_lowercase()));
+        let regex = self
+            .description_regex
+            .as_ref()
+            .is_none_or(|regex| regex.is_match(&data.description));
+        let channel = self.channel.is_empty()
+            || data.channel_code().is_some_and(|code| {
+                self.channel
+                    .iter()
+                    .any(|channel| channel.eq_ignore_ascii_case(code))
+            });
+        let payee = self.payee.is_empty() || {
+            let description = Description::parse(&data.description);
+            self.payee
+                .iter()
+                .any(|payee| same_payee(payee, &description.payee))
+        };
+        let transaction_type = self
+            .transaction_type
+            .is_none_or(|transaction_type| transaction_type == data.transaction_type);
+        let min_amount = self.min_amount.is_none_or(|min| data.amount >= min);
+        let max_amount = self.max_amount.is_none_or(|max| data.amount <= max);
+
+        contains && regex && channel && payee && transaction_type && min_amount && max_amount
+    }
+}
+
+/// Which rule categorized a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuleMatch<'a> {
+    /// Position of the rule in the rules file
+    pub index: usize,
+    pub name: &'a str,
+    pub category: &'a TransactionCategory,
+}
+
+/// Assigns categories to transactions with an ordered list of rules, the first matching rule wins
+///
+/// Rules are read from a TOML file:
+///
+/// ```toml
+/// [[rule]]
+/// name = "mortgage"
+/// category = "housing.mortgage"
+/// channel = ["DS"]
+/// description_contains = ["MTG/HYP"]
+///
+/// [[rule]]
+/// name = "dividends"
+/// category = "other"
+/// payee = ["LIFESTYL MSP/DIV"]
+///
+/// [[rule]]
+/// name = "large transfers"
+/// category = "account_transfers"
+/// description_regex = "^\\[CW\\] ?TF \\d+"
+/// max_amount = "-1000.00"
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Categorizer {
+    rules: Vec<Rule>,
+}
+
+impl Categorizer {
+    pub fn new(rules: Vec<Rule>) -> Categorizer {
+        Categorizer { rules }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Categorizer, RulesError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|source| RulesError::Io {
+            file: path.display().to_string(),
+            source,
+        })?;
+        Categorizer::from_toml(&text)
+    }
+
+    pub fn from_toml(text: &str) -> Result<Categorizer, RulesError> {
+        let file: RulesFile = toml::from_str(text).map_err(RulesError::Toml)?;
+        let rules = file
+            .rule
+            .into_iter()
+            .map(Categorizer::compile)
+            .collect::<Result<_, _>>()?;
+        Ok(Categorizer::new(rules))
+    }
+
+    fn compile(config: RuleConfig) -> Result<Rule, RulesError> {
+        let currency = config.currency.unwrap_or(Currency::CAD);
+        let amount = |text: Option<String>| {
+            text.map(|text| Money::parse(&text, currency))
+                .transpose()
+                .map_err(|source| RulesError::InvalidAmount {
+                    rule: config.name.clone(),
+                    source,
+                })
+        };
+        let min_amount = amount(config.min_amount.clone())?;
+        let max_amount = amount(config.max_amount.clone())?;
+        let description_regex = config
+            .description_regex
+            .as_deref()
+            .map(|pattern| RegexBuilder::new(pattern).case_insensitive(true).build())
+            .transpose()
+            .map_err(|source| RulesError::Regex {
+                rule: config.name.clone(),
+                source,
+            })?;
+
+        Ok(Rule {
+            name: config.name,
+            category: config.category,
+            description_contains: config.description_contains,
+            description_regex,
+            channel: config.channel,
+            payee: config.payee,
+            transaction_type: config.transaction_type,
+            min_amount,
+            max_amount,
+        })
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Checks that every rule assigns a category of `tree`
+    pub fn validate(&self, tree: &CategoryTree) -> Result<(), RulesError> {
+        match self
+            .rules
+            .iter()
+            .find(|rule| !tree.contains(&rule.category))
+        {
+            Some(rule) => Err(RulesError::UnknownCategory {
+                rule: rule.name.clone(),
+                category: rule.category.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// The first rule matching `data`
+    pub fn find(&self, data: &Data) -> Option<RuleMatch<'_>> {
+        self.rules
+            .iter()
+            .enumerate()
+            .find(|(_, rule)| rule.matches(data))
+            .map(|(index, rule)| RuleMatch {
+                index,
+                name: &rule.name,
+                category: &rule.category,
+            })
+    }
+
+    /// Sets `category` and `categorized_by` on every row a rule matches and returns how many
+    /// matched. Rows no rule matches are left untouched.
+    pub fn categorize(&self, rows: &mut [Data]) -> usize {
+        let mut matched = 0;
+        for data in rows.iter_mut() {
+            if let Some(rule) = self.find(data) {
+                data.category = rule.category.clone();
+                data.categorized_by = Some(rule.name.to_string());
+                matched += 1;
+            }
+        }
+        matched
+    }
+}
+
+#[derive(Debug)]
+pub enum RulesError {
+    Io {
+        file: String,
+        source: io::Error,
+    },
+    Toml(toml::de::Error),
+    Regex {
+        rule: String,
+        source: regex::Error,
+    },
+    InvalidAmount {
+        rule: String,
+        source: MoneyError,
+    },
+    UnknownCategory {
+        rule: String,
+        category: TransactionCategory,
+    },
+}
+
+impl fmt::Display for RulesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RulesError::Io { file, source } => write!(f, "{file}: {source}"),
+            RulesError::Toml(source) => write!(f, "invalid rules file: {source}"),
+            RulesError::Regex { rule, source } => write!(f, "rule {rule:?}: {source}"),
+            RulesError::InvalidAmount { rule, source } => write!(f, "rule {rule:?}: {source}"),
+            RulesError::UnknownCategory { rule, category } => {
+                write!(f, "rule {rule:?}: unknown category {category:?}")
+            }
+        }
+    }
+}
+
+impl Error for RulesError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RulesError::Io { source, .. } => Some(source),
+            RulesError::Toml(source) => Some(source),
+            RulesError::Regex { source, .. } => Some(source),
+            RulesError::InvalidAmount { source, .. } => Some(source),
+            RulesError::UnknownCategory { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./tests/test.rs"]
+mod test;
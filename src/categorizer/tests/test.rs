@@ -0,0 +1,168 @@
+use super::*;
+use rstest::rstest;
+
+use crate::csv_parser::parse_csv;
+
+const TEST_FILE_PATH: &str = "src/csv_parser/tests/test_statement.csv";
+
+const RULES_TOML: &str = r#"
+[[rule]]
+name = "mortgage"
+category = "Bills"
+channel = ["DS"]
+description_contains = ["mtg/hyp"]
+
+[[rule]]
+name = "large transfers"
+category = "account_transfers"
+description_regex = "^\\[CW\\] ?TF \\d+"
+max_amount = "-1000.00"
+
+[[rule]]
+name = "service charges"
+category = "Bills"
+channel = ["SC"]
+transaction_type = "DEBIT"
+
+[[rule]]
+name = "pre-authorized debits"
+category = "Utilities"
+channel = ["ds", "cw"]
+"#;
+
+#[test]
+fn test_categorize_sample_statement() {
+    let categorizer = Categorizer::from_toml(RULES_TOML).unwrap();
+    let mut rows = parse_csv(TEST_FILE_PATH).unwrap();
+
+    let matched = categorizer.categorize(&mut rows);
+
+    let assigned: Vec<(&str, TransactionCategory, Option<&str>)> = rows
+        .iter()
+        .map(|data| {
+            (
+                data.description.as_str(),
+                data.category.clone(),
+                data.categorized_by.as_deref(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        vec![
+            (
+                "[DS]BANK         MTG/HYP",
+                TransactionCategory::BILLS,
+                Some("mortgage")
+            ),
+            (
+                "[DS]STRATA FEE",
+                TransactionCategory::UTILITIES,
+                Some("pre-authorized debits")
+            ),
+            (
+                "[CW]INTERAC ETRNSFR SENT     BROTHER",
+                TransactionCategory::UTILITIES,
+                Some("pre-authorized debits")
+            ),
+            ("[DN]LIFESTYL MSP/DIV", TransactionCategory::OTHER, None),
+            ("[IB] SHAUGHNES", TransactionCategory::OTHER, None),
+            (
+                "[CW] TF 000123456789",
+                TransactionCategory::ACCOUNT_TRANSFERS,
+                Some("large transfers")
+            ),
+            (
+                "[CW]CITY TAX",
+                TransactionCategory::UTILITIES,
+                Some("pre-authorized debits")
+            ),
+            (
+                "[SC]PREMIUM PLAN",
+                TransactionCategory::BILLS,
+                Some("service charges")
+            ),
+            ("[SC]FULL PLAN FEE REBATE", TransactionCategory::OTHER, None),
+        ],
+        assigned
+    );
+    assert_eq!(6, matched);
+}
+
+#[test]
+fn test_find_reports_first_matching_rule() {
+    let categorizer = Categorizer::from_toml(RULES_TOML).unwrap();
+    let rows = parse_csv(TEST_FILE_PATH).unwrap();
+
+    assert_eq!(
+        Some(RuleMatch {
+            index: 0,
+            name: "mortgage",
+            category: &TransactionCategory::BILLS
+        }),
+        categorizer.find(&rows[0])
+    );
+}
+
+#[test]
+fn test_payee_ignores_case_and_spacing() {
+    let categorizer = Categorizer::from_toml(
+        "[[rule]]\nname = \"brother\"\ncategory = \"account_transfers\"\n\
+         payee = [\"interac etrnsfr sent brother\"]\n",
+    )
+    .unwrap();
+    let rows = parse_csv(TEST_FILE_PATH).unwrap();
+
+    let matched: Vec<&str> = rows
+        .iter()
+        .filter(|data| categorizer.find(data).is_some())
+        .map(|data| data.description.as_str())
+        .collect();
+
+    assert_eq!(vec!["[CW]INTERAC ETRNSFR SENT     BROTHER"], matched);
+}
+
+#[rstest]
+#[case("-1500.00", true)]
+#[case("-1000.00", true)]
+#[case("-999.99", false)]
+fn test_amount_bounds(#[case] amount: &str, #[case] expected: bool) {
+    let categorizer = Categorizer::from_toml(RULES_TOML).unwrap();
+    let mut data = parse_csv(TEST_FILE_PATH).unwrap().remove(5);
+    data.amount = Money::parse(amount, Currency::CAD).unwrap();
+
+    assert_eq!(expected, categorizer.rules()[1].matches(&data));
+}
+
+#[test]
+fn test_invalid_rules() {
+    let bad_regex = "[[rule]]\nname = \"bad\"\ncategory = \"Food\"\ndescription_regex = \"(\"";
+    let bad_category = "[[rule]]\nname = \"bad\"\ncategory = [\"Food\"]";
+    let bad_amount = "[[rule]]\nname = \"bad\"\ncategory = \"Food\"\nmin_amount = \"ten\"";
+
+    assert!(matches!(
+        Categorizer::from_toml(bad_regex),
+        Err(RulesError::Regex { rule, .. }) if rule == "bad"
+    ));
+    assert!(matches!(
+        Categorizer::from_toml(bad_category),
+        Err(RulesError::Toml(_))
+    ));
+    assert!(matches!(
+        Categorizer::from_toml(bad_amount),
+        Err(RulesError::InvalidAmount { .. })
+    ));
+}
+
+#[test]
+fn test_validate_against_category_tree() {
+    let categorizer = Categorizer::from_toml(RULES_TOML).unwrap();
+    assert!(categorizer.validate(&CategoryTree::defaults()).is_ok());
+
+    let groceries = "[[rule]]\nname = \"groceries\"\ncategory = \"food.groceries\"";
+    let categorizer = Categorizer::from_toml(groceries).unwrap();
+    assert!(matches!(
+        categorizer.validate(&CategoryTree::defaults()),
+        Err(RulesError::UnknownCategory { rule, category })
+            if rule == "groceries" && category.id() == "food.groceries"
+    ));
+}
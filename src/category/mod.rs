@@ -0,0 +1,320 @@
+use std::{borrow::Cow, collections::BTreeMap, error::Error, fmt, fs, io, path::Path};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::money::{Money, MoneyError};
+
+/// Stable identifier of a category in a `CategoryTree`, e.g. `housing` or `housing.mortgage`
+///
+/// The categories that used to be a fixed enum are kept as constants and are always part of a
+/// tree. Their old names (`Food`, `AccountTransfers`, ...) are still accepted when reading files.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(transparent)]
+pub struct TransactionCategory(Cow<'static, str>);
+
+impl TransactionCategory {
+    pub const FOOD: TransactionCategory = TransactionCategory(Cow::Borrowed("food"));
+    pub const UTILITIES: TransactionCategory = TransactionCategory(Cow::Borrowed("utilities"));
+    pub const BILLS: TransactionCategory = TransactionCategory(Cow::Borrowed("bills"));
+    pub const ENTERTAINMENT: TransactionCategory =
+        TransactionCategory(Cow::Borrowed("entertainment"));
+    pub const TRANSPORTATION: TransactionCategory =
+        TransactionCategory(Cow::Borrowed("transportation"));
+    pub const HEALTHCARE: TransactionCategory = TransactionCategory(Cow::Borrowed("healthcare"));
+    pub const EDUCATION: TransactionCategory = TransactionCategory(Cow::Borrowed("education"));
+    pub const ACCOUNT_TRANSFERS: TransactionCategory =
+        TransactionCategory(Cow::Borrowed("account_transfers"));
+    pub const OTHER: TransactionCategory = TransactionCategory(Cow::Borrowed("other"));
+
+    /// The former enum variants with their display names
+    const DEFAULTS: [(TransactionCategory, &'static str, &'static str); 9] = [
+        (TransactionCategory::FOOD, "Food", "Food"),
+        (TransactionCategory::UTILITIES, "Utilities", "Utilities"),
+        (TransactionCategory::BILLS, "Bills", "Bills"),
+        (
+            TransactionCategory::ENTERTAINMENT,
+            "Entertainment",
+            "Entertainment",
+        ),
+        (
+            TransactionCategory::TRANSPORTATION,
+            "Transportation",
+            "Transportation",
+        ),
+        (TransactionCategory::HEALTHCARE, "Healthcare", "Healthcare"),
+        (TransactionCategory::EDUCATION, "Education", "Education"),
+        (
+            TransactionCategory::ACCOUNT_TRANSFERS,
+            "AccountTransfers",
+            "Account Transfers",
+        ),
+        (TransactionCategory::OTHER, "Other", "Other"),
+    ];
+
+    /// Builds an identifier, mapping the former enum variant names to their ids
+    pub fn new(id: &str) -> TransactionCategory {
+        let id = id.trim();
+        TransactionCategory::DEFAULTS
+            .into_iter()
+            .find(|(_, legacy, _)| *legacy == id)
+            .map(|(category, _, _)| category)
+            .unwrap_or_else(|| TransactionCategory(Cow::Owned(id.to_string())))
+    }
+
+    pub fn id(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Default for TransactionCategory {
+    fn default() -> TransactionCategory {
+        TransactionCategory::OTHER
+    }
+}
+
+impl fmt::Display for TransactionCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.id())
+    }
+}
+
+impl<'de> Deserialize<'de> for TransactionCategory {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<TransactionCategory, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        Ok(TransactionCategory::new(&id))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Category {
+    pub id: TransactionCategory,
+    pub name: String,
+    #[serde(default)]
+    pub parent: Option<TransactionCategory>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CategoryFile {
+    #[serde(default)]
+    category: Vec<Category>,
+}
+
+/// The categories a household budgets with, as a forest of parent/child relations
+///
+/// The default categories are always present, a TOML file can rename them and add more:
+///
+/// ```toml
+/// [[category]]
+/// id = "housing"
+/// name = "Housing"
+///
+/// [[category]]
+/// id = "housing.mortgage"
+/// name = "Mortgage"
+/// parent = "housing"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CategoryTree {
+    categories: BTreeMap<TransactionCategory, Category>,
+}
+
+impl Default for CategoryTree {
+    fn default() -> CategoryTree {
+        CategoryTree::defaults()
+    }
+}
+
+impl CategoryTree {
+    /// A flat tree of the former `TransactionCategory` enum variants
+    pub fn defaults() -> CategoryTree {
+        let categories = TransactionCategory::DEFAULTS
+            .into_iter()
+            .map(|(id, _, name)| {
+                let category = Category {
+                    id: id.clone(),
+                    name: name.to_string(),
+                    parent: None,
+                };
+                (id, category)
+            })
+            .collect();
+        CategoryTree { categories }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<CategoryTree, CategoryError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|source| CategoryError::Io {
+            file: path.display().to_string(),
+            source,
+        })?;
+        CategoryTree::from_toml(&text)
+    }
+
+    pub fn from_toml(text: &str) -> Result<CategoryTree, CategoryError> {
+        let file: CategoryFile = toml::from_str(text).map_err(CategoryError::Toml)?;
+        let mut tree = CategoryTree::defaults();
+        let mut seen = Vec::new();
+        for category in file.category {
+            if category.id.id().is_empty() || category.id.id().contains(char::is_whitespace) {
+                return Err(CategoryError::InvalidId(category.id));
+            }
+            if seen.contains(&category.id) {
+                return Err(CategoryError::Duplicate(category.id));
+            }
+            seen.push(category.id.clone());
+            tree.categories.insert(category.id.clone(), category);
+        }
+        tree.validate()?;
+        Ok(tree)
+    }
+
+    fn validate(&self) -> Result<(), CategoryError> {
+        for category in self.categories.values() {
+            if let Some(parent) = &category.parent {
+                if !self.contains(parent) {
+                    return Err(CategoryError::UnknownParent {
+                        id: category.id.clone(),
+                        parent: parent.clone(),
+                    });
+                }
+            }
+            if self
+                .ancestors(&category.id)
+                .skip(1)
+                .take(self.categories.len())
+                .any(|ancestor| ancestor.id == category.id)
+            {
+                return Err(CategoryError::Cycle(category.id.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn contains(&self, id: &TransactionCategory) -> bool {
+        self.categories.contains_key(id)
+    }
+
+    pub fn get(&self, id: &TransactionCategory) -> Option<&Category> {
+        self.categories.get(id)
+    }
+
+    /// Every category, ordered by id
+    pub fn iter(&self) -> impl Iterator<Item = &Category> {
+        self.categories.values()
+    }
+
+    /// Categories without a parent
+    pub fn roots(&self) -> impl Iterator<Item = &Category> {
+        self.iter().filter(|category| category.parent.is_none())
+    }
+
+    pub fn children(&self, id: &TransactionCategory) -> impl Iterator<Item = &Category> {
+        let id = id.clone();
+        self.iter()
+            .filter(move |category| category.parent.as_ref() == Some(&id))
+    }
+
+    /// The category itself followed by its parent, grandparent, ... up to its root
+    pub fn ancestors<'a>(
+        &'a self,
+        id: &TransactionCategory,
+    ) -> impl Iterator<Item = &'a Category> + 'a {
+        std::iter::successors(self.get(id), move |category| {
+            category.parent.as_ref().and_then(|parent| self.get(parent))
+        })
+    }
+
+    /// Whether `id` is `ancestor` or one of its descendants
+    pub fn is_within(&self, id: &TransactionCategory, ancestor: &TransactionCategory) -> bool {
+        self.ancestors(id).any(|category| &category.id == ancestor)
+    }
+
+    /// The display path of a category, e.g. `Housing > Mortgage`. Unknown ids are shown as is.
+    pub fn path(&self, id: &TransactionCategory) -> String {
+        let mut names: Vec<&str> = self
+            .ancestors(id)
+            .map(|category| category.name.as_str())
+            .collect();
+        if names.is_empty() {
+            return id.to_string();
+        }
+        names.reverse();
+        names.join(" > ")
+    }
+
+    /// Adds every category's amount to itself and all of its ancestors
+    ///
+    /// The result has an entry for each category with a non-empty subtree. Amounts filed under an
+    /// id missing from the tree are kept under that id.
+    pub fn rollup<'a, I>(
+        &self,
+        amounts: I,
+    ) -> Result<BTreeMap<TransactionCategory, Money>, MoneyError>
+    where
+        I: IntoIterator<Item = (&'a TransactionCategory, Money)>,
+    {
+        let mut totals: BTreeMap<TransactionCategory, Money> = BTreeMap::new();
+        for (id, amount) in amounts {
+            let mut ids: Vec<&TransactionCategory> =
+                self.ancestors(id).map(|category| &category.id).collect();
+            if ids.is_empty() {
+                ids.push(id);
+            }
+            for id in ids {
+                let total = match totals.get(id) {
+                    Some(total) => total.checked_add(amount)?,
+                    None => amount,
+                };
+                totals.insert(id.clone(), total);
+            }
+        }
+        Ok(totals)
+    }
+}
+
+#[derive(Debug)]
+pub enum CategoryError {
+    Io {
+        file: String,
+        source: io::Error,
+    },
+    Toml(toml::de::Error),
+    InvalidId(TransactionCategory),
+    Duplicate(TransactionCategory),
+    UnknownParent {
+        id: TransactionCategory,
+        parent: TransactionCategory,
+    },
+    Cycle(TransactionCategory),
+}
+
+impl fmt::Display for CategoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CategoryError::Io { file, source } => write!(f, "{file}: {source}"),
+            CategoryError::Toml(source) => write!(f, "invalid categories file: {source}"),
+            CategoryError::InvalidId(id) => write!(f, "invalid category id {id:?}"),
+            CategoryError::Duplicate(id) => write!(f, "category {id:?} is defined twice"),
+            CategoryError::UnknownParent { id, parent } => {
+                write!(f, "category {id:?} has an unknown parent {parent:?}")
+            }
+            CategoryError::Cycle(id) => write!(f, "category {id:?} is its own ancestor"),
+        }
+    }
+}
+
+impl Error for CategoryError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CategoryError::Io { source, .. } => Some(source),
+            CategoryError::Toml(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./tests/test.rs"]
+mod test;
@@ -0,0 +1,101 @@
+use super::*;
+use rstest::rstest;
+
+use crate::money::Currency;
+
+const CATEGORIES_TOML: &str = r#"
+[[category]]
+id = "housing"
+name = "Housing"
+
+[[category]]
+id = "housing.mortgage"
+name = "Mortgage"
+parent = "housing"
+
+[[category]]
+id = "housing.strata"
+name = "Strata"
+parent = "housing"
+
+[[category]]
+id = "bills"
+name = "Bills & Fees"
+"#;
+
+fn housing() -> TransactionCategory {
+    TransactionCategory::new("housing")
+}
+
+fn mortgage() -> TransactionCategory {
+    TransactionCategory::new("housing.mortgage")
+}
+
+fn strata() -> TransactionCategory {
+    TransactionCategory::new("housing.strata")
+}
+
+#[rstest]
+#[case("Food", TransactionCategory::FOOD)]
+#[case("AccountTransfers", TransactionCategory::ACCOUNT_TRANSFERS)]
+#[case("account_transfers", TransactionCategory::ACCOUNT_TRANSFERS)]
+#[case("Other", TransactionCategory::OTHER)]
+fn test_legacy_names(#[case] id: &str, #[case] expected: TransactionCategory) {
+    assert_eq!(expected, TransactionCategory::new(id));
+}
+
+#[test]
+fn test_from_toml_keeps_defaults() {
+    let tree = CategoryTree::from_toml(CATEGORIES_TOML).unwrap();
+
+    assert!(tree.contains(&TransactionCategory::FOOD));
+    assert_eq!(
+        "Bills & Fees",
+        tree.get(&TransactionCategory::BILLS).unwrap().name
+    );
+    assert_eq!("Housing > Mortgage", tree.path(&mortgage()));
+    assert_eq!("unknown", tree.path(&TransactionCategory::new("unknown")));
+
+    let children: Vec<&str> = tree
+        .children(&housing())
+        .map(|category| category.id.id())
+        .collect();
+    assert_eq!(vec!["housing.mortgage", "housing.strata"], children);
+    assert!(tree.is_within(&strata(), &housing()));
+    assert!(!tree.is_within(&housing(), &strata()));
+}
+
+#[test]
+fn test_rollup() {
+    let tree = CategoryTree::from_toml(CATEGORIES_TOML).unwrap();
+    let amounts = [
+        (&mortgage(), Money::new(-137447, Currency::CAD)),
+        (&strata(), Money::new(-23197, Currency::CAD)),
+        (&TransactionCategory::FOOD, Money::new(-4512, Currency::CAD)),
+    ];
+
+    let totals = tree.rollup(amounts).unwrap();
+
+    assert_eq!(
+        Some(&Money::new(-160644, Currency::CAD)),
+        totals.get(&housing())
+    );
+    assert_eq!(
+        Some(&Money::new(-137447, Currency::CAD)),
+        totals.get(&mortgage())
+    );
+    assert_eq!(
+        Some(&Money::new(-4512, Currency::CAD)),
+        totals.get(&TransactionCategory::FOOD)
+    );
+    assert_eq!(None, totals.get(&TransactionCategory::BILLS));
+}
+
+#[rstest]
+#[case("[[category]]\nid = \"a\"\nname = \"A\"\nparent = \"missing\"")]
+#[case("[[category]]\nid = \"a\"\nname = \"A\"\nparent = \"b\"\n[[category]]\nid = \"b\"\nname = \"B\"\nparent = \"a\"")]
+#[case("[[category]]\nid = \"a\"\nname = \"A\"\n[[category]]\nid = \"a\"\nname = \"A again\"")]
+#[case("[[category]]\nid = \"a b\"\nname = \"A\"")]
+fn test_invalid_trees(#[case] toml: &str) {
+    assert!(CategoryTree::from_toml(toml).is_err());
+}
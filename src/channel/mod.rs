@@ -0,0 +1,206 @@
+use std::{collections::HashMap, error::Error, fmt, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::csv_parser::Data;
+
+/// How a transaction was made, decoded from the channel code its description starts with
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionChannel {
+    PreAuthorizedDebit,
+    WebBanking,
+    Deposit,
+    Branch,
+    ServiceCharge,
+    Atm,
+    PointOfSale,
+    Cheque,
+    Transfer,
+    Other,
+}
+
+impl TransactionChannel {
+    pub const ALL: [TransactionChannel; 10] = [
+        TransactionChannel::PreAuthorizedDebit,
+        TransactionChannel::WebBanking,
+        TransactionChannel::Deposit,
+        TransactionChannel::Branch,
+        TransactionChannel::ServiceCharge,
+        TransactionChannel::Atm,
+        TransactionChannel::PointOfSale,
+        TransactionChannel::Cheque,
+        TransactionChannel::Transfer,
+        TransactionChannel::Other,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionChannel::PreAuthorizedDebit => "pre_authorized_debit",
+            TransactionChannel::WebBanking => "web_banking",
+            TransactionChannel::Deposit => "deposit",
+            TransactionChannel::Branch => "branch",
+            TransactionChannel::ServiceCharge => "service_charge",
+            TransactionChannel::Atm => "atm",
+            TransactionChannel::PointOfSale => "point_of_sale",
+            TransactionChannel::Cheque => "cheque",
+            TransactionChannel::Transfer => "transfer",
+            TransactionChannel::Other => "other",
+        }
+    }
+
+    pub fn parse(str: &str) -> Option<TransactionChannel> {
+        TransactionChannel::ALL
+            .into_iter()
+            .find(|channel| channel.as_str().eq_ignore_ascii_case(str.trim()))
+    }
+}
+
+impl fmt::Display for TransactionChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The bracketed code a description starts with, e.g. `DS` for `[DS]STRATA FEE`
+pub fn channel_code(description: &str) -> Option<&str> {
+    let rest = description.trim_start().strip_prefix('[')?;
+    let (code, _) = rest.split_once(']')?;
+    Some(code.trim())
+}
+
+/// Channel codes of the sample statements and what they mean
+const DEFAULT_CODES: [(&str, TransactionChannel); 5] = [
+    ("DS", TransactionChannel::PreAuthorizedDebit),
+    ("CW", TransactionChannel::WebBanking),
+    ("DN", TransactionChannel::Deposit),
+    ("IB", TransactionChannel::Branch),
+    ("SC", TransactionChannel::ServiceCharge),
+];
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChannelFile {
+    #[serde(default)]
+    codes: HashMap<String, TransactionChannel>,
+}
+
+/// Which channel each code stands for, e.g. `DS` for `[DS]STRATA FEE` is a pre-authorized debit
+///
+/// Starts from the codes of the sample statements. A TOML file can add codes or change what they
+/// mean:
+///
+/// ```toml
+/// [codes]
+/// AT = "atm"
+/// IB = "web_banking"
+/// ```
+///
+/// Codes are matched ignoring case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelTable {
+    codes: HashMap<String, TransactionChannel>,
+}
+
+impl Default for ChannelTable {
+    fn default() -> ChannelTable {
+        ChannelTable::defaults()
+    }
+}
+
+impl ChannelTable {
+    pub fn defaults() -> ChannelTable {
+        let mut table = ChannelTable {
+            codes: HashMap::new(),
+        };
+        for (code, channel) in DEFAULT_CODES {
+            table.insert(code, channel);
+        }
+        table
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<ChannelTable, ChannelError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|source| ChannelError::Io {
+            file: path.display().to_string(),
+            source,
+        })?;
+        ChannelTable::from_toml(&text)
+    }
+
+    /// The default codes with those of `text` added
+    pub fn from_toml(text: &str) -> Result<ChannelTable, ChannelError> {
+        let file: ChannelFile = toml::from_str(text).map_err(ChannelError::Toml)?;
+        let mut table = ChannelTable::defaults();
+        for (code, channel) in file.codes {
+            table.insert(&code, channel);
+        }
+        Ok(table)
+    }
+
+    pub fn insert(&mut self, code: &str, channel: TransactionChannel) {
+        self.codes.insert(code.trim().to_uppercase(), channel);
+    }
+
+    /// The channel of a code, `None` for codes the table doesn't know
+    pub fn get(&self, code: &str) -> Option<TransactionChannel> {
+        self.codes.get(&code.trim().to_uppercase()).copied()
+    }
+
+    /// The channel of a transaction, from the code its description starts with
+    pub fn channel(&self, data: &Data) -> Option<TransactionChannel> {
+        self.channel_of(&data.description)
+    }
+
+    /// The channel of the code `description` starts with, e.g. `[DS]STRATA FEE`
+    pub fn channel_of(&self, description: &str) -> Option<TransactionChannel> {
+        self.get(channel_code(description)?)
+    }
+
+    /// Sets `channel` on every row
+    pub fn apply(&self, rows: &mut [Data]) {
+        for data in rows.iter_mut() {
+            data.channel = self.channel(data);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ChannelError {
+    Io { file: String, source: io::Error },
+    Toml(toml::de::Error),
+}
+
+impl fmt::Display for ChannelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChannelError::Io { file, source } => write!(f, "{file}: {source}"),
+            ChannelError::Toml(source) => write!(f, "invalid channel codes file: {source}"),
+        }
+    }
+}
+
+impl Error for ChannelError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ChannelError::Io { source, .. } => Some(source),
+            ChannelError::Toml(source) => Some(source),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./tests/test.rs"]
+mod test;
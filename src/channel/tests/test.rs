@@ -0,0 +1,51 @@
+use super::*;
+use rstest::rstest;
+
+use crate::csv_parser::parse_csv;
+
+const TEST_FILE_PATH: &str = "src/csv_parser/tests/test_statement.csv";
+
+#[test]
+fn test_apply_default_codes() {
+    let mut rows = parse_csv(TEST_FILE_PATH).unwrap();
+
+    ChannelTable::defaults().apply(&mut rows);
+
+    let channels: Vec<Option<TransactionChannel>> = rows.iter().map(|data| data.channel).collect();
+    assert_eq!(
+        vec![
+            Some(TransactionChannel::PreAuthorizedDebit),
+            Some(TransactionChannel::PreAuthorizedDebit),
+            Some(TransactionChannel::WebBanking),
+            Some(TransactionChannel::Deposit),
+            Some(TransactionChannel::Branch),
+            Some(TransactionChannel::WebBanking),
+            Some(TransactionChannel::WebBanking),
+            Some(TransactionChannel::ServiceCharge),
+            Some(TransactionChannel::ServiceCharge),
+        ],
+        channels
+    );
+}
+
+#[test]
+fn test_codes_file_extends_defaults() {
+    let table = ChannelTable::from_toml("[codes]\nat = \"atm\"\nIB = \"web_banking\"\n").unwrap();
+
+    assert_eq!(Some(TransactionChannel::Atm), table.get("AT"));
+    assert_eq!(Some(TransactionChannel::WebBanking), table.get("ib"));
+    assert_eq!(Some(TransactionChannel::Deposit), table.get("DN"));
+    assert_eq!(None, table.get("XX"));
+    assert!(matches!(
+        ChannelTable::from_toml("[codes]\nAT = \"teller\"\n"),
+        Err(ChannelError::Toml(_))
+    ));
+}
+
+#[rstest]
+#[case("service_charge", Some(TransactionChannel::ServiceCharge))]
+#[case(" ATM ", Some(TransactionChannel::Atm))]
+#[case("teller", None)]
+fn test_parse(#[case] text: &str, #[case] expected: Option<TransactionChannel>) {
+    assert_eq!(expected, TransactionChannel::parse(text));
+}
@@ -0,0 +1,1568 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    error::Error,
+    fmt, fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use chrono::{Datelike, Local, NaiveDate, NaiveDateTime};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+
+use crate::account::{mask_number, Account, AccountType};
+use crate::budget::Budgets;
+use crate::camt::{is_camt_file, parse_camt, CAMT_FORMAT};
+use crate::categorizer::Categorizer;
+use crate::category::{CategoryTree, TransactionCategory};
+use crate::channel::ChannelTable;
+use crate::csv_parser::{
+    detect_format, parse_csv_with, Data, ParseMode, ParseOutput, Split, StatementFormat,
+    TransactionChannel, TransactionType,
+};
+use crate::forecast::{self, ForecastDay, Schedule};
+use crate::journal::{write_journal, AccountMap, JournalFormat};
+use crate::money::{Currency, Money};
+use crate::ofx::{is_ofx_file, parse_ofx, OFX_FORMAT};
+use crate::output::{render, OutputFormat, Tabular};
+use crate::payee::Aliases;
+use crate::qif::{is_qif_file, read_qif, write_qif, DateOrder, QifTransaction, QIF_FORMAT};
+use crate::reconcile::{self, Checkpoint};
+use crate::recurring::Detector;
+use crate::report::{
+    account_totals, channel_totals, monthly_report, payee_totals, tag_totals, CategoryRow,
+    MonthRow, MonthlyReport,
+};
+use crate::store::{Store, StoreError, StoredTransaction, TransactionFilter};
+use crate::tax::Schedules;
+use crate::transfer::{Matcher, TransferStatus, TRANSFER_RULE};
+
+/// Track spending from bank statement exports
+#[derive(Debug, Parser)]
+#[command(name = "finance-tracker", version)]
+pub struct Cli {
+    /// SQLite database holding the imported transactions
+    #[arg(long, global = true, default_value = "finance.db")]
+    pub db: PathBuf,
+    /// TOML file defining the category tree
+    #[arg(long, global = true)]
+    pub categories: Option<PathBuf>,
+    /// TOML file of names to show instead of the payees in descriptions
+    #[arg(long, global = true)]
+    pub aliases: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Add and list accounts
+    #[command(subcommand)]
+    Account(AccountCommand),
+    /// Import statement files into the database, skipping transactions already imported
+    Import(ImportArgs),
+    /// List stored transactions
+    List(ListArgs),
+    /// Summarize stored transactions
+    Report(ReportArgs),
+    /// Assign categories to stored transactions with a rules file
+    Categorize(CategorizeArgs),
+    /// Divide a transaction between several categories
+    Split(SplitArgs),
+    /// Add tags to a transaction, or remove them
+    Tag(TagArgs),
+    /// List tags with the number of transactions carrying them
+    Tags(TagsArgs),
+    /// Set the note of a transaction, or remove it when no text is given
+    Note(NoteArgs),
+    /// Link a local file, e.g. a receipt, to a transaction
+    Attach(AttachArgs),
+    /// Compare spending in the current period to the limits of a budgets file
+    Budget(BudgetArgs),
+    /// Year-end totals of deductible expenses per line of a tax schedules file
+    Tax(TaxArgs),
+    /// List recurring transactions and subscriptions with their next due date
+    Recurring(RecurringArgs),
+    /// Project the balance of an account day by day
+    Forecast(ForecastArgs),
+    /// Find and review transfers between accounts
+    #[command(subcommand)]
+    Transfers(TransfersCommand),
+    /// Show the running balance of an account and check it against known balances
+    Reconcile(ReconcileArgs),
+    /// Write stored transactions in a format other finance programs import
+    Export(ExportArgs),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AccountCommand {
+    /// Add an account that statements can be imported into
+    Add(AccountAddArgs),
+    /// List accounts with their current balance
+    List(AccountListArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct AccountAddArgs {
+    /// Short name of the account, e.g. chequing
+    pub id: String,
+    /// Card or account number as it appears in statements, only the last digits are kept
+    #[arg(long)]
+    pub number: Option<String>,
+    #[arg(long)]
+    pub institution: Option<String>,
+    #[arg(long = "type", value_enum, default_value_t)]
+    pub account_type: AccountType,
+    #[arg(long, default_value = "CAD")]
+    pub currency: String,
+    /// Balance before the first imported transaction
+    #[arg(long, allow_hyphen_values = true, default_value = "0")]
+    pub opening_balance: String,
+}
+
+#[derive(Debug, Args)]
+pub struct AccountListArgs {
+    #[arg(long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+#[derive(Debug, Args)]
+pub struct ImportArgs {
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+    /// Account the statements belong to, created if needed. By default every row goes to the
+    /// account with its card or account number.
+    #[arg(long)]
+    pub account: Option<String>,
+    /// Statement format to use instead of detecting it: a CSV format, `ofx` for OFX and QFX,
+    /// `camt.053` for ISO 20022 XML or `qif`
+    #[arg(long)]
+    pub format: Option<String>,
+    /// TOML file with additional statement formats
+    #[arg(long)]
+    pub formats: Option<PathBuf>,
+    /// Categorization rules applied to the new transactions
+    #[arg(long)]
+    pub rules: Option<PathBuf>,
+    /// TOML file with additional channel codes
+    #[arg(long)]
+    pub channels: Option<PathBuf>,
+    /// Skip malformed rows instead of rejecting the whole file, CSV statements only
+    #[arg(long)]
+    pub lenient: bool,
+    /// Read QIF dates as day/month/year instead of month/day/year
+    #[arg(long)]
+    pub day_first: bool,
+    /// Import statements even if they are older than one already imported for the account
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TypeArg {
+    Debit,
+    Credit,
+}
+
+impl From<TypeArg> for TransactionType {
+    fn from(arg: TypeArg) -> TransactionType {
+        match arg {
+            TypeArg::Debit => TransactionType::DEBIT,
+            TypeArg::Credit => TransactionType::CREDIT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Args)]
+pub struct FilterArgs {
+    #[arg(long)]
+    pub account: Option<String>,
+    /// First date to include, e.g. 2024-06-01
+    #[arg(long)]
+    pub from: Option<NaiveDate>,
+    /// Last date to include, e.g. 2024-06-30
+    #[arg(long)]
+    pub to: Option<NaiveDate>,
+    /// Category id, its subcategories are included
+    #[arg(long)]
+    pub category: Option<String>,
+    #[arg(long = "type", value_enum)]
+    pub transaction_type: Option<TypeArg>,
+    /// Smallest signed amount to include, debits are negative
+    #[arg(long, allow_hyphen_values = true)]
+    pub min: Option<String>,
+    /// Largest signed amount to include, debits are negative
+    #[arg(long, allow_hyphen_values = true)]
+    pub max: Option<String>,
+    /// Currency of --min and --max
+    #[arg(long, default_value = "CAD")]
+    pub currency: String,
+    /// Text the description or the note must contain
+    #[arg(long)]
+    pub search: Option<String>,
+    #[arg(long)]
+    pub tag: Option<String>,
+    /// How the transaction was made, from the code its description starts with
+    #[arg(long, value_enum)]
+    pub channel: Option<TransactionChannel>,
+}
+
+impl FilterArgs {
+    fn to_filter(&self) -> Result<TransactionFilter, Box<dyn Error>> {
+        let currency = Currency::new(&self.currency)?;
+        let amount = |text: &Option<String>| {
+            text.as_deref()
+                .map(|text| Money::parse(text, currency))
+                .transpose()
+        };
+        Ok(TransactionFilter {
+            account: self.account.clone(),
+            from: self.from,
+            to: self.to,
+            category: self.category.as_deref().map(TransactionCategory::new),
+            transaction_type: self.transaction_type.map(TransactionType::from),
+            min_amount: amount(&self.min)?,
+            max_amount: amount(&self.max)?,
+            search: self.search.clone(),
+            tag: self.tag.clone(),
+            channel: self.channel,
+        })
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct ListArgs {
+    #[command(flatten)]
+    pub filter: FilterArgs,
+    #[arg(long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+#[derive(Debug, Args)]
+pub struct SplitArgs {
+    /// ID of the transaction, as shown by `list`
+    pub id: i64,
+    /// A part of the amount as CATEGORY=AMOUNT, optionally followed by =MEMO, e.g.
+    /// food=-110.00=Groceries. Can be repeated, the amounts must add up to the transaction's.
+    /// Without parts the transaction is no longer split.
+    #[arg(long = "part", allow_hyphen_values = true)]
+    pub parts: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct TagArgs {
+    /// ID of the transaction, as shown by `list`
+    pub id: i64,
+    /// Tags such as vacation-2024, made of letters, digits, '-', '_', '.' and '/'
+    #[arg(required = true)]
+    pub tags: Vec<String>,
+    /// Remove the tags instead of adding them
+    #[arg(long)]
+    pub remove: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct TagsArgs {
+    #[arg(long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+#[derive(Debug, Args)]
+pub struct NoteArgs {
+    /// ID of the transaction, as shown by `list`
+    pub id: i64,
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct AttachArgs {
+    /// ID of the transaction, as shown by `list`
+    pub id: i64,
+    /// The file is linked by its absolute path, not copied
+    pub file: PathBuf,
+    /// Unlink the file instead
+    #[arg(long)]
+    pub remove: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ReportArgs {
+    #[command(flatten)]
+    pub filter: FilterArgs,
+    #[arg(long, value_enum, default_value_t)]
+    pub by: ReportBy,
+    #[arg(long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    #[command(flatten)]
+    pub filter: FilterArgs,
+    #[arg(long, value_enum, default_value_t)]
+    pub format: ExportFormat,
+    /// File to write instead of the standard output
+    #[arg(long)]
+    pub file: Option<PathBuf>,
+    /// TOML file choosing the journal accounts of accounts and categories
+    #[arg(long)]
+    pub account_map: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ExportFormat {
+    /// Quicken interchange format, one section per account
+    #[default]
+    Qif,
+    /// Ledger journal
+    Ledger,
+    /// hledger journal
+    Hledger,
+    /// Beancount ledger
+    Beancount,
+}
+
+/// How `report` groups transactions. Amounts of a category include its subcategories.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ReportBy {
+    /// Income, expenses and the change from the previous month, one row per month
+    #[default]
+    Month,
+    /// One row per category over the whole period
+    Category,
+    /// One row per category and month
+    MonthCategory,
+    /// One row per account and currency
+    Account,
+    /// One row per payee and currency, the largest spending first
+    Payee,
+    /// One row per channel and currency, e.g. total service charges
+    Channel,
+    /// One row per tag and currency, a transaction counts towards each of its tags
+    Tag,
+}
+
+#[derive(Debug, Args)]
+pub struct CategorizeArgs {
+    /// Categorization rules file
+    #[arg(long)]
+    pub rules: PathBuf,
+    /// Also recategorize transactions that already have a category
+    #[arg(long)]
+    pub all: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct BudgetArgs {
+    /// Budgets file
+    #[arg(long)]
+    pub budgets: PathBuf,
+    /// Day to evaluate the budgets on, defaults to today
+    #[arg(long)]
+    pub as_of: Option<NaiveDate>,
+    #[arg(long)]
+    pub account: Option<String>,
+    #[arg(long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+#[derive(Debug, Args)]
+pub struct TaxArgs {
+    /// Tax schedules file
+    #[arg(long)]
+    pub schedules: PathBuf,
+    /// Tax year, defaults to last year
+    #[arg(long)]
+    pub year: Option<i32>,
+    #[arg(long)]
+    pub account: Option<String>,
+    /// List the qualifying transactions instead of the totals per line
+    #[arg(long)]
+    pub transactions: bool,
+    /// Write the whole report as an HTML document to print or save as PDF, `--output` and
+    /// `--transactions` are ignored
+    #[arg(long)]
+    pub html: bool,
+    /// File to write instead of the standard output
+    #[arg(long)]
+    pub file: Option<PathBuf>,
+    #[arg(long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+#[derive(Debug, Args)]
+pub struct RecurringArgs {
+    /// Last day of the history, defaults to today
+    #[arg(long)]
+    pub as_of: Option<NaiveDate>,
+    #[arg(long)]
+    pub account: Option<String>,
+    /// Largest change between two amounts of a series, as a percentage
+    #[arg(long, default_value_t = Detector::default().amount_tolerance)]
+    pub tolerance: u32,
+    #[arg(long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+#[derive(Debug, Args)]
+pub struct ForecastArgs {
+    /// Balance at the end of --as-of
+    #[arg(long, allow_hyphen_values = true)]
+    pub balance: String,
+    #[arg(long, default_value = "CAD")]
+    pub currency: String,
+    /// Last day of the history, defaults to today
+    #[arg(long)]
+    pub as_of: Option<NaiveDate>,
+    /// Number of months to project
+    #[arg(long, default_value_t = 3)]
+    pub months: u32,
+    /// TOML file with scheduled items
+    #[arg(long)]
+    pub schedule: Option<PathBuf>,
+    /// Account whose history recurring items are detected in
+    #[arg(long)]
+    pub account: Option<String>,
+    /// Also list days without any projected transaction
+    #[arg(long)]
+    pub all_days: bool,
+    #[arg(long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TransfersCommand {
+    /// Pair debits and credits moving money between two accounts
+    Match(TransferMatchArgs),
+    /// List matched transfers, only those waiting for review unless --all is given
+    Review(TransferReviewArgs),
+    /// Count a matched transfer as a transfer
+    Confirm { id: i64 },
+    /// Mark a matched pair as not being a transfer
+    Reject { id: i64 },
+}
+
+#[derive(Debug, Args)]
+pub struct TransferMatchArgs {
+    /// Largest number of days between the two sides of a transfer
+    #[arg(long, default_value_t = Matcher::default().date_window)]
+    pub window: i64,
+    /// Largest difference between the two amounts, as a percentage
+    #[arg(long, default_value_t = Matcher::default().amount_tolerance)]
+    pub tolerance: u32,
+}
+
+#[derive(Debug, Args)]
+pub struct TransferReviewArgs {
+    #[arg(long)]
+    pub all: bool,
+    #[arg(long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+#[derive(Debug, Args)]
+pub struct ReconcileArgs {
+    #[arg(long)]
+    pub account: String,
+    /// Closing balance of a statement, e.g. 2024-06-30=1234.56. Can be repeated.
+    #[arg(long, allow_hyphen_values = true)]
+    pub closing: Vec<String>,
+    #[arg(long, value_enum, default_value_t)]
+    pub output: OutputFormat,
+}
+
+/// The running balance of an account disagrees with a known balance
+#[derive(Debug)]
+pub struct ReconcileFailed {
+    pub account: String,
+}
+
+impl fmt::Display for ReconcileFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "account {} does not reconcile", self.account)
+    }
+}
+
+impl Error for ReconcileFailed {}
+
+/// Some of the files given to `import` could not be imported
+#[derive(Debug)]
+pub struct ImportFailed {
+    pub failed: usize,
+}
+
+impl fmt::Display for ImportFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} file(s) could not be imported", self.failed)
+    }
+}
+
+impl Error for ImportFailed {}
+
+/// Runs a parsed command line, writing results to `out` and warnings to stderr
+pub fn run(cli: Cli, out: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+    let tree = match &cli.categories {
+        Some(path) => CategoryTree::load(path)?,
+        None => CategoryTree::defaults(),
+    };
+    let aliases = match &cli.aliases {
+        Some(path) => Aliases::load(path)?,
+        None => Aliases::new(),
+    };
+    let mut store = Store::open(&cli.db)?;
+
+    match cli.command {
+        Command::Account(AccountCommand::Add(args)) => add_account(&mut store, &args, out),
+        Command::Account(AccountCommand::List(args)) => list_accounts(&store, &tree, &args, out),
+        Command::Import(args) => import(&mut store, &tree, &args, out),
+        Command::List(args) => list(&store, &tree, &aliases, &args, out),
+        Command::Report(args) => report(&store, &tree, &aliases, &args, out),
+        Command::Categorize(args) => categorize(&mut store, &tree, &args, out),
+        Command::Split(args) => split(&mut store, &tree, &args, out),
+        Command::Tag(args) => tag(&mut store, &args, out),
+        Command::Tags(args) => list_tags(&store, &args, out),
+        Command::Note(args) => note(&mut store, &args, out),
+        Command::Attach(args) => attach(&mut store, &args, out),
+        Command::Budget(args) => budget(&store, &tree, &args, out),
+        Command::Tax(args) => tax(&store, &tree, &args, out),
+        Command::Recurring(args) => recurring(&store, &tree, &args, out),
+        Command::Forecast(args) => forecast(&store, &tree, &args, out),
+        Command::Reconcile(args) => reconcile(&store, &tree, &args, out),
+        Command::Export(args) => export(&store, &tree, &args, out),
+        Command::Transfers(TransfersCommand::Match(args)) => {
+            match_transfers(&mut store, &args, out)
+        }
+        Command::Transfers(TransfersCommand::Review(args)) => {
+            review_transfers(&store, &aliases, &args, out)
+        }
+        Command::Transfers(TransfersCommand::Confirm { id }) => {
+            set_transfer_status(&mut store, id, TransferStatus::Confirmed, out)
+        }
+        Command::Transfers(TransfersCommand::Reject { id }) => {
+            set_transfer_status(&mut store, id, TransferStatus::Rejected, out)
+        }
+    }
+}
+
+fn load_rules(path: &Path, tree: &CategoryTree) -> Result<Categorizer, Box<dyn Error>> {
+    let categorizer = Categorizer::load(path)?;
+    categorizer.validate(tree)?;
+    Ok(categorizer)
+}
+
+fn add_account(
+    store: &mut Store,
+    args: &AccountAddArgs,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let currency = Currency::new(&args.currency)?;
+    let account = Account {
+        id: args.id.clone(),
+        masked_number: args.number.as_deref().and_then(mask_number),
+        institution: args.institution.clone(),
+        account_type: args.account_type,
+        currency,
+        opening_balance: Money::parse(&args.opening_balance, currency)?,
+    };
+    store.add_account(&account)?;
+    writeln!(out, "added account {}", account.id)?;
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+struct AccountRow {
+    #[serde(flatten)]
+    account: Account,
+    transactions: usize,
+    balance: Money,
+    /// Export time of the newest statement imported
+    valid_as_of: Option<NaiveDateTime>,
+}
+
+impl Tabular for AccountRow {
+    const HEADERS: &'static [&'static str] = &[
+        "ID",
+        "Number",
+        "Institution",
+        "Type",
+        "Currency",
+        "Opening",
+        "Count",
+        "Balance",
+        "Valid As Of",
+    ];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.account.id.clone(),
+            self.account.masked_number.clone().unwrap_or_default(),
+            self.account.institution.clone().unwrap_or_default(),
+            self.account.account_type.to_string(),
+            self.account.currency.to_string(),
+            self.account.opening_balance.to_string(),
+            self.transactions.to_string(),
+            self.balance.to_string(),
+            self.valid_as_of
+                .map(|valid_as_of| valid_as_of.to_string())
+                .unwrap_or_default(),
+        ]
+    }
+}
+
+fn list_accounts(
+    store: &Store,
+    tree: &CategoryTree,
+    args: &AccountListArgs,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let mut rows = Vec::new();
+    for account in store.accounts()? {
+        let filter = TransactionFilter {
+            account: Some(account.id.clone()),
+            ..TransactionFilter::default()
+        };
+        let transactions = store.find(&filter, tree)?;
+        let balance = Money::checked_sum(
+            transactions
+                .iter()
+                .map(|transaction| transaction.data.amount)
+                .chain([account.opening_balance]),
+            account.currency,
+        )?;
+        rows.push(AccountRow {
+            transactions: transactions.len(),
+            balance,
+            valid_as_of: store.valid_as_of(&account.id)?,
+            account,
+        });
+    }
+    writeln!(out, "{}", render(&rows, args.output)?)?;
+    Ok(())
+}
+
+fn import(
+    store: &mut Store,
+    tree: &CategoryTree,
+    args: &ImportArgs,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let mut formats = StatementFormat::builtin();
+    if let Some(path) = &args.formats {
+        formats.extend(StatementFormat::load(path)?);
+    }
+    let decoders = Decoders {
+        formats,
+        categorizer: args
+            .rules
+            .as_deref()
+            .map(|path| load_rules(path, tree))
+            .transpose()?,
+        channels: match &args.channels {
+            Some(path) => ChannelTable::load(path)?,
+            None => ChannelTable::defaults(),
+        },
+    };
+
+    let mut failed = 0;
+    for file in &args.files {
+        let file = file.display().to_string();
+        let result = import_file(store, tree, &file, &decoders, args, out);
+        if let Err(e) = result {
+            eprintln!("error: {e}");
+            failed += 1;
+        }
+    }
+    // Codes of the file may decode transactions imported before it was written
+    if args.channels.is_some() {
+        let filled = store.fill_channels(&decoders.channels)?;
+        if filled > 0 {
+            writeln!(out, "{filled} stored transactions got a channel")?;
+        }
+    }
+
+    if failed > 0 {
+        return Err(Box::new(ImportFailed { failed }));
+    }
+    Ok(())
+}
+
+/// What `import` reads statements with and applies to their rows
+struct Decoders {
+    formats: Vec<StatementFormat>,
+    categorizer: Option<Categorizer>,
+    channels: ChannelTable,
+}
+
+fn import_file(
+    store: &mut Store,
+    tree: &CategoryTree,
+    file: &str,
+    decoders: &Decoders,
+    args: &ImportArgs,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let account_currency = match &args.account {
+        Some(id) => store.account(id)?.map(|account| account.currency),
+        None => None,
+    };
+    let (mut output, format_name, currency) =
+        read_statement(file, &decoders.formats, tree, account_currency, args)?;
+    for diagnostic in &output.diagnostics {
+        eprintln!("warning: skipped {diagnostic}");
+    }
+    decoders.channels.apply(&mut output.rows);
+    if let Some(categorizer) = &decoders.categorizer {
+        categorizer.categorize(&mut output.rows);
+    }
+    let groups = match &args.account {
+        Some(id) => {
+            if store.account(id)?.is_none() {
+                store.add_account(&Account::new(id, currency))?;
+                eprintln!("note: added account {id:?}, use `account add` to describe new accounts");
+            }
+            vec![(id.clone(), output.rows)]
+        }
+        None => rows_by_account(store, output.rows)?,
+    };
+
+    let mut inserted = 0;
+    let mut duplicates = 0;
+    for (account, rows) in &groups {
+        if let (Some(valid_as_of), Some(newest)) =
+            (output.metadata.valid_as_of, store.valid_as_of(account)?)
+        {
+            if valid_as_of <= newest && !args.force {
+                eprintln!(
+                    "warning: skipped {account} rows, the statement is valid as of {valid_as_of} \
+                     but data valid as of {newest} was already imported, pass --force to import it"
+                );
+                continue;
+            }
+        }
+        let summary = store.import_with(file, account, rows, &output.metadata)?;
+        inserted += summary.inserted;
+        duplicates += summary.duplicates;
+    }
+
+    let covered = match output.metadata.covered() {
+        Some((first, last)) => format!(", {first} to {last}"),
+        None => String::new(),
+    };
+    writeln!(
+        out,
+        "{file}: {inserted} imported, {duplicates} duplicates, {} skipped ({format_name}{covered})",
+        output.diagnostics.len(),
+    )?;
+    Ok(())
+}
+
+/// Parses `file` as OFX, CAMT.053, QIF or with the statement format named by `--format` or
+/// detected, returning the format's name and the currency of new accounts
+///
+/// QIF amounts are taken to be in `account_currency`, or CAD for new accounts. `--lenient` is
+/// rejected for OFX, CAMT.053 and QIF files, their parsers can't skip a malformed record.
+fn read_statement(
+    file: &str,
+    formats: &[StatementFormat],
+    tree: &CategoryTree,
+    account_currency: Option<Currency>,
+    args: &ImportArgs,
+) -> Result<(ParseOutput, String, Currency), Box<dyn Error>> {
+    let is_qif = match &args.format {
+        Some(name) => name == QIF_FORMAT,
+        None => is_qif_file(file),
+    };
+    let not_lenient = |name: &str| -> Result<(), String> {
+        if args.lenient {
+            return Err(format!(
+                "--lenient only applies to CSV statements, {name} files are imported whole"
+            ));
+        }
+        Ok(())
+    };
+    if is_qif {
+        not_lenient(QIF_FORMAT)?;
+        let currency = account_currency.unwrap_or(Currency::CAD);
+        let order = if args.day_first {
+            DateOrder::DayFirst
+        } else {
+            DateOrder::MonthFirst
+        };
+        let mut output = ParseOutput::default();
+        for transaction in read_qif(file, currency, tree, order)? {
+            output.metadata.cover(transaction.data.date);
+            output.rows.push(transaction.data);
+        }
+        return Ok((output, QIF_FORMAT.to_string(), currency));
+    }
+
+    let markup = match &args.format {
+        Some(name) => [OFX_FORMAT, CAMT_FORMAT]
+            .into_iter()
+            .find(|format| format == name),
+        None if is_ofx_file(file) => Some(OFX_FORMAT),
+        None if is_camt_file(file) => Some(CAMT_FORMAT),
+        None => None,
+    };
+    if let Some(name) = markup {
+        not_lenient(name)?;
+        let output = if name == OFX_FORMAT {
+            parse_ofx(file)?
+        } else {
+            parse_camt(file)?
+        };
+        let currency = output
+            .rows
+            .first()
+            .map_or(Currency::CAD, |data| data.amount.currency());
+        return Ok((output, name.to_string(), currency));
+    }
+
+    let mode = if args.lenient {
+        ParseMode::Lenient
+    } else {
+        ParseMode::Strict
+    };
+    let format = match &args.format {
+        Some(name) => formats
+            .iter()
+            .find(|format| format.name == *name)
+            .ok_or_else(|| format!("unknown statement format {name:?}"))?,
+        None => {
+            let detection = detect_format(file, formats)?;
+            match detection.best() {
+                Ok(best) => formats
+                    .iter()
+                    .find(|format| format.name == best.name)
+                    .expect("detected formats come from the candidates"),
+                Err(e) => {
+                    eprintln!("{detection}");
+                    return Err(Box::new(e));
+                }
+            }
+        }
+    };
+    Ok((
+        parse_csv_with(file, format, mode)?,
+        format.name.clone(),
+        format.currency,
+    ))
+}
+
+/// Statement rows grouped by account id
+type AccountRows = Vec<(String, Vec<Data>)>;
+
+/// Splits `rows` by the account their card or account number belongs to, keeping their order
+fn rows_by_account(store: &Store, rows: Vec<Data>) -> Result<AccountRows, Box<dyn Error>> {
+    let mut groups: AccountRows = Vec::new();
+    for data in rows {
+        let number = data
+            .account_number
+            .as_deref()
+            .ok_or("the statement has no account numbers, pass --account")?;
+        let account = store.account_by_number(number)?.ok_or_else(|| {
+            format!(
+                "no account with number {number}, pass --account or add it with \
+                 `account add --number`"
+            )
+        })?;
+        match groups.iter_mut().find(|(id, _)| *id == account.id) {
+            Some((_, rows)) => rows.push(data),
+            None => groups.push((account.id, vec![data])),
+        }
+    }
+    Ok(groups)
+}
+
+#[derive(Debug, Serialize)]
+struct TransactionRow {
+    id: i64,
+    account: String,
+    date: NaiveDate,
+    transaction_type: TransactionType,
+    channel: Option<TransactionChannel>,
+    amount: String,
+    currency: Currency,
+    description: String,
+    payee: String,
+    category: TransactionCategory,
+    splits: Vec<Split>,
+    categorized_by: Option<String>,
+    tags: Vec<String>,
+    note: Option<String>,
+    attachments: Vec<String>,
+}
+
+impl TransactionRow {
+    fn new(transaction: StoredTransaction, aliases: &Aliases) -> TransactionRow {
+        let data = transaction.data;
+        TransactionRow {
+            id: transaction.id,
+            account: transaction.account,
+            date: data.date,
+            transaction_type: data.transaction_type,
+            channel: data.channel,
+            amount: data.amount.to_string(),
+            currency: data.amount.currency(),
+            payee: aliases.normalize(&data.description).payee,
+            description: data.description,
+            category: data.category,
+            splits: data.splits,
+            categorized_by: data.categorized_by,
+            tags: data.tags,
+            note: data.note,
+            attachments: data.attachments,
+        }
+    }
+}
+
+impl Tabular for TransactionRow {
+    const HEADERS: &'static [&'static str] = &[
+        "ID",
+        "Account",
+        "Date",
+        "Type",
+        "Channel",
+        "Amount",
+        "Currency",
+        "Description",
+        "Payee",
+        "Category",
+        "Rule",
+        "Tags",
+        "Note",
+    ];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.account.clone(),
+            self.date.to_string(),
+            self.transaction_type.as_str().to_string(),
+            self.channel
+                .map(|channel| channel.to_string())
+                .unwrap_or_default(),
+            self.amount.clone(),
+            self.currency.to_string(),
+            self.description.clone(),
+            self.payee.clone(),
+            // The categories of the parts of split transactions
+            if self.splits.is_empty() {
+                self.category.to_string()
+            } else {
+                self.splits
+                    .iter()
+                    .map(|split| split.category.id())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            },
+            self.categorized_by.clone().unwrap_or_default(),
+            self.tags.join(", "),
+            self.note.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+fn list(
+    store: &Store,
+    tree: &CategoryTree,
+    aliases: &Aliases,
+    args: &ListArgs,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let rows: Vec<TransactionRow> = store
+        .find(&args.filter.to_filter()?, tree)?
+        .into_iter()
+        .map(|transaction| TransactionRow::new(transaction, aliases))
+        .collect();
+    writeln!(out, "{}", render(&rows, args.output)?)?;
+    Ok(())
+}
+
+fn export(
+    store: &Store,
+    tree: &CategoryTree,
+    args: &ExportArgs,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let transactions = store.find(&args.filter.to_filter()?, tree)?;
+    let map = match &args.account_map {
+        Some(path) => AccountMap::load(path)?,
+        None => AccountMap::new(),
+    };
+    map.validate(tree)?;
+    let mut file;
+    let writer: &mut dyn Write = match &args.file {
+        Some(path) => {
+            file = fs::File::create(path)?;
+            &mut file
+        }
+        None => out,
+    };
+    match args.format {
+        ExportFormat::Qif => {
+            for account in store.accounts()? {
+                let rows: Vec<QifTransaction> = transactions
+                    .iter()
+                    .filter(|transaction| transaction.account == account.id)
+                    .map(|transaction| QifTransaction::new(transaction.data.clone()))
+                    .collect();
+                if !rows.is_empty() {
+                    write_qif(writer, Some(&account.id), account.account_type, &rows, tree)?;
+                }
+            }
+        }
+        ExportFormat::Ledger | ExportFormat::Hledger | ExportFormat::Beancount => {
+            let format = match args.format {
+                ExportFormat::Ledger => JournalFormat::Ledger,
+                ExportFormat::Hledger => JournalFormat::Hledger,
+                _ => JournalFormat::Beancount,
+            };
+            write_journal(
+                writer,
+                format,
+                &map,
+                tree,
+                &store.accounts()?,
+                &transactions,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn report(
+    store: &Store,
+    tree: &CategoryTree,
+    aliases: &Aliases,
+    args: &ReportArgs,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let filter = args.filter.to_filter()?;
+    let transactions = store.find(&filter, tree)?;
+    // Split transactions count towards each of their categories, only the parts in the
+    // filtered category are kept
+    let allocations: Vec<(&StoredTransaction, Data)> = transactions
+        .iter()
+        .flat_map(|transaction| {
+            transaction
+                .data
+                .allocations()
+                .into_iter()
+                .map(move |data| (transaction, data))
+        })
+        .filter(|(_, data)| filter.matches_category(&data.category, tree))
+        .collect();
+    let mut by_currency: BTreeMap<Currency, Vec<&Data>> = BTreeMap::new();
+    for (_, data) in &allocations {
+        by_currency
+            .entry(data.amount.currency())
+            .or_default()
+            .push(data);
+    }
+
+    let mut reports = Vec::new();
+    for (currency, rows) in by_currency {
+        reports.push(monthly_report(rows, currency, tree)?);
+    }
+    let rendered = match args.by {
+        ReportBy::Month => {
+            let rows: Vec<MonthRow> = reports.iter().flat_map(MonthlyReport::month_rows).collect();
+            render(&rows, args.output)?
+        }
+        ReportBy::Category => {
+            let rows: Vec<CategoryRow> = reports
+                .iter()
+                .flat_map(MonthlyReport::category_rows)
+                .collect();
+            render(&rows, args.output)?
+        }
+        ReportBy::Account => {
+            let rows = account_totals(
+                allocations
+                    .iter()
+                    .map(|(transaction, data)| (transaction.account.as_str(), data)),
+                tree,
+            )?;
+            render(&rows, args.output)?
+        }
+        ReportBy::Payee => {
+            // The payee comes from the transaction, the descriptions of its parts carry their memos
+            let rows = payee_totals(
+                allocations.iter().map(|(transaction, data)| {
+                    let description = &transaction.data.description;
+                    (aliases.normalize(description).payee, data)
+                }),
+                tree,
+            )?;
+            render(&rows, args.output)?
+        }
+        ReportBy::Channel => {
+            let rows = channel_totals(allocations.iter().map(|(_, data)| data), tree)?;
+            render(&rows, args.output)?
+        }
+        ReportBy::Tag => {
+            let rows = tag_totals(allocations.iter().map(|(_, data)| data), tree)?;
+            render(&rows, args.output)?
+        }
+        ReportBy::MonthCategory => {
+            let rows: Vec<CategoryRow> = reports
+                .iter()
+                .flat_map(MonthlyReport::month_category_rows)
+                .collect();
+            render(&rows, args.output)?
+        }
+    };
+    writeln!(out, "{rendered}")?;
+    Ok(())
+}
+
+fn categorize(
+    store: &mut Store,
+    tree: &CategoryTree,
+    args: &CategorizeArgs,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let categorizer = load_rules(&args.rules, tree)?;
+
+    let mut changed = 0;
+    let mut unmatched = 0;
+    for transaction in store.transactions()? {
+        let data = &transaction.data;
+        let uncategorized =
+            data.categorized_by.is_none() && data.category == TransactionCategory::OTHER;
+        // Transfers are filed by the transfer matcher and changed with `transfers reject`
+        let transfer = data.categorized_by.as_deref() == Some(TRANSFER_RULE);
+        if transfer || (!args.all && !uncategorized) {
+            continue;
+        }
+        match categorizer.find(data) {
+            Some(rule) => {
+                store.set_category(transaction.id, rule.category, Some(rule.name))?;
+                changed += 1;
+            }
+            None => unmatched += 1,
+        }
+    }
+
+    writeln!(
+        out,
+        "{changed} categorized, {unmatched} without a matching rule"
+    )?;
+    Ok(())
+}
+
+fn split(
+    store: &mut Store,
+    tree: &CategoryTree,
+    args: &SplitArgs,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let transaction = store
+        .transaction(args.id)?
+        .ok_or(StoreError::NotFound(args.id))?;
+    let currency = transaction.data.amount.currency();
+    let splits = args
+        .parts
+        .iter()
+        .map(|text| Split::parse(text, currency))
+        .collect::<Result<Vec<Split>, String>>()?;
+    if let Some(split) = splits.iter().find(|split| !tree.contains(&split.category)) {
+        return Err(format!("unknown category {:?}", split.category.id()).into());
+    }
+    store.set_splits(args.id, &splits)?;
+    if splits.is_empty() {
+        writeln!(out, "transaction {} is no longer split", args.id)?;
+    } else {
+        writeln!(
+            out,
+            "transaction {} split into {} parts",
+            args.id,
+            splits.len()
+        )?;
+    }
+    Ok(())
+}
+
+fn tag(store: &mut Store, args: &TagArgs, out: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+    if args.remove {
+        store.remove_tags(args.id, &args.tags)?;
+    } else {
+        store.add_tags(args.id, &args.tags)?;
+    }
+    let transaction = store
+        .transaction(args.id)?
+        .ok_or(StoreError::NotFound(args.id))?;
+    writeln!(
+        out,
+        "transaction {} tags: {}",
+        args.id,
+        transaction.data.tags.join(", ")
+    )?;
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+struct TagRow {
+    tag: String,
+    transactions: usize,
+}
+
+impl Tabular for TagRow {
+    const HEADERS: &'static [&'static str] = &["Tag", "Transactions"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![self.tag.clone(), self.transactions.to_string()]
+    }
+}
+
+fn list_tags(store: &Store, args: &TagsArgs, out: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+    let rows: Vec<TagRow> = store
+        .tags()?
+        .into_iter()
+        .map(|(tag, transactions)| TagRow { tag, transactions })
+        .collect();
+    writeln!(out, "{}", render(&rows, args.output)?)?;
+    Ok(())
+}
+
+fn note(store: &mut Store, args: &NoteArgs, out: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+    let text = args
+        .text
+        .as_deref()
+        .map(str::trim)
+        .filter(|text| !text.is_empty());
+    store.set_note(args.id, text)?;
+    match text {
+        Some(_) => writeln!(out, "transaction {} note set", args.id)?,
+        None => writeln!(out, "transaction {} note removed", args.id)?,
+    }
+    Ok(())
+}
+
+fn attach(store: &mut Store, args: &AttachArgs, out: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+    if args.remove {
+        // The file may be gone already, so it is unlinked by the path it was attached with
+        let path = fs::canonicalize(&args.file).unwrap_or_else(|_| args.file.clone());
+        let path = path.display().to_string();
+        let file = args.file.display().to_string();
+        if !(store.detach(args.id, &path)? || store.detach(args.id, &file)?) {
+            return Err(format!("{file} is not attached to transaction {}", args.id).into());
+        }
+        writeln!(out, "transaction {}: detached {file}", args.id)?;
+        return Ok(());
+    }
+    let path = fs::canonicalize(&args.file)
+        .map_err(|e| format!("{}: {e}", args.file.display()))?
+        .display()
+        .to_string();
+    store.attach(args.id, &path)?;
+    writeln!(out, "transaction {}: attached {path}", args.id)?;
+    Ok(())
+}
+
+fn budget(
+    store: &Store,
+    tree: &CategoryTree,
+    args: &BudgetArgs,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let budgets = Budgets::load(&args.budgets)?;
+    budgets.validate(tree)?;
+
+    let as_of = args.as_of.unwrap_or_else(today);
+    let rows: Vec<Data> = history(store, tree, args.account.as_deref(), as_of)?
+        .iter()
+        .flat_map(Data::allocations)
+        .collect();
+    let statuses = budgets.evaluate(&rows, tree, as_of)?;
+    writeln!(out, "{}", render(&statuses, args.output)?)?;
+    Ok(())
+}
+
+fn tax(
+    store: &Store,
+    tree: &CategoryTree,
+    args: &TaxArgs,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let schedules = Schedules::load(&args.schedules)?;
+    schedules.validate(tree)?;
+
+    let year = args.year.unwrap_or_else(|| today().year() - 1);
+    let filter = TransactionFilter {
+        account: args.account.clone(),
+        from: NaiveDate::from_ymd_opt(year, 1, 1),
+        to: NaiveDate::from_ymd_opt(year, 12, 31),
+        ..TransactionFilter::default()
+    };
+    let rows: Vec<Data> = store
+        .find(&filter, tree)?
+        .iter()
+        .flat_map(|transaction| transaction.data.allocations())
+        .collect();
+    let report = schedules.report(&rows, tree, year)?;
+
+    let mut file;
+    let writer: &mut dyn Write = match &args.file {
+        Some(path) => {
+            file = fs::File::create(path)?;
+            &mut file
+        }
+        None => out,
+    };
+    if args.html {
+        write!(writer, "{}", report.to_html())?;
+    } else if args.transactions {
+        writeln!(writer, "{}", render(&report.items, args.output)?)?;
+    } else {
+        writeln!(writer, "{}", render(&report.lines, args.output)?)?;
+    }
+    Ok(())
+}
+
+fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+/// The transactions of `account`, or of every account, up to `as_of`
+fn history(
+    store: &Store,
+    tree: &CategoryTree,
+    account: Option<&str>,
+    as_of: NaiveDate,
+) -> Result<Vec<Data>, Box<dyn Error>> {
+    let filter = TransactionFilter {
+        account: account.map(str::to_string),
+        to: Some(as_of),
+        ..TransactionFilter::default()
+    };
+    Ok(store
+        .find(&filter, tree)?
+        .into_iter()
+        .map(|transaction| transaction.data)
+        .collect())
+}
+
+fn recurring(
+    store: &Store,
+    tree: &CategoryTree,
+    args: &RecurringArgs,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let as_of = args.as_of.unwrap_or_else(today);
+    let rows = history(store, tree, args.account.as_deref(), as_of)?;
+    let detector = Detector {
+        amount_tolerance: args.tolerance,
+    };
+    writeln!(
+        out,
+        "{}",
+        render(&detector.detect(&rows, as_of), args.output)?
+    )?;
+    Ok(())
+}
+
+fn forecast(
+    store: &Store,
+    tree: &CategoryTree,
+    args: &ForecastArgs,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let balance = Money::parse(&args.balance, Currency::new(&args.currency)?)?;
+    let schedule = match &args.schedule {
+        Some(path) => Schedule::load(path)?,
+        None => Schedule::default(),
+    };
+    let as_of = args.as_of.unwrap_or_else(today);
+    let rows = history(store, tree, args.account.as_deref(), as_of)?;
+    let series = Detector::default().detect(&rows, as_of);
+
+    let forecast = forecast::forecast(balance, as_of, args.months, &series, &schedule)?;
+    let days: Vec<ForecastDay> = forecast
+        .days
+        .iter()
+        .filter(|day| args.all_days || !day.events.is_empty())
+        .cloned()
+        .collect();
+    writeln!(out, "{}", render(&days, args.output)?)?;
+    if args.output == OutputFormat::Table {
+        if let Some(lowest) = forecast.lowest() {
+            writeln!(
+                out,
+                "\nLowest balance: {} {} on {}",
+                lowest.balance,
+                lowest.balance.currency(),
+                lowest.date
+            )?;
+        }
+        if let Some(overdraft) = forecast.first_overdraft() {
+            writeln!(out, "Overdrawn from {}", overdraft.date)?;
+        }
+    }
+    Ok(())
+}
+
+fn match_transfers(
+    store: &mut Store,
+    args: &TransferMatchArgs,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let transfers = store.transfers()?;
+    let (rejected, paired): (Vec<_>, Vec<_>) = transfers
+        .iter()
+        .map(|transfer| transfer.transfer)
+        .partition(|transfer| transfer.status == TransferStatus::Rejected);
+    let paired: HashSet<i64> = paired
+        .iter()
+        .flat_map(|transfer| [transfer.debit, transfer.credit])
+        .collect();
+    let rejected: HashSet<(i64, i64)> = rejected
+        .iter()
+        .map(|transfer| (transfer.debit, transfer.credit))
+        .collect();
+    let mut transactions = store.transactions()?;
+    transactions.retain(|transaction| !paired.contains(&transaction.id));
+
+    let matcher = Matcher {
+        date_window: args.window,
+        amount_tolerance: args.tolerance,
+        ..Matcher::default()
+    };
+    let matches = matcher.find_except(&transactions, &rejected);
+    store.record_transfers(&matches)?;
+
+    let confirmed = matches
+        .iter()
+        .filter(|transfer| transfer.status == TransferStatus::Confirmed)
+        .count();
+    writeln!(
+        out,
+        "{confirmed} transfers matched, {} to review",
+        matches.len() - confirmed
+    )?;
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+struct TransferRow {
+    id: i64,
+    status: TransferStatus,
+    confidence: f64,
+    debit: TransactionRow,
+    credit: TransactionRow,
+}
+
+impl Tabular for TransferRow {
+    const HEADERS: &'static [&'static str] = &[
+        "ID",
+        "Status",
+        "Confidence",
+        "From",
+        "Date",
+        "Amount",
+        "Description",
+        "To",
+        "Date",
+        "Amount",
+        "Description",
+    ];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.status.as_str().to_string(),
+            format!("{:.0}%", self.confidence * 100.0),
+            self.debit.account.clone(),
+            self.debit.date.to_string(),
+            self.debit.amount.clone(),
+            self.debit.description.clone(),
+            self.credit.account.clone(),
+            self.credit.date.to_string(),
+            self.credit.amount.clone(),
+            self.credit.description.clone(),
+        ]
+    }
+}
+
+fn review_transfers(
+    store: &Store,
+    aliases: &Aliases,
+    args: &TransferReviewArgs,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let mut rows = Vec::new();
+    for stored in store.transfers()? {
+        let transfer = stored.transfer;
+        if !args.all && transfer.status != TransferStatus::Review {
+            continue;
+        }
+        let side = |id: i64| -> Result<TransactionRow, Box<dyn Error>> {
+            let transaction = store.transaction(id)?.ok_or(StoreError::NotFound(id))?;
+            Ok(TransactionRow::new(transaction, aliases))
+        };
+        rows.push(TransferRow {
+            id: stored.id,
+            status: transfer.status,
+            confidence: transfer.confidence,
+            debit: side(transfer.debit)?,
+            credit: side(transfer.credit)?,
+        });
+    }
+    writeln!(out, "{}", render(&rows, args.output)?)?;
+    Ok(())
+}
+
+fn set_transfer_status(
+    store: &mut Store,
+    id: i64,
+    status: TransferStatus,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    store.set_transfer_status(id, status)?;
+    writeln!(out, "transfer {id} {}", status.as_str())?;
+    Ok(())
+}
+
+fn reconcile(
+    store: &Store,
+    tree: &CategoryTree,
+    args: &ReconcileArgs,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let account = store
+        .account(&args.account)?
+        .ok_or_else(|| StoreError::UnknownAccount(args.account.clone()))?;
+    let checkpoints = args
+        .closing
+        .iter()
+        .map(|text| Checkpoint::parse(text, account.currency))
+        .collect::<Result<Vec<Checkpoint>, String>>()?;
+    let filter = TransactionFilter {
+        account: Some(account.id.clone()),
+        ..TransactionFilter::default()
+    };
+    let transactions = store.find(&filter, tree)?;
+
+    let reconciliation = reconcile::reconcile(
+        account.opening_balance,
+        transactions.iter().map(|transaction| &transaction.data),
+        &checkpoints,
+    )?;
+    writeln!(out, "{}", render(&reconciliation.entries, args.output)?)?;
+    if args.output == OutputFormat::Table {
+        writeln!(out, "\n{reconciliation}")?;
+    } else {
+        eprintln!("{reconciliation}");
+    }
+    if reconciliation.first_divergence().is_some() {
+        return Err(Box::new(ReconcileFailed {
+            account: account.id,
+        }));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[path = "./tests/test.rs"]
+mod test;
@@ -0,0 +1,891 @@
+use super::*;
+use std::{env, fs};
+
+const TEST_FILE_PATH: &str = "src/csv_parser/tests/test_statement.csv";
+const OFX_FILE_PATH: &str = "src/ofx/tests/test_statement.ofx";
+const QIF_FILE_PATH: &str = "src/qif/tests/test_statement.qif";
+const CAMT_FILE_PATH: &str = "src/camt/tests/test_statement.xml";
+
+const RULES_TOML: &str = r#"
+[[rule]]
+name = "mortgage"
+category = "bills"
+description_contains = ["MTG/HYP"]
+"#;
+
+/// A database path unique to the test, removed when dropped
+struct TempDb(PathBuf);
+
+impl TempDb {
+    fn new(name: &str) -> TempDb {
+        let path =
+            env::temp_dir().join(format!("finance-tracker-{}-{name}.db", std::process::id()));
+        let _ = fs::remove_file(&path);
+        TempDb(path)
+    }
+
+    fn run(&self, args: &[&str]) -> Result<String, Box<dyn Error>> {
+        let db = self.0.display().to_string();
+        let cli = Cli::try_parse_from(["finance-tracker", "--db", db.as_str()].iter().chain(args))?;
+        let mut out = Vec::new();
+        run(cli, &mut out)?;
+        Ok(String::from_utf8(out)?)
+    }
+}
+
+impl Drop for TempDb {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+#[test]
+fn test_import_detects_format_and_skips_duplicates() {
+    let db = TempDb::new("import");
+
+    let first = db
+        .run(&["import", TEST_FILE_PATH, "--account", "chequing"])
+        .unwrap();
+    let stale = db
+        .run(&["import", TEST_FILE_PATH, "--account", "chequing"])
+        .unwrap();
+    let forced = db
+        .run(&["import", TEST_FILE_PATH, "--account", "chequing", "--force"])
+        .unwrap();
+
+    assert_eq!(
+        format!(
+            "{TEST_FILE_PATH}: 9 imported, 0 duplicates, 0 skipped \
+             (first-bank, 2024-06-03 to 2024-06-28)\n"
+        ),
+        first
+    );
+    // The same export again is skipped as stale
+    assert_eq!(
+        format!(
+            "{TEST_FILE_PATH}: 0 imported, 0 duplicates, 0 skipped \
+             (first-bank, 2024-06-03 to 2024-06-28)\n"
+        ),
+        stale
+    );
+    assert_eq!(
+        format!(
+            "{TEST_FILE_PATH}: 0 imported, 9 duplicates, 0 skipped \
+             (first-bank, 2024-06-03 to 2024-06-28)\n"
+        ),
+        forced
+    );
+}
+
+#[test]
+fn test_import_ofx() {
+    let db = TempDb::new("import-ofx");
+    db.run(&["account", "add", "chequing", "--number", "6007620712733055"])
+        .unwrap();
+
+    let first = db.run(&["import", OFX_FILE_PATH]).unwrap();
+    let second = db.run(&["import", OFX_FILE_PATH, "--force"]).unwrap();
+    let list = db.run(&["list", "--output", "csv"]).unwrap();
+
+    assert_eq!(
+        format!("{OFX_FILE_PATH}: 3 imported, 0 duplicates, 0 skipped (ofx, 2024-06-03 to 2024-06-28)\n"),
+        first
+    );
+    assert_eq!(
+        format!("{OFX_FILE_PATH}: 0 imported, 3 duplicates, 0 skipped (ofx, 2024-06-03 to 2024-06-28)\n"),
+        second
+    );
+    assert!(list.contains("2,chequing,2024-06-11,DEBIT,,-12.50,CAD,COFFEE & CO VISA DEBIT,COFFEE & CO VISA DEBIT,other,,,\n"));
+}
+
+#[test]
+fn test_import_camt() {
+    let db = TempDb::new("camt");
+    db.run(&[
+        "account",
+        "add",
+        "girokonto",
+        "--number",
+        "DE89 3704 0044 0532 0130 00",
+        "--currency",
+        "EUR",
+    ])
+    .unwrap();
+
+    let imported = db.run(&["import", CAMT_FILE_PATH]).unwrap();
+    let report = db.run(&["report", "--output", "csv"]).unwrap();
+
+    assert_eq!(
+        format!(
+            "{CAMT_FILE_PATH}: 3 imported, 0 duplicates, 0 skipped \
+             (camt.053, 2024-06-03 to 2024-06-30)\n"
+        ),
+        imported
+    );
+    assert_eq!(
+        "Month,Currency,Count,Credits,Debits,Net,Average,Largest,Net Change\n\
+         2024-06,EUR,3,99.50,-1854.90,-1755.40,-585.13,-1850.00,\n",
+        report
+    );
+}
+
+#[test]
+fn test_import_and_export_qif() {
+    let db = TempDb::new("qif");
+
+    let imported = db
+        .run(&["import", QIF_FILE_PATH, "--account", "cash"])
+        .unwrap();
+    let exported = db
+        .run(&["export", "--format", "qif", "--from", "2024-06-10"])
+        .unwrap();
+    let food = db
+        .run(&[
+            "report",
+            "--by",
+            "category",
+            "--category",
+            "food",
+            "--output",
+            "csv",
+        ])
+        .unwrap();
+
+    // The split transaction is stored as one row with its splits
+    assert_eq!(
+        format!(
+            "{QIF_FILE_PATH}: 4 imported, 0 duplicates, 0 skipped \
+             (qif, 2024-06-03 to 2024-06-28)\n"
+        ),
+        imported
+    );
+    assert!(exported.starts_with(
+        "!Account\nNcash\nTBank\n^\n!Type:Bank\n\
+         D06/10/2024\nT-80.00\nPINTERAC ETRNSFR SENT\nLAccount Transfers\n^\n\
+         D06/15/2024\nT-150.00\nPCOSTCO\nLFood\n\
+         SFood\nEGroceries\n$-110.00\nSOther\nEHardware\n$-40.00\n^\n"
+    ));
+    assert_eq!(3, exported.matches("^\n").count() - 1);
+    // Only the food part of the split counts towards food
+    assert_eq!(
+        "Month,Category,Name,Currency,Count,Credits,Debits,Net,Average,Largest\n\
+         ,food,Food,CAD,1,0.00,-110.00,-110.00,-110.00,-110.00\n",
+        food
+    );
+}
+
+#[test]
+fn test_split_transaction() {
+    let db = TempDb::new("split");
+    db.run(&["import", TEST_FILE_PATH, "--account", "chequing"])
+        .unwrap();
+
+    let unbalanced = db.run(&["split", "1", "--part", "bills=-1000.00"]);
+    let unknown = db.run(&["split", "1", "--part", "garden=-1374.47"]);
+    let split = db
+        .run(&[
+            "split",
+            "1",
+            "--part",
+            "bills=-1000.00=Principal",
+            "--part",
+            "other=-374.47=Interest",
+        ])
+        .unwrap();
+    let list = db
+        .run(&["list", "--category", "bills", "--output", "csv"])
+        .unwrap();
+    let bills = db
+        .run(&[
+            "report",
+            "--by",
+            "category",
+            "--category",
+            "bills",
+            "--output",
+            "csv",
+        ])
+        .unwrap();
+    let by_account = db
+        .run(&[
+            "report",
+            "--by",
+            "account",
+            "--category",
+            "bills",
+            "--output",
+            "csv",
+        ])
+        .unwrap();
+    let by_payee = db
+        .run(&[
+            "report",
+            "--by",
+            "payee",
+            "--category",
+            "bills",
+            "--output",
+            "csv",
+        ])
+        .unwrap();
+    let journal = db
+        .run(&["export", "--format", "ledger", "--to", "2024-06-03"])
+        .unwrap();
+    let undone = db.run(&["split", "1"]).unwrap();
+
+    assert_eq!(
+        "splits add up to -1000.00, the transaction amount is -1374.47",
+        unbalanced.unwrap_err().to_string()
+    );
+    assert!(unknown.is_err());
+    assert_eq!("transaction 1 split into 2 parts\n", split);
+    assert!(list.ends_with(",[DS]BANK         MTG/HYP,BANK MTG/HYP,\"bills, other\",,,\n"));
+    assert!(bills.contains(",bills,Bills,CAD,1,0.00,-1000.00,-1000.00,"));
+    assert!(by_account.contains("chequing,CAD,1,0.00,-1000.00,-1000.00,"));
+    assert!(by_payee.contains("BANK MTG/HYP,CAD,1,0.00,-1000.00,-1000.00,"));
+    assert!(journal.starts_with(
+        "2024/06/03 * [DS]BANK         MTG/HYP\n\
+         \x20   Expenses:Bills             1000.00 CAD\n\
+         \x20   Expenses:Other              374.47 CAD\n\
+         \x20   Assets:Bank:Chequing      -1374.47 CAD\n"
+    ));
+    assert_eq!("transaction 1 is no longer split\n", undone);
+}
+
+#[test]
+fn test_tags_notes_and_attachments() {
+    let db = TempDb::new("tags");
+    let receipt = env::temp_dir().join(format!(
+        "finance-tracker-{}-receipt.pdf",
+        std::process::id()
+    ));
+    fs::write(&receipt, "receipt").unwrap();
+    db.run(&["import", TEST_FILE_PATH, "--account", "chequing"])
+        .unwrap();
+
+    let tagged = db.run(&["tag", "3", "june-trip", "family"]).unwrap();
+    db.run(&["tag", "5", "June-Trip"]).unwrap();
+    let invalid = db.run(&["tag", "5", "june trip"]);
+    let removed = db.run(&["tag", "3", "family", "--remove"]).unwrap();
+    let noted = db.run(&["note", "5", "ferry tickets"]).unwrap();
+    let attached = db.run(&["attach", "5", receipt.to_str().unwrap()]).unwrap();
+    let missing = db.run(&["attach", "5", "/no/such/receipt.pdf"]);
+    let tags = db.run(&["tags", "--output", "csv"]).unwrap();
+    let trip = db
+        .run(&["report", "--by", "tag", "--output", "csv"])
+        .unwrap();
+    let listed = db
+        .run(&["list", "--tag", "june-trip", "--output", "csv"])
+        .unwrap();
+    let searched = db
+        .run(&["list", "--search", "ferry", "--output", "json"])
+        .unwrap();
+    let detached = db
+        .run(&["attach", "5", receipt.to_str().unwrap(), "--remove"])
+        .unwrap();
+    let cleared = db.run(&["note", "5"]).unwrap();
+    fs::remove_file(&receipt).unwrap();
+
+    assert_eq!("transaction 3 tags: family, june-trip\n", tagged);
+    assert!(invalid.is_err());
+    assert_eq!("transaction 3 tags: june-trip\n", removed);
+    assert_eq!("transaction 5 note set\n", noted);
+    assert!(attached.starts_with("transaction 5: attached /"));
+    assert!(missing.is_err());
+    assert_eq!("Tag,Transactions\njune-trip,2\n", tags);
+    assert_eq!(
+        "Tag,Currency,Count,Credits,Debits,Net,Average,Largest\n\
+         june-trip,CAD,2,0.00,-280.00,-280.00,-140.00,-200.00\n",
+        trip
+    );
+    assert_eq!(3, listed.lines().count());
+    assert!(listed
+        .lines()
+        .nth(2)
+        .unwrap()
+        .ends_with(",june-trip,ferry tickets"));
+    assert!(searched.contains("\"note\": \"ferry tickets\""));
+    assert!(searched.contains(receipt.file_name().unwrap().to_str().unwrap()));
+    assert!(detached.starts_with("transaction 5: detached "));
+    assert_eq!("transaction 5 note removed\n", cleared);
+}
+
+#[test]
+fn test_export_journal() {
+    let db = TempDb::new("journal");
+    let map = env::temp_dir().join(format!(
+        "finance-tracker-{}-accounts.toml",
+        std::process::id()
+    ));
+    fs::write(
+        &map,
+        "[accounts]\nchequing = \"Assets:FirstBank:Chequing\"\n",
+    )
+    .unwrap();
+    db.run(&["import", TEST_FILE_PATH, "--account", "chequing"])
+        .unwrap();
+
+    let journal = db.run(&[
+        "export",
+        "--format",
+        "hledger",
+        "--account-map",
+        map.to_str().unwrap(),
+        "--to",
+        "2024-06-03",
+    ]);
+    let invalid = db.run(&[
+        "export",
+        "--format",
+        "ledger",
+        "--account-map",
+        "missing.toml",
+    ]);
+    fs::remove_file(&map).unwrap();
+
+    assert_eq!(
+        "2024-06-03 * [DS]BANK         MTG/HYP\n\
+         \x20   Expenses:Other                  1374.47 CAD\n\
+         \x20   Assets:FirstBank:Chequing      -1374.47 CAD\n\
+         \n\
+         2024-06-03 * [DS]STRATA FEE\n\
+         \x20   Expenses:Other                   231.97 CAD\n\
+         \x20   Assets:FirstBank:Chequing       -231.97 CAD\n",
+        journal.unwrap()
+    );
+    assert!(invalid.is_err());
+}
+
+#[test]
+fn test_import_errors() {
+    let db = TempDb::new("import-errors");
+
+    let unknown_format = db.run(&[
+        "import",
+        TEST_FILE_PATH,
+        "--account",
+        "chequing",
+        "--format",
+        "nope",
+    ]);
+    let missing_file = db.run(&["import", "missing.csv", "--account", "chequing"]);
+    let lenient_ofx = db.run(&[
+        "import",
+        OFX_FILE_PATH,
+        "--account",
+        "chequing",
+        "--lenient",
+    ]);
+    let lenient_qif = db.run(&[
+        "import",
+        QIF_FILE_PATH,
+        "--account",
+        "chequing",
+        "--lenient",
+    ]);
+
+    assert!(unknown_format.unwrap_err().is::<ImportFailed>());
+    assert!(missing_file.unwrap_err().is::<ImportFailed>());
+    assert!(lenient_ofx.unwrap_err().is::<ImportFailed>());
+    assert!(lenient_qif.unwrap_err().is::<ImportFailed>());
+    assert_eq!(
+        "",
+        db.run(&["list", "--output", "csv"])
+            .unwrap()
+            .lines()
+            .skip(1)
+            .collect::<String>()
+    );
+    assert!(db.run(&["import", TEST_FILE_PATH]).is_err());
+}
+
+#[test]
+fn test_list_filters_and_formats() {
+    let db = TempDb::new("list");
+    db.run(&["import", TEST_FILE_PATH, "--account", "chequing"])
+        .unwrap();
+
+    let json = db
+        .run(&[
+            "list",
+            "--type",
+            "credit",
+            "--from",
+            "2024-06-01",
+            "--to",
+            "2024-06-30",
+            "--output",
+            "json",
+        ])
+        .unwrap();
+    let rows: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(2, rows.as_array().unwrap().len());
+    assert_eq!("521.30", rows[0]["amount"]);
+    assert_eq!("CREDIT", rows[0]["transaction_type"]);
+
+    let csv = db
+        .run(&["list", "--search", "strata", "--output", "csv"])
+        .unwrap();
+    assert_eq!(
+        "ID,Account,Date,Type,Channel,Amount,Currency,Description,Payee,Category,Rule,Tags,Note\n\
+         2,chequing,2024-06-03,DEBIT,pre_authorized_debit,-231.97,CAD,[DS]STRATA FEE,STRATA FEE,\
+         other,,,\n",
+        csv
+    );
+
+    let table = db.run(&["list", "--max", "-1000"]).unwrap();
+    assert_eq!(5, table.lines().count());
+}
+
+#[test]
+fn test_channels() {
+    let db = TempDb::new("channels");
+    let codes = env::temp_dir().join(format!(
+        "finance-tracker-{}-channels.toml",
+        std::process::id()
+    ));
+    fs::write(&codes, "[codes]\nIB = \"web_banking\"\n").unwrap();
+    db.run(&[
+        "import",
+        TEST_FILE_PATH,
+        "--account",
+        "chequing",
+        "--channels",
+        codes.to_str().unwrap(),
+    ])
+    .unwrap();
+    fs::remove_file(&codes).unwrap();
+
+    let charges = db
+        .run(&["list", "--channel", "service-charge", "--output", "json"])
+        .unwrap();
+    let channels = db
+        .run(&["report", "--by", "channel", "--output", "csv"])
+        .unwrap();
+
+    let rows: serde_json::Value = serde_json::from_str(&charges).unwrap();
+    assert_eq!(2, rows.as_array().unwrap().len());
+    assert_eq!("service_charge", rows[0]["channel"]);
+    assert_eq!(
+        "Channel,Currency,Count,Credits,Debits,Net,Average,Largest\n\
+         pre_authorized_debit,CAD,2,0.00,-1606.44,-1606.44,-803.22,-1374.47\n\
+         web_banking,CAD,4,0.00,-2947.36,-2947.36,-736.84,-1500.00\n\
+         deposit,CAD,1,521.30,0.00,521.30,521.30,521.30\n\
+         service_charge,CAD,2,30.95,-30.95,0.00,0.00,-30.95\n",
+        channels
+    );
+}
+
+#[test]
+fn test_channels_file_decodes_stored_transactions() {
+    let db = TempDb::new("channels-stored");
+    let statement = env::temp_dir().join(format!("finance-tracker-{}-atm.csv", std::process::id()));
+    let codes = env::temp_dir().join(format!(
+        "finance-tracker-{}-atm-channels.toml",
+        std::process::id()
+    ));
+    fs::write(
+        &statement,
+        fs::read_to_string(TEST_FILE_PATH)
+            .unwrap()
+            .replace("[CW]CITY TAX", "[AT]CASH WITHDRAWAL"),
+    )
+    .unwrap();
+    fs::write(&codes, "[codes]\nAT = \"atm\"\n").unwrap();
+    db.run(&[
+        "import",
+        statement.to_str().unwrap(),
+        "--account",
+        "chequing",
+    ])
+    .unwrap();
+
+    let before = db
+        .run(&["list", "--channel", "atm", "--output", "csv"])
+        .unwrap();
+    let imported = db
+        .run(&[
+            "import",
+            TEST_FILE_PATH,
+            "--account",
+            "savings",
+            "--channels",
+            codes.to_str().unwrap(),
+        ])
+        .unwrap();
+    let after = db
+        .run(&["list", "--channel", "atm", "--output", "csv"])
+        .unwrap();
+    fs::remove_file(&statement).unwrap();
+    fs::remove_file(&codes).unwrap();
+
+    assert_eq!(1, before.lines().count());
+    assert!(imported.ends_with("\n1 stored transactions got a channel\n"));
+    assert_eq!(2, after.lines().count());
+    assert!(after.contains(",atm,-1167.36,CAD,[AT]CASH WITHDRAWAL,"));
+}
+
+#[test]
+fn test_payee_aliases() {
+    let db = TempDb::new("payees");
+    let aliases = env::temp_dir().join(format!(
+        "finance-tracker-{}-aliases.toml",
+        std::process::id()
+    ));
+    fs::write(
+        &aliases,
+        "[aliases]\n\"LIFESTYL MSP/DIV\" = \"Lifestyle Markets dividend\"\n\
+         \"PREMIUM PLAN\" = \"Bank fees\"\n\"FULL PLAN FEE REBATE\" = \"Bank fees\"\n",
+    )
+    .unwrap();
+    db.run(&["import", TEST_FILE_PATH, "--account", "chequing"])
+        .unwrap();
+
+    let aliases = aliases.to_str().unwrap();
+    let list = db.run(&[
+        "list",
+        "--aliases",
+        aliases,
+        "--type",
+        "credit",
+        "--output",
+        "csv",
+    ]);
+    let payees = db.run(&[
+        "report",
+        "--by",
+        "payee",
+        "--aliases",
+        aliases,
+        "--min",
+        "-100",
+        "--output",
+        "csv",
+    ]);
+    fs::remove_file(aliases).unwrap();
+
+    assert!(list
+        .unwrap()
+        .contains(",[DN]LIFESTYL MSP/DIV,Lifestyle Markets dividend,other,,,\n"));
+    assert_eq!(
+        "Payee,Currency,Count,Credits,Debits,Net,Average,Largest\n\
+         INTERAC ETRNSFR SENT BROTHER,CAD,1,0.00,-80.00,-80.00,-80.00,-80.00\n\
+         Bank fees,CAD,2,30.95,-30.95,0.00,0.00,-30.95\n\
+         Lifestyle Markets dividend,CAD,1,521.30,0.00,521.30,521.30,521.30\n",
+        payees.unwrap()
+    );
+}
+
+#[test]
+fn test_categorize_and_report() {
+    let db = TempDb::new("categorize");
+    let rules = env::temp_dir().join(format!("finance-tracker-{}-rules.toml", std::process::id()));
+    fs::write(&rules, RULES_TOML).unwrap();
+    db.run(&["import", TEST_FILE_PATH, "--account", "chequing"])
+        .unwrap();
+
+    let categorized = db
+        .run(&["categorize", "--rules", rules.to_str().unwrap()])
+        .unwrap();
+    let months = db.run(&["report", "--output", "csv"]).unwrap();
+    let categories = db
+        .run(&["report", "--by", "category", "--output", "csv"])
+        .unwrap();
+    fs::remove_file(&rules).unwrap();
+
+    assert_eq!("1 categorized, 8 without a matching rule\n", categorized);
+    assert_eq!(
+        "Month,Currency,Count,Credits,Debits,Net,Average,Largest,Net Change\n\
+         2024-06,CAD,9,552.25,-4584.75,-4032.50,-448.06,-1500.00,\n",
+        months
+    );
+    assert_eq!(
+        "Month,Category,Name,Currency,Count,Credits,Debits,Net,Average,Largest\n\
+         ,bills,Bills,CAD,1,0.00,-1374.47,-1374.47,-1374.47,-1374.47\n\
+         ,other,Other,CAD,8,552.25,-3210.28,-2658.03,-332.25,-1500.00\n",
+        categories
+    );
+}
+
+#[test]
+fn test_budget() {
+    let db = TempDb::new("budget");
+    let budgets = env::temp_dir().join(format!(
+        "finance-tracker-{}-budgets.toml",
+        std::process::id()
+    ));
+    fs::write(
+        &budgets,
+        "[[budget]]\ncategory = \"other\"\nlimit = \"3000\"\n",
+    )
+    .unwrap();
+    db.run(&["import", TEST_FILE_PATH, "--account", "chequing"])
+        .unwrap();
+
+    let report = db.run(&[
+        "budget",
+        "--budgets",
+        budgets.to_str().unwrap(),
+        "--as-of",
+        "2024-06-15",
+        "--output",
+        "csv",
+    ]);
+    fs::remove_file(&budgets).unwrap();
+
+    assert_eq!(
+        "Category,Name,Period,Start,End,Currency,Available,Spent,Remaining,Used,Projected,State\n\
+         other,Other,monthly,2024-06-01,2024-06-30,CAD,3000.00,1165.14,1834.86,39%,2330.28,ok\n",
+        report.unwrap()
+    );
+}
+
+#[test]
+fn test_tax_report() {
+    let db = TempDb::new("tax");
+    let schedules = env::temp_dir().join(format!(
+        "finance-tracker-{}-schedules.toml",
+        std::process::id()
+    ));
+    let html = env::temp_dir().join(format!("finance-tracker-{}-tax.html", std::process::id()));
+    fs::write(
+        &schedules,
+        "[[schedule]]\nline = \"33099\"\nname = \"Medical expenses\"\ntags = [\"medical\"]\n\
+         [[schedule]]\nline = \"9945\"\nname = \"Home office\"\npayees = [\"CITY TAX\"]\n\
+         percent = 15\n",
+    )
+    .unwrap();
+    let schedules = schedules.to_str().unwrap();
+    db.run(&["import", TEST_FILE_PATH, "--account", "chequing"])
+        .unwrap();
+    db.run(&["tag", "5", "medical"]).unwrap();
+
+    let totals = db
+        .run(&[
+            "tax",
+            "--schedules",
+            schedules,
+            "--year",
+            "2024",
+            "--output",
+            "csv",
+        ])
+        .unwrap();
+    let transactions = db
+        .run(&[
+            "tax",
+            "--schedules",
+            schedules,
+            "--year",
+            "2024",
+            "--transactions",
+            "--output",
+            "csv",
+        ])
+        .unwrap();
+    let other_year = db
+        .run(&[
+            "tax",
+            "--schedules",
+            schedules,
+            "--year",
+            "2023",
+            "--output",
+            "csv",
+        ])
+        .unwrap();
+    let written = db
+        .run(&[
+            "tax",
+            "--schedules",
+            schedules,
+            "--year",
+            "2024",
+            "--html",
+            "--file",
+            html.to_str().unwrap(),
+        ])
+        .unwrap();
+    let document = fs::read_to_string(&html).unwrap();
+    fs::remove_file(schedules).unwrap();
+    fs::remove_file(&html).unwrap();
+
+    assert_eq!(
+        "Line,Name,Currency,Transactions,Amount,Deductible\n\
+         33099,Medical expenses,CAD,1,200.00,200.00\n\
+         9945,Home office,CAD,1,1167.36,175.10\n",
+        totals
+    );
+    assert_eq!(
+        "Line,Date,Description,Category,Amount,Currency,Deductible\n\
+         33099,2024-06-20,[IB] SHAUGHNES,other,-200.00,CAD,200.00\n\
+         9945,2024-06-25,[CW]CITY TAX,other,-1167.36,CAD,175.10\n",
+        transactions
+    );
+    assert_eq!(
+        "Line,Name,Currency,Transactions,Amount,Deductible\n",
+        other_year
+    );
+    assert_eq!("", written);
+    assert!(document.contains("<h1>Tax report 2024</h1>"));
+    assert!(document.contains("<td>Home office</td>"));
+}
+
+#[test]
+fn test_forecast() {
+    let db = TempDb::new("forecast");
+    let schedule = env::temp_dir().join(format!(
+        "finance-tracker-{}-schedule.toml",
+        std::process::id()
+    ));
+    fs::write(
+        &schedule,
+        "[[scheduled]]\ndescription = \"[CW]CITY TAX\"\namount = \"-1167.36\"\ndate = \"2024-07-02\"\n",
+    )
+    .unwrap();
+
+    let report = db.run(&[
+        "forecast",
+        "--balance",
+        "1000",
+        "--as-of",
+        "2024-06-30",
+        "--months",
+        "1",
+        "--schedule",
+        schedule.to_str().unwrap(),
+    ]);
+    fs::remove_file(&schedule).unwrap();
+
+    let report = report.unwrap();
+    assert!(report.contains("2024-07-02  CAD       -1167.36  -167.36  [CW]CITY TAX -1167.36"));
+    assert!(report
+        .ends_with("\nLowest balance: -167.36 CAD on 2024-07-02\nOverdrawn from 2024-07-02\n"));
+}
+
+#[test]
+fn test_accounts_by_card_number() {
+    let db = TempDb::new("accounts");
+
+    let unknown = db.run(&["import", TEST_FILE_PATH]);
+    db.run(&[
+        "account",
+        "add",
+        "chequing",
+        "--number",
+        "'6007620712733055'",
+        "--type",
+        "chequing",
+        "--opening-balance",
+        "5000",
+    ])
+    .unwrap();
+    db.run(&["account", "add", "visa", "--type", "credit-card"])
+        .unwrap();
+    let imported = db.run(&["import", TEST_FILE_PATH]).unwrap();
+    let accounts = db.run(&["account", "list", "--output", "csv"]).unwrap();
+    let report = db
+        .run(&["report", "--by", "account", "--output", "csv"])
+        .unwrap();
+
+    assert!(unknown.is_err());
+    assert_eq!(
+        format!(
+            "{TEST_FILE_PATH}: 9 imported, 0 duplicates, 0 skipped \
+             (first-bank, 2024-06-03 to 2024-06-28)\n"
+        ),
+        imported
+    );
+    assert_eq!(
+        "ID,Number,Institution,Type,Currency,Opening,Count,Balance,Valid As Of\n\
+         chequing,************3055,,chequing,CAD,5000.00,9,967.50,2024-07-14 16:48:14\n\
+         visa,,,credit_card,CAD,0.00,0,0.00,\n",
+        accounts
+    );
+    assert_eq!(
+        "Account,Currency,Count,Credits,Debits,Net,Average,Largest\n\
+         chequing,CAD,9,552.25,-4584.75,-4032.50,-448.06,-1500.00\n",
+        report
+    );
+}
+
+#[test]
+fn test_transfers() {
+    let db = TempDb::new("transfers");
+    let savings = env::temp_dir().join(format!(
+        "finance-tracker-{}-savings.csv",
+        std::process::id()
+    ));
+    fs::write(
+        &savings,
+        "First Bank Card,Transaction Type,Date Posted, Transaction Amount,Description\n\
+         '6007620799990001',CREDIT,20240624,1500.0,[CW] TF 000123456789\n\
+         '6007620799990001',CREDIT,20240628,200.0,DEPOSIT\n",
+    )
+    .unwrap();
+    db.run(&["import", TEST_FILE_PATH, "--account", "chequing"])
+        .unwrap();
+    db.run(&["import", savings.to_str().unwrap(), "--account", "savings"])
+        .unwrap();
+    fs::remove_file(&savings).unwrap();
+
+    let matched = db.run(&["transfers", "match"]).unwrap();
+    let again = db.run(&["transfers", "match", "--window", "10"]).unwrap();
+    let review = db.run(&["transfers", "review", "--output", "csv"]).unwrap();
+    let confirmed = db.run(&["transfers", "confirm", "2"]).unwrap();
+    let report = db.run(&["report", "--output", "csv"]).unwrap();
+
+    assert_eq!("1 transfers matched, 0 to review\n", matched);
+    assert_eq!("0 transfers matched, 1 to review\n", again);
+    assert_eq!(
+        "ID,Status,Confidence,From,Date,Amount,Description,To,Date,Amount,Description\n\
+         2,review,0%,chequing,2024-06-20,-200.00,[IB] SHAUGHNES,savings,2024-06-28,200.00,DEPOSIT\n",
+        review
+    );
+    assert_eq!("transfer 2 confirmed\n", confirmed);
+    assert_eq!(
+        "Month,Currency,Count,Credits,Debits,Net,Average,Largest,Net Change\n\
+         2024-06,CAD,7,552.25,-2884.75,-2332.50,-333.21,-1374.47,\n",
+        report
+    );
+}
+
+#[test]
+fn test_reconcile() {
+    let db = TempDb::new("reconcile");
+    db.run(&[
+        "account",
+        "add",
+        "chequing",
+        "--number",
+        "6007620712733055",
+        "--opening-balance",
+        "5000",
+    ])
+    .unwrap();
+    db.run(&["import", TEST_FILE_PATH]).unwrap();
+
+    let balanced = db
+        .run(&[
+            "reconcile",
+            "--account",
+            "chequing",
+            "--closing",
+            "2024-06-30=967.50",
+        ])
+        .unwrap();
+    let diverged = db.run(&[
+        "reconcile",
+        "--account",
+        "chequing",
+        "--closing",
+        "2024-06-30=1047.50",
+    ]);
+
+    assert!(balanced.contains("2024-06-03  CAD       -1374.47  3625.53"));
+    assert!(balanced.ends_with("\nclosing balance 967.50 CAD, 1 of 1 checks agree\n"));
+    assert_eq!(
+        "account chequing does not reconcile",
+        diverged.unwrap_err().to_string()
+    );
+}
@@ -0,0 +1,439 @@
+use std::{cmp::Ordering, error::Error, fmt, fs};
+
+use chrono::NaiveDate;
+use csv::{ReaderBuilder, StringRecord};
+
+use crate::money::{Currency, Money};
+
+use super::format::AmountLayout;
+use super::{line_of, parse_bytes, ParseError, ParseMode, StatementFormat};
+
+/// Number of lines read from the top of a file to detect its format
+const SAMPLE_LINES: usize = 200;
+
+/// Delimiters tried when sniffing a file
+const DELIMITERS: [char; 4] = [',', ';', '\t', '|'];
+
+/// Date formats tried when sniffing a file, in order of preference
+const DATE_FORMATS: [&str; 8] = [
+    "%Y%m%d", "%Y-%m-%d", "%Y/%m/%d", "%d/%m/%Y", "%m/%d/%Y", "%d-%m-%Y", "%m-%d-%Y", "%d.%m.%Y",
+];
+
+/// Best candidates closer than this are reported as ambiguous
+const AMBIGUITY_MARGIN: f64 = 0.1;
+
+/// Candidates below this confidence are never picked
+const MIN_CONFIDENCE: f64 = 0.5;
+
+/// The amount layout observed in a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AmountConvention {
+    /// One column holding negative and positive amounts
+    Signed { column: usize },
+    /// Two columns of which exactly one is filled on every row
+    DebitCredit { first: usize, second: usize },
+}
+
+/// What could be learned about a file without knowing its format
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Sniff {
+    pub delimiter: Option<char>,
+    /// 1-based line and cells of the row that looks like a header
+    pub header: Option<(u64, Vec<String>)>,
+    /// Date formats that parse every value of the date column, best first. More than one when the
+    /// sample cannot tell them apart, e.g. `%d/%m/%Y` and `%m/%d/%Y` with days up to the 12th.
+    pub date_formats: Vec<String>,
+    pub amount: Option<AmountConvention>,
+}
+
+/// How well one `StatementFormat` fits a file
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub format: StatementFormat,
+    /// Between 0 and 1
+    pub confidence: f64,
+    /// Sampled rows the format parses
+    pub rows: usize,
+    /// Sampled rows the format fails to parse
+    pub errors: usize,
+    /// Why the confidence is lowered, for the report
+    pub notes: Vec<String>,
+}
+
+/// The outcome of `detect_format`, candidates sorted from most to least likely
+#[derive(Debug, Clone)]
+pub struct Detection {
+    pub file: String,
+    pub sniff: Sniff,
+    pub candidates: Vec<Candidate>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DetectError {
+    /// No format parses the file with enough confidence
+    NoMatch { file: String },
+    /// Several formats parse the file almost equally well
+    Ambiguous { file: String, names: Vec<String> },
+}
+
+impl fmt::Display for DetectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DetectError::NoMatch { file } => write!(f, "{file}: no known statement format matches"),
+            DetectError::Ambiguous { file, names } => write!(
+                f,
+                "{file}: statement format is ambiguous between {}, pick one explicitly",
+                names.join(", ")
+            ),
+        }
+    }
+}
+
+impl Error for DetectError {}
+
+impl Detection {
+    /// The format to use, unless no candidate is confident enough or the best ones are too close
+    pub fn best(&self) -> Result<&StatementFormat, DetectError> {
+        let confident: Vec<&Candidate> = self
+            .candidates
+            .iter()
+            .filter(|candidate| candidate.confidence >= MIN_CONFIDENCE)
+            .collect();
+        match confident.as_slice() {
+            [] => Err(DetectError::NoMatch {
+                file: self.file.clone(),
+            }),
+            [best, rest @ ..] => {
+                let close: Vec<String> = rest
+                    .iter()
+                    .take_while(|other| best.confidence - other.confidence < AMBIGUITY_MARGIN)
+                    .map(|other| other.format.name.clone())
+                    .collect();
+                if close.is_empty() {
+                    Ok(&best.format)
+                } else {
+                    Err(DetectError::Ambiguous {
+                        file: self.file.clone(),
+                        names: std::iter::once(best.format.name.clone())
+                            .chain(close)
+                            .collect(),
+                    })
+                }
+            }
+        }
+    }
+}
+
+/// A human readable summary of the detection, listing every candidate
+impl fmt::Display for Detection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.file)?;
+        let sniff = &self.sniff;
+        match sniff.delimiter {
+            Some(delimiter) => writeln!(f, "  delimiter: {delimiter:?}")?,
+            None => writeln!(f, "  delimiter: unknown")?,
+        }
+        match &sniff.header {
+            Some((line, cells)) => writeln!(f, "  header: line {line}: {}", cells.join(" | "))?,
+            None => writeln!(f, "  header: none")?,
+        }
+        if sniff.date_formats.is_empty() {
+            writeln!(f, "  date format: unknown")?;
+        } else {
+            writeln!(f, "  date format: {}", sniff.date_formats.join(" or "))?;
+        }
+        match sniff.amount {
+            Some(AmountConvention::Signed { column }) => {
+                writeln!(f, "  amounts: signed, column {column}")?
+            }
+            Some(AmountConvention::DebitCredit { first, second }) => {
+                writeln!(f, "  amounts: debit/credit, columns {first} and {second}")?
+            }
+            None => writeln!(f, "  amounts: unknown")?,
+        }
+        for candidate in &self.candidates {
+            writeln!(
+                f,
+                "  {:>5.1}% {} ({} rows parsed, {} rejected)",
+                candidate.confidence * 100.0,
+                candidate.format.name,
+                candidate.rows,
+                candidate.errors
+            )?;
+            for note in &candidate.notes {
+                writeln!(f, "         {note}")?;
+            }
+        }
+        match self.best() {
+            Ok(format) => write!(f, "  => {}", format.name),
+            Err(e) => write!(f, "  => {e}"),
+        }
+    }
+}
+
+/// Guesses which of `formats` a statement file is laid out in
+///
+/// The top of the file is sniffed for its delimiter, header row, date format and amount convention,
+/// then every format is tried on it. A format's confidence is the share of rows it parses, lowered
+/// when its delimiter, date format or amount columns disagree with what was sniffed. Use
+/// `Detection::best` to pick the winner.
+pub fn detect_format(
+    file_path: &str,
+    formats: &[StatementFormat],
+) -> Result<Detection, ParseError> {
+    let contents = fs::read(file_path).map_err(|source| ParseError::Io {
+        file: file_path.to_string(),
+        source,
+    })?;
+    let sample = sample(&contents);
+    let sniff = sniff(sample);
+
+    let mut candidates: Vec<Candidate> = formats
+        .iter()
+        .map(|format| score(file_path, sample, &sniff, format))
+        .collect();
+    candidates.sort_by(|a, b| {
+        b.confidence
+            .partial_cmp(&a.confidence)
+            .unwrap_or(Ordering::Equal)
+    });
+
+    Ok(Detection {
+        file: file_path.to_string(),
+        sniff,
+        candidates,
+    })
+}
+
+/// The first `SAMPLE_LINES` lines of `contents`
+fn sample(contents: &[u8]) -> &[u8] {
+    let end = contents
+        .iter()
+        .enumerate()
+        .filter(|(_, byte)| **byte == b'\n')
+        .nth(SAMPLE_LINES - 1)
+        .map_or(contents.len(), |(index, _)| index + 1);
+    &contents[..end]
+}
+
+fn records(sample: &[u8], delimiter: char) -> Vec<StringRecord> {
+    ReaderBuilder::new()
+        .flexible(true)
+        .has_headers(false)
+        .delimiter(delimiter as u8)
+        .from_reader(sample)
+        .records()
+        .filter_map(Result::ok)
+        .filter(|record| record.iter().any(|field| !field.trim().is_empty()))
+        .collect()
+}
+
+fn sniff(sample: &[u8]) -> Sniff {
+    let Some(delimiter) = sniff_delimiter(sample) else {
+        return Sniff::default();
+    };
+    let records = records(sample, delimiter);
+
+    // The widest rows hold the transactions, anything narrower is preamble
+    let width = records.iter().map(StringRecord::len).max().unwrap_or(0);
+    let header = records.iter().position(|record| {
+        record.len() == width
+            && record
+                .iter()
+                .all(|field| field.chars().any(char::is_alphabetic))
+    });
+    let rows: Vec<&StringRecord> = records
+        .iter()
+        .skip(header.map_or(0, |index| index + 1))
+        .filter(|record| record.len() >= width.saturating_sub(1) && record.len() > 1)
+        .collect();
+
+    let date_formats = sniff_date_formats(&rows, width);
+    let amount = sniff_amount(&rows, width, &date_formats);
+
+    Sniff {
+        delimiter: Some(delimiter),
+        header: header.map(|index| {
+            let record = &records[index];
+            (
+                line_of(sample, record.position()),
+                record
+                    .iter()
+                    .map(|field| field.trim().to_string())
+                    .collect(),
+            )
+        }),
+        date_formats,
+        amount,
+    }
+}
+
+/// The delimiter splitting the most lines into the same number of fields
+fn sniff_delimiter(sample: &[u8]) -> Option<char> {
+    DELIMITERS
+        .iter()
+        .filter_map(|&delimiter| {
+            let widths: Vec<usize> = records(sample, delimiter)
+                .iter()
+                .map(StringRecord::len)
+                .filter(|width| *width > 1)
+                .collect();
+            let most_common = widths
+                .iter()
+                .map(|width| widths.iter().filter(|other| *other == width).count())
+                .max()?;
+            Some((delimiter, most_common))
+        })
+        .max_by_key(|(_, count)| *count)
+        .map(|(delimiter, _)| delimiter)
+}
+
+fn column<'a>(rows: &'a [&StringRecord], index: usize) -> impl Iterator<Item = &'a str> + 'a {
+    rows.iter()
+        .map(move |record| record.get(index).unwrap_or("").trim())
+        .filter(|field| !field.is_empty())
+}
+
+fn sniff_date_formats(rows: &[&StringRecord], width: usize) -> Vec<String> {
+    (0..width)
+        .find_map(|index| {
+            column(rows, index).next()?;
+            let formats: Vec<String> = DATE_FORMATS
+                .iter()
+                .filter(|format| {
+                    column(rows, index)
+                        .all(|field| NaiveDate::parse_from_str(field, format).is_ok())
+                })
+                .map(|format| format.to_string())
+                .collect();
+            (!formats.is_empty()).then_some(formats)
+        })
+        .unwrap_or_default()
+}
+
+fn sniff_amount(
+    rows: &[&StringRecord],
+    width: usize,
+    date_formats: &[String],
+) -> Option<AmountConvention> {
+    let is_amount = |field: &str| {
+        Money::parse(field, Currency::USD).is_ok()
+            && !date_formats
+                .iter()
+                .any(|format| NaiveDate::parse_from_str(field, format).is_ok())
+    };
+    let numeric: Vec<usize> = (0..width)
+        .filter(|&index| column(rows, index).next().is_some() && column(rows, index).all(is_amount))
+        .collect();
+
+    let signed = numeric.iter().find(|&&index| {
+        column(rows, index).count() == rows.len()
+            && column(rows, index).any(|field| field.starts_with('-'))
+    });
+    if let Some(&column) = signed {
+        return Some(AmountConvention::Signed { column });
+    }
+
+    numeric.windows(2).find_map(|pair| {
+        let exclusive = rows.iter().all(|record| {
+            let filled = |index: usize| !record.get(index).unwrap_or("").trim().is_empty();
+            filled(pair[0]) != filled(pair[1])
+        });
+        exclusive.then_some(AmountConvention::DebitCredit {
+            first: pair[0],
+            second: pair[1],
+        })
+    })
+}
+
+fn score(file_path: &str, sample: &[u8], sniff: &Sniff, format: &StatementFormat) -> Candidate {
+    let mut candidate = Candidate {
+        format: format.clone(),
+        confidence: 0.0,
+        rows: 0,
+        errors: 0,
+        notes: Vec::new(),
+    };
+
+    let output = match parse_bytes(file_path, sample, format, ParseMode::Lenient) {
+        Ok(output) => output,
+        Err(e) => {
+            candidate.notes.push(e.to_string());
+            return candidate;
+        }
+    };
+    candidate.rows = output.rows.len();
+    candidate.errors = output.diagnostics.len();
+    if candidate.rows == 0 {
+        candidate.notes.push(String::from("no transactions found"));
+        return candidate;
+    }
+
+    let mut confidence = candidate.rows as f64 / (candidate.rows + candidate.errors) as f64;
+    if let Some(first) = output.diagnostics.first() {
+        candidate.notes.push(first.to_string());
+    }
+    if sniff
+        .delimiter
+        .is_some_and(|delimiter| delimiter != format.delimiter)
+    {
+        confidence *= 0.5;
+        candidate
+            .notes
+            .push(format!("expects {:?} as the delimiter", format.delimiter));
+    }
+    if !sniff.date_formats.is_empty() && !sniff.date_formats.contains(&format.date_format) {
+        confidence *= 0.5;
+        candidate
+            .notes
+            .push(format!("expects dates as {}", format.date_format));
+    } else if sniff.date_formats.len() > 1 {
+        // The dates in the sample fit other formats just as well
+        confidence *= 0.9;
+    }
+    if let Some(expected) = amount_mismatch(sniff, format) {
+        confidence *= 0.5;
+        candidate
+            .notes
+            .push(format!("expects amounts in {expected}"));
+    }
+    if format.header_cells().is_empty() {
+        // Formats found by a header name are more specific than index-only ones
+        confidence *= 0.95;
+    }
+
+    candidate.confidence = confidence;
+    candidate
+}
+
+/// Where `format` reads amounts from, when that is not the column the sniff found them in
+///
+/// Named columns are looked up in the sniffed header, so two profiles that both parse the file can
+/// still be told apart by which of its numeric columns they take as the amount.
+fn amount_mismatch(sniff: &Sniff, format: &StatementFormat) -> Option<String> {
+    let sniffed = sniff.amount?;
+    let header = sniff
+        .header
+        .as_ref()
+        .map(|(_, cells)| StringRecord::from(cells.clone()));
+    let layout = format.resolve(header.as_ref()).ok()?;
+    match (layout.amount, sniffed) {
+        (AmountLayout::Signed { column, .. }, AmountConvention::Signed { column: found })
+            if column == found =>
+        {
+            None
+        }
+        (
+            AmountLayout::DebitCredit { debit, credit },
+            AmountConvention::DebitCredit { first, second },
+        ) if (debit.min(credit), debit.max(credit)) == (first, second) => None,
+        (AmountLayout::Signed { column, .. }, _) => Some(format!("column {column}")),
+        (AmountLayout::DebitCredit { debit, credit }, _) => {
+            Some(format!("columns {debit} and {credit}"))
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./tests/detect.rs"]
+mod test;
@@ -0,0 +1,119 @@
+use std::{error::Error, fmt, io};
+
+use crate::money::MoneyError;
+
+/// Everything that can go wrong while reading a statement
+///
+/// Row level variants carry the file, the 1-based line number, the 0-based column index and the
+/// raw text of the offending field so the problem can be located in the original export.
+#[derive(Debug)]
+pub enum ParseError {
+    /// The file could not be opened or read
+    Io { file: String, source: io::Error },
+    /// No row of the file is a header containing these columns
+    MissingHeader { file: String, columns: Vec<String> },
+    /// The CSV reader could not decode a record, e.g. because of invalid UTF-8
+    Record {
+        file: String,
+        line: u64,
+        source: csv::Error,
+    },
+    /// A data row is too short to contain a required field
+    MissingField {
+        file: String,
+        line: u64,
+        column: usize,
+        name: &'static str,
+    },
+    InvalidTransactionType {
+        file: String,
+        line: u64,
+        column: usize,
+        raw: String,
+    },
+    InvalidDate {
+        file: String,
+        line: u64,
+        column: usize,
+        raw: String,
+        expected_format: String,
+    },
+    InvalidAmount {
+        file: String,
+        line: u64,
+        column: usize,
+        raw: String,
+        source: MoneyError,
+    },
+}
+
+impl ParseError {
+    /// The line the error occurred on, `None` for errors affecting the whole file
+    pub fn line(&self) -> Option<u64> {
+        match self {
+            ParseError::Io { .. } | ParseError::MissingHeader { .. } => None,
+            ParseError::Record { line, .. }
+            | ParseError::MissingField { line, .. }
+            | ParseError::InvalidTransactionType { line, .. }
+            | ParseError::InvalidDate { line, .. }
+            | ParseError::InvalidAmount { line, .. } => Some(*line),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Io { file, source } => write!(f, "{file}: {source}"),
+            ParseError::MissingHeader { file, columns } => {
+                write!(f, "{file}: no header row with the columns {columns:?}")
+            }
+            ParseError::Record { file, line, source } => {
+                write!(f, "{file}:{line}: unreadable record: {source}")
+            }
+            ParseError::MissingField {
+                file,
+                line,
+                column,
+                name,
+            } => write!(f, "{file}:{line}: missing {name} in column {column}"),
+            ParseError::InvalidTransactionType {
+                file,
+                line,
+                column,
+                raw,
+            } => write!(
+                f,
+                "{file}:{line}: column {column}: expected transaction type 'DEBIT' or 'CREDIT', got {raw:?}"
+            ),
+            ParseError::InvalidDate {
+                file,
+                line,
+                column,
+                raw,
+                expected_format,
+            } => write!(
+                f,
+                "{file}:{line}: column {column}: expected a date in the format '{expected_format}', got {raw:?}"
+            ),
+            ParseError::InvalidAmount {
+                file,
+                line,
+                column,
+                raw,
+                source,
+            } => write!(f, "{file}:{line}: column {column}: {source} (got {raw:?})"),
+        }
+    }
+}
+
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParseError::Io { source, .. } => Some(source),
+            ParseError::Record { source, .. } => Some(source),
+            ParseError::InvalidAmount { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
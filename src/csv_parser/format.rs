@@ -0,0 +1,357 @@
+use std::{error::Error, fmt, fs, io, path::Path};
+
+use csv::StringRecord;
+use serde::Deserialize;
+
+use crate::money::Currency;
+
+use super::CharExtensions;
+
+/// Name of the built-in profile for the "First Bank Card" exports
+pub const FIRST_BANK: &str = "first-bank";
+
+/// A field of a statement, either by 0-based index or by its (case insensitive) header name
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum Column {
+    Index(usize),
+    Header(String),
+}
+
+/// How the amount of a transaction is spread over the columns of a statement
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "layout", rename_all = "snake_case", deny_unknown_fields)]
+pub enum AmountColumns {
+    /// A single column holding a signed amount
+    Signed {
+        column: Column,
+        #[serde(default)]
+        sign: SignConvention,
+    },
+    /// Separate columns for money going out and money coming in, one of them left empty
+    DebitCredit { debit: Column, credit: Column },
+}
+
+/// What the sign of a `AmountColumns::Signed` amount means
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignConvention {
+    /// Money going out is negative, e.g. `-1374.47` for a mortgage payment
+    #[default]
+    DebitNegative,
+    /// Money going out is positive, as in most credit card exports
+    DebitPositive,
+}
+
+/// How to find the header row of a statement
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeaderRule {
+    /// The header is the first row containing every column referenced by name. Files whose
+    /// columns are all given by index are read without a header.
+    #[default]
+    Auto,
+    /// The file has no header row
+    Absent,
+    /// The header is the first row containing all of these cells
+    Contains(Vec<String>),
+}
+
+/// Which of the rows after the header hold transactions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RowRule {
+    /// Every non-empty row
+    #[default]
+    All,
+    /// Rows whose account column only holds digits and quotes, e.g. `'6007620712733055'`.
+    /// Anything else, like a "Following data is valid as of..." banner, is skipped.
+    NumericAccount,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColumnMap {
+    #[serde(default)]
+    pub account: Option<Column>,
+    /// Column holding `DEBIT`/`CREDIT`. When absent the type is derived from the amount's sign.
+    #[serde(default)]
+    pub transaction_type: Option<Column>,
+    pub date: Column,
+    pub description: Column,
+    pub amount: AmountColumns,
+    /// Column holding the account balance after the transaction, if the export has one
+    #[serde(default)]
+    pub balance: Option<Column>,
+}
+
+/// Describes the layout of one bank's CSV export
+///
+/// The built-in profiles are listed by `StatementFormat::builtin`, more can be defined in a TOML
+/// file and read with `StatementFormat::load`:
+///
+/// ```toml
+/// [[format]]
+/// name = "credit-union"
+/// delimiter = ";"
+/// date_format = "%d/%m/%Y"
+/// currency = "CAD"
+///
+/// [format.columns]
+/// date = "Date"
+/// description = "Memo"
+/// amount = { layout = "debit_credit", debit = "Withdrawals", credit = "Deposits" }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StatementFormat {
+    pub name: String,
+    #[serde(default = "default_delimiter")]
+    pub delimiter: char,
+    /// A `chrono` format string, e.g. `%Y%m%d`
+    pub date_format: String,
+    #[serde(default = "default_currency")]
+    pub currency: Currency,
+    #[serde(default)]
+    pub header: HeaderRule,
+    #[serde(default)]
+    pub rows: RowRule,
+    pub columns: ColumnMap,
+}
+
+fn default_delimiter() -> char {
+    ','
+}
+
+fn default_currency() -> Currency {
+    Currency::CAD
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FormatFile {
+    #[serde(default)]
+    format: Vec<StatementFormat>,
+}
+
+impl StatementFormat {
+    /// The "First Bank Card, Transaction Type, Date Posted, Transaction Amount, Description" layout
+    pub fn first_bank() -> StatementFormat {
+        StatementFormat {
+            name: FIRST_BANK.to_string(),
+            delimiter: ',',
+            date_format: String::from("%Y%m%d"),
+            currency: Currency::CAD,
+            header: HeaderRule::Absent,
+            rows: RowRule::NumericAccount,
+            columns: ColumnMap {
+                account: Some(Column::Index(0)),
+                transaction_type: Some(Column::Index(1)),
+                date: Column::Index(2),
+                amount: AmountColumns::Signed {
+                    column: Column::Index(3),
+                    sign: SignConvention::DebitNegative,
+                },
+                description: Column::Index(4),
+                balance: None,
+            },
+        }
+    }
+
+    /// Every profile shipped with the crate
+    pub fn builtin() -> Vec<StatementFormat> {
+        vec![StatementFormat::first_bank()]
+    }
+
+    /// Reads the `[[format]]` tables of a TOML file
+    pub fn load(path: impl AsRef<Path>) -> Result<Vec<StatementFormat>, FormatError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|source| FormatError::Io {
+            file: path.display().to_string(),
+            source,
+        })?;
+        StatementFormat::from_toml(&text)
+    }
+
+    pub fn from_toml(text: &str) -> Result<Vec<StatementFormat>, FormatError> {
+        let file: FormatFile = toml::from_str(text).map_err(FormatError::Toml)?;
+        for format in &file.format {
+            format.validate()?;
+        }
+        Ok(file.format)
+    }
+
+    fn validate(&self) -> Result<(), FormatError> {
+        let invalid = |reason: &str| {
+            Err(FormatError::Invalid {
+                name: self.name.clone(),
+                reason: reason.to_string(),
+            })
+        };
+        if !self.delimiter.is_ascii() {
+            return invalid("the delimiter must be an ASCII character");
+        }
+        if self.header == HeaderRule::Absent && !self.names().is_empty() {
+            return invalid("columns can only be given by name when the file has a header");
+        }
+        if self.rows == RowRule::NumericAccount && self.columns.account.is_none() {
+            return invalid("the numeric_account row rule needs an account column");
+        }
+        Ok(())
+    }
+
+    /// Every column of the profile that is referenced by header name
+    fn names(&self) -> Vec<&str> {
+        let columns = &self.columns;
+        let amount: Vec<&Column> = match &columns.amount {
+            AmountColumns::Signed { column, .. } => vec![column],
+            AmountColumns::DebitCredit { debit, credit } => vec![debit, credit],
+        };
+        [
+            &columns.account,
+            &columns.transaction_type,
+            &columns.balance,
+        ]
+        .into_iter()
+        .flatten()
+        .chain([&columns.date, &columns.description])
+        .chain(amount)
+        .filter_map(|column| match column {
+            Column::Header(name) => Some(name.as_str()),
+            Column::Index(_) => None,
+        })
+        .collect()
+    }
+
+    /// The cells identifying the header row, empty when the file has no header
+    pub(crate) fn header_cells(&self) -> Vec<String> {
+        match &self.header {
+            HeaderRule::Auto => self.names().into_iter().map(str::to_string).collect(),
+            HeaderRule::Absent => Vec::new(),
+            HeaderRule::Contains(cells) => cells.clone(),
+        }
+    }
+
+    /// Whether `record` is the header row described by `cells`
+    pub(crate) fn is_header(cells: &[String], record: &StringRecord) -> bool {
+        cells.iter().all(|cell| {
+            record
+                .iter()
+                .any(|field| field.trim().eq_ignore_ascii_case(cell.trim()))
+        })
+    }
+
+    /// Maps every column of the profile to an index, using `header` for named columns
+    pub(crate) fn resolve(&self, header: Option<&StringRecord>) -> Result<Layout, Vec<String>> {
+        let mut missing = Vec::new();
+        let mut index = |column: &Column| match column {
+            Column::Index(index) => *index,
+            Column::Header(name) => header
+                .and_then(|header| {
+                    header
+                        .iter()
+                        .position(|field| field.trim().eq_ignore_ascii_case(name.trim()))
+                })
+                .unwrap_or_else(|| {
+                    missing.push(name.clone());
+                    usize::MAX
+                }),
+        };
+
+        let columns = &self.columns;
+        let layout = Layout {
+            account: columns.account.as_ref().map(&mut index),
+            transaction_type: columns.transaction_type.as_ref().map(&mut index),
+            date: index(&columns.date),
+            description: index(&columns.description),
+            balance: columns.balance.as_ref().map(&mut index),
+            amount: match &columns.amount {
+                AmountColumns::Signed { column, sign } => AmountLayout::Signed {
+                    column: index(column),
+                    sign: *sign,
+                },
+                AmountColumns::DebitCredit { debit, credit } => AmountLayout::DebitCredit {
+                    debit: index(debit),
+                    credit: index(credit),
+                },
+            },
+        };
+
+        if missing.is_empty() {
+            Ok(layout)
+        } else {
+            Err(missing)
+        }
+    }
+
+    /// Whether a row after the header holds a transaction
+    pub(crate) fn is_data_row(&self, layout: &Layout, record: &StringRecord) -> bool {
+        if record.iter().all(|field| field.trim().is_empty()) {
+            return false;
+        }
+        match self.rows {
+            RowRule::All => true,
+            RowRule::NumericAccount => layout
+                .account
+                .and_then(|column| record.get(column))
+                .is_some_and(|account| account.chars().all(|c| c.is_numeric() || c.is_quote())),
+        }
+    }
+}
+
+/// A `ColumnMap` with every column resolved to its index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Layout {
+    pub account: Option<usize>,
+    pub transaction_type: Option<usize>,
+    pub date: usize,
+    pub description: usize,
+    pub balance: Option<usize>,
+    pub amount: AmountLayout,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AmountLayout {
+    Signed { column: usize, sign: SignConvention },
+    DebitCredit { debit: usize, credit: usize },
+}
+
+#[derive(Debug)]
+pub enum FormatError {
+    Io {
+        file: String,
+        source: io::Error,
+    },
+    Toml(toml::de::Error),
+    /// The profile can never match a file
+    Invalid {
+        name: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::Io { file, source } => write!(f, "{file}: {source}"),
+            FormatError::Toml(source) => write!(f, "invalid statement format file: {source}"),
+            FormatError::Invalid { name, reason } => {
+                write!(f, "invalid statement format {name:?}: {reason}")
+            }
+        }
+    }
+}
+
+impl Error for FormatError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FormatError::Io { source, .. } => Some(source),
+            FormatError::Toml(source) => Some(source),
+            FormatError::Invalid { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./tests/format.rs"]
+mod test;
@@ -0,0 +1,79 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use csv::StringRecord;
+use serde::Serialize;
+
+use crate::money::Money;
+
+/// Text introducing the export timestamp, e.g. `Following data is valid as of 20240714164814`
+const VALID_AS_OF: &str = "valid as of";
+/// Format of the export timestamp
+const VALID_AS_OF_FORMAT: &str = "%Y%m%d%H%M%S";
+
+/// What a statement says about itself, besides its transactions
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StatementMetadata {
+    /// When the bank exported the data, from a "valid as of" banner
+    pub valid_as_of: Option<NaiveDateTime>,
+    /// Non-empty lines before the first transaction, with their fields joined by spaces. The header
+    /// row is left out when the format finds it by its cells, formats reading columns by index
+    /// like `StatementFormat::first_bank` keep it here.
+    pub preamble: Vec<String>,
+    /// Date of the oldest transaction
+    pub first_date: Option<NaiveDate>,
+    /// Date of the newest transaction
+    pub last_date: Option<NaiveDate>,
+    /// Balance the bank reported for the account, e.g. the OFX `LEDGERBAL`
+    pub ledger_balance: Option<StatementBalance>,
+    /// Funds available to spend, e.g. the OFX `AVAILBAL`
+    pub available_balance: Option<StatementBalance>,
+}
+
+/// An account balance stated by the bank
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct StatementBalance {
+    pub amount: Money,
+    pub date: NaiveDate,
+}
+
+impl StatementMetadata {
+    /// The first and last dates with transactions
+    pub fn covered(&self) -> Option<(NaiveDate, NaiveDate)> {
+        self.first_date.zip(self.last_date)
+    }
+
+    /// Records a line of the preamble
+    pub(crate) fn read_preamble(&mut self, record: &StringRecord) {
+        let line = record
+            .iter()
+            .map(|field| field.trim_start_matches('\u{feff}').trim())
+            .filter(|field| !field.is_empty())
+            .collect::<Vec<&str>>()
+            .join(" ");
+        if line.is_empty() {
+            return;
+        }
+        if self.valid_as_of.is_none() {
+            self.valid_as_of = parse_valid_as_of(&line);
+        }
+        self.preamble.push(line);
+    }
+
+    /// Widens the covered date range to include `date`
+    pub(crate) fn cover(&mut self, date: NaiveDate) {
+        self.first_date = Some(self.first_date.map_or(date, |first| first.min(date)));
+        self.last_date = Some(self.last_date.map_or(date, |last| last.max(date)));
+    }
+}
+
+/// The timestamp following "valid as of" in `line`
+fn parse_valid_as_of(line: &str) -> Option<NaiveDateTime> {
+    // ASCII lowercasing keeps the byte offsets of `line`, full lowercasing may not
+    let start = line.to_ascii_lowercase().find(VALID_AS_OF)? + VALID_AS_OF.len();
+    let digits: String = line
+        .get(start..)?
+        .trim_start()
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    NaiveDateTime::parse_from_str(&digits, VALID_AS_OF_FORMAT).ok()
+}
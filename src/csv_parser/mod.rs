@@ -1,64 +1,358 @@
-use std::{error::Error, fs::File};
+use std::fs;
 
 use chrono::NaiveDate;
-use csv::ReaderBuilder;
+use csv::{Position, ReaderBuilder, StringRecord};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq)]
+use crate::account::mask_number;
+pub use crate::category::TransactionCategory;
+pub use crate::channel::TransactionChannel;
+use crate::money::{Currency, Money, MoneyError};
+
+pub mod detect;
+mod error;
+pub mod format;
+mod metadata;
+
+pub use detect::detect_format;
+pub use error::ParseError;
+pub use format::StatementFormat;
+pub use metadata::{StatementBalance, StatementMetadata};
+
+use format::{AmountLayout, Layout, SignConvention};
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum TransactionType {
     CREDIT,
     DEBIT,
 }
 
 impl TransactionType {
-    fn from_option_str(opt: Option<&str>) -> Result<TransactionType, String> {
-        match opt {
-            Some("DEBIT") => Ok(TransactionType::DEBIT),
-            Some("CREDIT") => Ok(TransactionType::CREDIT),
-            _ => Err(format!("Invalid transaction type provided: {:?}", opt)),
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionType::CREDIT => "CREDIT",
+            TransactionType::DEBIT => "DEBIT",
+        }
+    }
+
+    pub(crate) fn parse(str: &str) -> Option<TransactionType> {
+        match str.trim().to_ascii_uppercase().as_str() {
+            "DEBIT" | "DR" => Some(TransactionType::DEBIT),
+            "CREDIT" | "CR" => Some(TransactionType::CREDIT),
+            _ => None,
         }
     }
 }
 
-#[allow(dead_code)]
-#[derive(Debug, PartialEq)]
-pub enum TransactionCategory {
-    Food,
-    Utilities,
-    Bills,
-    Entertainment,
-    Transportation,
-    Healthcare,
-    Education,
-    AccountTransfers,
-    Other,
+/// The part of a split transaction filed under one category
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Split {
+    pub category: TransactionCategory,
+    pub amount: Money,
+    pub memo: Option<String>,
 }
 
-#[derive(Debug, PartialEq)]
+impl Split {
+    /// Parses `food=-110.00`, optionally followed by a memo, e.g. `food=-110.00=Groceries`
+    pub fn parse(text: &str, currency: Currency) -> Result<Split, String> {
+        let mut parts = text.splitn(3, '=');
+        let (Some(category), Some(amount)) = (parts.next(), parts.next()) else {
+            return Err(format!("expected CATEGORY=AMOUNT, got {text:?}"));
+        };
+        Ok(Split {
+            category: TransactionCategory::new(category.trim()),
+            amount: Money::parse(amount, currency).map_err(|e| e.to_string())?,
+            memo: parts
+                .next()
+                .map(|memo| memo.trim().to_string())
+                .filter(|memo| !memo.is_empty()),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Data {
+    /// Masked card or account number of the row, e.g. `************3055`. The full number is
+    /// never kept.
+    pub account_number: Option<String>,
     pub transaction_type: TransactionType,
     pub date: NaiveDate,
-    pub amount: f32,
+    pub amount: Money,
     pub description: String,
     pub category: TransactionCategory,
+    /// How the amount is divided between categories, empty unless the transaction is split.
+    /// The amounts add up to `amount`, and reports file each part under its own category
+    /// instead of `category`.
+    pub splits: Vec<Split>,
+    /// Account balance after the transaction, when the statement has a balance column
+    pub balance: Option<Money>,
+    /// Identifier the bank assigned to the transaction, e.g. the OFX `FITID`. Unique within the
+    /// account, so re-imports are recognized even when the description changes.
+    pub transaction_id: Option<String>,
+    /// When the transaction was made or took effect, if it differs from `date`, the day it
+    /// posted: the OFX user date or the CAMT value date
+    pub user_date: Option<NaiveDate>,
+    /// How the transaction was made, set from its channel code by `ChannelTable::apply`. `None`
+    /// until then and when the code is missing or unknown.
+    pub channel: Option<TransactionChannel>,
+    /// Name of the categorizer rule that assigned `category`, `None` while uncategorized
+    pub categorized_by: Option<String>,
+    /// Free-form labels added by the user, e.g. `vacation-2024`, sorted and without duplicates
+    pub tags: Vec<String>,
+    pub note: Option<String>,
+    /// Paths of local files attached by the user, e.g. receipts
+    pub attachments: Vec<String>,
 }
 
 impl Data {
-    fn parse_date(str: Option<&str>) -> NaiveDate {
-        match str {
-            Some(str) => NaiveDate::parse_from_str(str, "%Y%m%d")
-                .expect(format!("Attempted to parse date with NaiveDate: {:?}. Expected the format to be '%Y%m%d', e.g., '20240601'", str).as_str()),
-            None => NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+    /// An uncategorized transaction, a debit when `amount` is negative
+    pub fn new(date: NaiveDate, amount: Money, description: &str) -> Data {
+        Data {
+            account_number: None,
+            transaction_type: if amount.is_negative() {
+                TransactionType::DEBIT
+            } else {
+                TransactionType::CREDIT
+            },
+            date,
+            amount,
+            description: description.to_string(),
+            category: TransactionCategory::OTHER,
+            splits: Vec::new(),
+            balance: None,
+            transaction_id: None,
+            user_date: None,
+            channel: None,
+            categorized_by: None,
+            tags: Vec::new(),
+            note: None,
+            attachments: Vec::new(),
+        }
+    }
+
+    /// The bracketed channel code the description starts with, e.g. `DS` for `[DS]STRATA FEE`
+    pub fn channel_code(&self) -> Option<&str> {
+        crate::channel::channel_code(&self.description)
+    }
+
+    /// Whether `splits` is empty or its amounts add up to `amount`
+    pub fn splits_balance(&self) -> bool {
+        self.splits.is_empty()
+            || Money::checked_sum(
+                self.splits.iter().map(|split| split.amount),
+                self.amount.currency(),
+            ) == Ok(self.amount)
+    }
+
+    /// One row per split with its category and amount, or the transaction itself when it isn't
+    /// split. Split rows keep the description, followed by the split's memo.
+    pub fn allocations(&self) -> Vec<Data> {
+        if self.splits.is_empty() {
+            return vec![self.clone()];
         }
+        self.splits
+            .iter()
+            .map(|split| {
+                let description = match &split.memo {
+                    Some(memo) => format!("{} {memo}", self.description),
+                    None => self.description.clone(),
+                };
+                Data {
+                    transaction_type: if split.amount.is_negative() {
+                        TransactionType::DEBIT
+                    } else {
+                        TransactionType::CREDIT
+                    },
+                    amount: split.amount,
+                    description: description.trim().to_string(),
+                    category: split.category.clone(),
+                    splits: Vec::new(),
+                    balance: None,
+                    ..self.clone()
+                }
+            })
+            .collect()
+    }
+
+    fn parse_date(str: &str, format: &str) -> Result<NaiveDate, chrono::ParseError> {
+        NaiveDate::parse_from_str(str.trim(), format)
     }
 }
 
 trait CharExtensions {
-    fn is_quote(self) -> bool;
+    fn is_quote(&self) -> bool;
 }
 
 impl CharExtensions for char {
-    fn is_quote(self) -> bool {
-        self == '\'' || self == '\"'
+    fn is_quote(&self) -> bool {
+        *self == '\'' || *self == '\"'
+    }
+}
+
+/// How `parse_csv_with` treats rows that fail to parse
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// Stop at the first malformed row and return its error
+    #[default]
+    Strict,
+    /// Skip malformed rows, reporting each of them in `ParseOutput::diagnostics`
+    Lenient,
+}
+
+/// The result of parsing a statement with `parse_csv_with`
+#[derive(Debug, Default)]
+pub struct ParseOutput {
+    /// Successfully parsed rows, in file order
+    pub rows: Vec<Data>,
+    /// One entry per skipped row. Always empty in `ParseMode::Strict`.
+    pub diagnostics: Vec<ParseError>,
+    pub metadata: StatementMetadata,
+}
+
+/// A data row of the CSV file, used to attach the location to field errors
+struct Row<'a> {
+    file: &'a str,
+    line: u64,
+    record: &'a StringRecord,
+    format: &'a StatementFormat,
+    layout: &'a Layout,
+}
+
+impl Row<'_> {
+    fn field(&self, column: usize, name: &'static str) -> Result<&str, ParseError> {
+        self.record
+            .get(column)
+            .ok_or_else(|| ParseError::MissingField {
+                file: self.file.to_string(),
+                line: self.line,
+                column,
+                name,
+            })
+    }
+
+    fn invalid_amount(&self, column: usize, raw: &str, source: MoneyError) -> ParseError {
+        ParseError::InvalidAmount {
+            file: self.file.to_string(),
+            line: self.line,
+            column,
+            raw: raw.to_string(),
+            source,
+        }
+    }
+
+    /// The amount of the row, negative for money going out
+    fn amount(&self) -> Result<Money, ParseError> {
+        let currency = self.format.currency;
+        match self.layout.amount {
+            AmountLayout::Signed { column, sign } => {
+                let raw = self.field(column, "amount")?;
+                let amount = Money::parse(raw, currency)
+                    .and_then(|amount| match sign {
+                        SignConvention::DebitNegative => Ok(amount),
+                        SignConvention::DebitPositive => amount.checked_neg(),
+                    })
+                    .map_err(|source| self.invalid_amount(column, raw, source))?;
+                Ok(amount)
+            }
+            AmountLayout::DebitCredit { debit, credit } => {
+                // Trailing empty columns are often left out entirely
+                let raw_debit = self.record.get(debit).unwrap_or("").trim();
+                let raw_credit = self.record.get(credit).unwrap_or("").trim();
+                let parse = |column: usize, raw: &str| {
+                    Money::parse(raw, currency)
+                        .and_then(Money::checked_abs)
+                        .map_err(|source| self.invalid_amount(column, raw, source))
+                };
+                match (raw_debit.is_empty(), raw_credit.is_empty()) {
+                    (false, true) => parse(debit, raw_debit)?
+                        .checked_neg()
+                        .map_err(|source| self.invalid_amount(debit, raw_debit, source)),
+                    (true, false) => parse(credit, raw_credit),
+                    (false, false) => parse(credit, raw_credit)?
+                        .checked_sub(parse(debit, raw_debit)?)
+                        .map_err(|source| self.invalid_amount(credit, raw_credit, source)),
+                    (true, true) => Err(self.invalid_amount(
+                        debit,
+                        raw_debit,
+                        MoneyError::InvalidAmount(String::new()),
+                    )),
+                }
+            }
+        }
+    }
+
+    fn parse(&self) -> Result<Data, ParseError> {
+        let amount = self.amount()?;
+
+        let transaction_type = match self.layout.transaction_type {
+            Some(column) => {
+                let raw_type = self.field(column, "transaction type")?;
+                TransactionType::parse(raw_type).ok_or_else(|| {
+                    ParseError::InvalidTransactionType {
+                        file: self.file.to_string(),
+                        line: self.line,
+                        column,
+                        raw: raw_type.to_string(),
+                    }
+                })?
+            }
+            None if amount.is_negative() => TransactionType::DEBIT,
+            None => TransactionType::CREDIT,
+        };
+
+        let column = self.layout.date;
+        let raw_date = self.field(column, "date")?;
+        let date = Data::parse_date(raw_date, &self.format.date_format).map_err(|_| {
+            ParseError::InvalidDate {
+                file: self.file.to_string(),
+                line: self.line,
+                column,
+                raw: raw_date.to_string(),
+                expected_format: self.format.date_format.clone(),
+            }
+        })?;
+
+        let balance = match self.layout.balance {
+            Some(column) => {
+                let raw = self.field(column, "balance")?;
+                if raw.trim().is_empty() {
+                    None
+                } else {
+                    Some(
+                        Money::parse(raw, self.format.currency)
+                            .map_err(|source| self.invalid_amount(column, raw, source))?,
+                    )
+                }
+            }
+            None => None,
+        };
+        let account_number = match self.layout.account {
+            Some(column) => self.record.get(column).and_then(mask_number),
+            None => None,
+        };
+
+        Ok(Data {
+            account_number,
+            transaction_type,
+            date,
+            amount,
+            description: self
+                .field(self.layout.description, "description")?
+                .trim()
+                .to_string(),
+            // Assigned afterwards by `categorizer::Categorizer`
+            category: TransactionCategory::OTHER,
+            splits: Vec::new(),
+            balance,
+            transaction_id: None,
+            user_date: None,
+            channel: None,
+            categorized_by: None,
+            tags: Vec::new(),
+            note: None,
+            attachments: Vec::new(),
+        })
     }
 }
 
@@ -73,10 +367,13 @@ impl CharExtensions for char {
 /// ```csv
 /// First Bank Card, Transaction Type, Date Posted, Transaction Amount, Description
 /// ```
+/// Use `parse_csv_with` and a `StatementFormat` for exports laid out differently.
 ///
 /// # Returns
 ///
-/// A `Result` containing `<Vec<Data>` if the operation is successful, or a boxed `dyn Error` trait object if an error occurs
+/// A `Result` containing `<Vec<Data>` if the operation is successful, or the `ParseError` of the
+/// first malformed row. Use `parse_csv_with` and `ParseMode::Lenient` to skip malformed rows
+/// instead.
 ///
 /// # Example
 ///
@@ -94,53 +391,142 @@ impl CharExtensions for char {
 ///
 /// *Code*
 /// ```
-/// let file_path = "path/to/your/file.csv"
+/// use finance_tracker::csv_parser::parse_csv;
+///
+/// let file_path = "path/to/your/file.csv";
 /// let contents = parse_csv(file_path);
 /// ```
-pub fn parse_csv(file_path: &str) -> Result<Vec<Data>, Box<dyn Error>> {
-    let file = File::open(file_path)?;
-    let mut rdr = ReaderBuilder::new().flexible(true).from_reader(file);
-
-    let vec: Vec<Data> = rdr
-        .records()
-        .filter_map(|result| match result {
-            Ok(record) => {
-                let valid_record_len = record.len() >= 5;
-                let valid_first_item = record.get(0)
-                .unwrap_or("default")
-                .chars()
-                .all(|c| c.is_numeric() || c.is_quote());
-
-                if !valid_record_len || !valid_first_item {
-                    return None;
+pub fn parse_csv(file_path: &str) -> Result<Vec<Data>, ParseError> {
+    parse_csv_with(file_path, &StatementFormat::first_bank(), ParseMode::Strict)
+        .map(|output| output.rows)
+}
+
+/// Extracts data from a CSV file laid out as described by `format`
+///
+/// In `ParseMode::Lenient` a malformed row never aborts the import: it is left out of
+/// `ParseOutput::rows` and its error is added to `ParseOutput::diagnostics`. Failing to open the
+/// file or to find its header is an error in both modes.
+pub fn parse_csv_with(
+    file_path: &str,
+    format: &StatementFormat,
+    mode: ParseMode,
+) -> Result<ParseOutput, ParseError> {
+    let contents = fs::read(file_path).map_err(|source| ParseError::Io {
+        file: file_path.to_string(),
+        source,
+    })?;
+    parse_bytes(file_path, &contents, format, mode)
+}
+
+/// The 1-based line a record starts on
+///
+/// `csv` reports the position right after the previous record, which is too early when blank
+/// lines were skipped in between, so those are skipped here too.
+fn line_of(contents: &[u8], position: Option<&Position>) -> u64 {
+    let Some(position) = position else {
+        return 0;
+    };
+    let start =
+        usize::try_from(position.byte()).map_or(contents.len(), |byte| byte.min(contents.len()));
+    let blank_lines = contents[start..]
+        .iter()
+        .take_while(|byte| **byte == b'\n' || **byte == b'\r')
+        .filter(|byte| **byte == b'\n')
+        .count();
+    position.line() + blank_lines as u64
+}
+
+/// Parses statement contents already in memory, `file_path` is only used in errors
+fn parse_bytes(
+    file_path: &str,
+    contents: &[u8],
+    format: &StatementFormat,
+    mode: ParseMode,
+) -> Result<ParseOutput, ParseError> {
+    let mut rdr = ReaderBuilder::new()
+        .flexible(true)
+        .has_headers(false)
+        .delimiter(format.delimiter as u8)
+        .from_reader(contents);
+
+    let header_cells = format.header_cells();
+    let mut layout = if header_cells.is_empty() {
+        format.resolve(None).ok()
+    } else {
+        None
+    };
+
+    let mut output = ParseOutput::default();
+    // Whether the first data row was reached, ending the preamble
+    let mut in_data = false;
+    for result in rdr.records() {
+        let parsed = match result {
+            Ok(record) => match &layout {
+                // Skip the preamble until the header is found
+                None => {
+                    if StatementFormat::is_header(&header_cells, &record) {
+                        let resolved = format.resolve(Some(&record)).map_err(|columns| {
+                            ParseError::MissingHeader {
+                                file: file_path.to_string(),
+                                columns,
+                            }
+                        })?;
+                        layout = Some(resolved);
+                    } else {
+                        output.metadata.read_preamble(&record);
+                    }
+                    continue;
                 }
-                
-                if record.iter().any(|field| !field.is_empty()) {
-                    Some(Data {
-                        transaction_type: TransactionType::from_option_str(record.get(1))
-                            .expect("Expected TransactionType to be either 'DEBIT' or 'CREDIT'."),
-                        date: Data::parse_date(record.get(2)),
-                        amount: record.get(3).unwrap().parse::<f32>().unwrap_or(0.0),
-                        description: record
-                            .get(4)
-                            .unwrap_or("N/A")
-                            .to_string()
-                            .trim()
-                            .to_string(),
-                        category: TransactionCategory::Other, // TODO: Use the correct category for the data. (use chatgpt api call to organize it for you)
-                    })
-                } else {
-                    // Skip empty row (optional: log or handle empty row)
-                    None
+                Some(layout) => {
+                    if !format.is_data_row(layout, &record) {
+                        if !in_data {
+                            output.metadata.read_preamble(&record);
+                        }
+                        continue;
+                    }
+                    in_data = true;
+
+                    Row {
+                        file: file_path,
+                        line: line_of(contents, record.position()),
+                        record: &record,
+                        format,
+                        layout,
+                    }
+                    .parse()
                 }
+            },
+            Err(e) if e.is_io_error() => {
+                return Err(ParseError::Io {
+                    file: file_path.to_string(),
+                    source: e.into(),
+                })
             }
-            Err(e) => {
-                panic!("Error parsing CSV record: {e}")
+            Err(e) => Err(ParseError::Record {
+                file: file_path.to_string(),
+                line: line_of(contents, e.position()),
+                source: e,
+            }),
+        };
+
+        match (parsed, mode) {
+            (Ok(data), _) => {
+                output.metadata.cover(data.date);
+                output.rows.push(data);
             }
-        })
-        .collect();
+            (Err(e), ParseMode::Strict) => return Err(e),
+            (Err(e), ParseMode::Lenient) => output.diagnostics.push(e),
+        }
+    }
+
+    if layout.is_none() {
+        return Err(ParseError::MissingHeader {
+            file: file_path.to_string(),
+            columns: header_cells,
+        });
+    }
 
-    Ok(vec)
+    Ok(output)
 }
 
 #[cfg(test)]
@@ -0,0 +1,123 @@
+use super::*;
+
+const TEST_FILE_PATH: &str = "src/csv_parser/tests/test_statement.csv";
+const CREDIT_UNION_FILE_PATH: &str = "src/csv_parser/tests/test_credit_union.csv";
+const BALANCE_FILE_PATH: &str = "src/csv_parser/tests/test_balance.csv";
+
+const CREDIT_UNION_TOML: &str = r#"
+[[format]]
+name = "credit-union"
+delimiter = ";"
+date_format = "%d/%m/%Y"
+
+[format.columns]
+account = "Account"
+date = "Date"
+description = "Memo"
+amount = { layout = "debit_credit", debit = "Withdrawals", credit = "Deposits" }
+"#;
+
+fn formats() -> Vec<StatementFormat> {
+    let mut formats = StatementFormat::builtin();
+    formats.extend(StatementFormat::from_toml(CREDIT_UNION_TOML).unwrap());
+    formats
+}
+
+#[test]
+fn test_sniff_builtin_statement() {
+    let detection = detect_format(TEST_FILE_PATH, &formats()).unwrap();
+
+    assert_eq!(Some(','), detection.sniff.delimiter);
+    assert_eq!(
+        Some(4),
+        detection.sniff.header.as_ref().map(|(line, _)| *line)
+    );
+    assert_eq!(vec![String::from("%Y%m%d")], detection.sniff.date_formats);
+    assert_eq!(
+        Some(AmountConvention::Signed { column: 3 }),
+        detection.sniff.amount
+    );
+    assert_eq!("first-bank", detection.best().unwrap().name);
+}
+
+#[test]
+fn test_sniff_debit_credit_statement() {
+    let detection = detect_format(CREDIT_UNION_FILE_PATH, &formats()).unwrap();
+
+    assert_eq!(Some(';'), detection.sniff.delimiter);
+    assert_eq!(
+        Some(AmountConvention::DebitCredit {
+            first: 3,
+            second: 4
+        }),
+        detection.sniff.amount
+    );
+    assert_eq!("credit-union", detection.best().unwrap().name);
+    assert_eq!(0.0, detection.candidates[1].confidence);
+}
+
+#[test]
+fn test_ambiguous_detection() {
+    let mut copy = StatementFormat::first_bank();
+    copy.name = String::from("first-bank-copy");
+    let detection = detect_format(TEST_FILE_PATH, &[StatementFormat::first_bank(), copy]).unwrap();
+
+    assert_eq!(
+        Err(DetectError::Ambiguous {
+            file: TEST_FILE_PATH.to_string(),
+            names: vec![String::from("first-bank"), String::from("first-bank-copy")]
+        }),
+        detection.best()
+    );
+    assert!(detection.to_string().contains("ambiguous"));
+}
+
+#[test]
+fn test_no_match() {
+    let formats = StatementFormat::from_toml(CREDIT_UNION_TOML).unwrap();
+    let detection = detect_format(TEST_FILE_PATH, &formats).unwrap();
+
+    assert_eq!(
+        Err(DetectError::NoMatch {
+            file: TEST_FILE_PATH.to_string()
+        }),
+        detection.best()
+    );
+}
+
+#[test]
+fn test_sniffed_amount_column_breaks_ties() {
+    let formats = StatementFormat::from_toml(
+        r#"
+[[format]]
+name = "by-balance"
+date_format = "%Y-%m-%d"
+
+[format.columns]
+date = "Date"
+description = "Description"
+amount = { layout = "signed", column = "Balance" }
+
+[[format]]
+name = "by-amount"
+date_format = "%Y-%m-%d"
+
+[format.columns]
+date = "Date"
+description = "Description"
+amount = { layout = "signed", column = "Amount" }
+"#,
+    )
+    .unwrap();
+    let detection = detect_format(BALANCE_FILE_PATH, &formats).unwrap();
+
+    assert_eq!(
+        Some(AmountConvention::Signed { column: 2 }),
+        detection.sniff.amount
+    );
+    assert_eq!("by-amount", detection.best().unwrap().name);
+    assert_eq!(
+        vec![String::from("expects amounts in column 3")],
+        detection.candidates[1].notes
+    );
+}
@@ -0,0 +1,145 @@
+use super::*;
+use crate::csv_parser::{
+    parse_bytes, parse_csv, parse_csv_with, ParseError, ParseMode, TransactionType,
+};
+use crate::money::Money;
+
+const TEST_FILE_PATH: &str = "src/csv_parser/tests/test_statement.csv";
+const CREDIT_UNION_FILE_PATH: &str = "src/csv_parser/tests/test_credit_union.csv";
+
+const CREDIT_UNION_TOML: &str = r#"
+[[format]]
+name = "credit-union"
+delimiter = ";"
+date_format = "%d/%m/%Y"
+
+[format.columns]
+account = "Account"
+date = "Date"
+description = "Memo"
+amount = { layout = "debit_credit", debit = "Withdrawals", credit = "Deposits" }
+"#;
+
+#[test]
+fn test_builtin_matches_parse_csv() {
+    let output = parse_csv_with(
+        TEST_FILE_PATH,
+        &StatementFormat::first_bank(),
+        ParseMode::Strict,
+    )
+    .unwrap();
+
+    assert!(output.diagnostics.is_empty());
+    assert_eq!(parse_csv(TEST_FILE_PATH).unwrap(), output.rows);
+}
+
+#[test]
+fn test_from_toml() {
+    let formats = StatementFormat::from_toml(CREDIT_UNION_TOML).unwrap();
+
+    assert_eq!(1, formats.len());
+    let format = &formats[0];
+    assert_eq!("credit-union", format.name);
+    assert_eq!(';', format.delimiter);
+    assert_eq!(Currency::CAD, format.currency);
+    assert_eq!(HeaderRule::Auto, format.header);
+    assert_eq!(
+        Some(Column::Header(String::from("Account"))),
+        format.columns.account
+    );
+}
+
+#[test]
+fn test_parse_debit_credit_columns() {
+    let format = &StatementFormat::from_toml(CREDIT_UNION_TOML).unwrap()[0];
+    let rows = parse_csv_with(CREDIT_UNION_FILE_PATH, format, ParseMode::Strict)
+        .unwrap()
+        .rows;
+
+    let amounts: Vec<(TransactionType, i64)> = rows
+        .into_iter()
+        .map(|data| (data.transaction_type, data.amount.minor_units()))
+        .collect();
+    assert_eq!(
+        vec![
+            (TransactionType::DEBIT, -137447),
+            (TransactionType::CREDIT, 215000),
+            (TransactionType::DEBIT, -495),
+        ],
+        amounts
+    );
+}
+
+#[test]
+fn test_parse_signed_debit_positive() {
+    let mut format = StatementFormat::from_toml(CREDIT_UNION_TOML)
+        .unwrap()
+        .remove(0);
+    format.columns.amount = AmountColumns::Signed {
+        column: Column::Header(String::from("Withdrawals")),
+        sign: SignConvention::DebitPositive,
+    };
+    let output = parse_csv_with(CREDIT_UNION_FILE_PATH, &format, ParseMode::Lenient).unwrap();
+
+    assert_eq!(
+        Some(Money::new(-137447, Currency::CAD)),
+        output.rows.first().map(|data| data.amount)
+    );
+    // The deposit row has no withdrawal amount
+    assert_eq!(1, output.diagnostics.len());
+}
+
+#[test]
+fn test_parse_balance_column() {
+    let mut format = StatementFormat::from_toml(CREDIT_UNION_TOML)
+        .unwrap()
+        .remove(0);
+    format.columns.balance = Some(Column::Header(String::from("Balance")));
+    let contents = "Account;Date;Memo;Withdrawals;Deposits;Balance\n\
+                    12-3456;03/06/2024;MORTGAGE PAYMENT;1374.47;;3625.53\n\
+                    12-3456;11/06/2024;PAYROLL DEPOSIT;;2150.00;\n";
+
+    let rows = parse_bytes("memory", contents.as_bytes(), &format, ParseMode::Strict)
+        .unwrap()
+        .rows;
+
+    assert_eq!(Some(Money::new(362553, Currency::CAD)), rows[0].balance);
+    assert_eq!(None, rows[1].balance);
+    assert_eq!(Some(String::from("**3456")), rows[0].account_number);
+}
+
+#[test]
+fn test_missing_header() {
+    let format = &StatementFormat::from_toml(CREDIT_UNION_TOML).unwrap()[0];
+
+    match parse_csv_with(TEST_FILE_PATH, format, ParseMode::Lenient) {
+        Err(ParseError::MissingHeader { columns, .. }) => {
+            assert!(columns.contains(&String::from("Memo")))
+        }
+        other => panic!("Expected 'ParseError::MissingHeader'. Got {other:?}"),
+    }
+}
+
+#[test]
+fn test_invalid_profiles() {
+    let names_without_header = r#"
+[[format]]
+name = "broken"
+date_format = "%Y-%m-%d"
+header = "absent"
+
+[format.columns]
+date = "Date"
+description = 1
+amount = { layout = "signed", column = 2 }
+"#;
+
+    assert!(matches!(
+        StatementFormat::from_toml(names_without_header),
+        Err(FormatError::Invalid { name, .. }) if name == "broken"
+    ));
+    assert!(matches!(
+        StatementFormat::from_toml("[[format]]\nname = \"incomplete\""),
+        Err(FormatError::Toml(_))
+    ));
+}
@@ -1,7 +1,10 @@
 use super::*;
 use rstest::rstest;
 
+use crate::money::{Currency, Money};
+
 const TEST_FILE_PATH: &str = "src/csv_parser/tests/test_statement.csv";
+const MALFORMED_FILE_PATH: &str = "src/csv_parser/tests/test_malformed.csv";
 
 #[test]
 fn test_parse_csv() {
@@ -14,13 +17,23 @@ fn test_parse_csv() {
             );
             assert_eq!(
                 Data {
+                    account_number: Some(String::from("************3055")),
                     transaction_type: TransactionType::DEBIT,
                     date: NaiveDate::from_ymd_opt(2024, 6, 3).unwrap(),
-                    amount: -1374.47,
+                    amount: Money::new(-137447, Currency::CAD),
                     description: String::from("[DS]BANK         MTG/HYP"),
-                    category: TransactionCategory::Other
+                    category: TransactionCategory::OTHER,
+                    splits: Vec::new(),
+                    balance: None,
+                    transaction_id: None,
+                    user_date: None,
+                    channel: None,
+                    categorized_by: None,
+                    tags: Vec::new(),
+                    note: None,
+                    attachments: Vec::new(),
                 },
-                *data.get(0).unwrap()
+                *data.first().unwrap()
             )
         }
         Err(e) => {
@@ -29,27 +42,193 @@ fn test_parse_csv() {
     }
 }
 
-#[rstest]
-#[case(Some(String::from("20240610")))]
-#[case(None)]
-fn test_parse_date(#[case] date: Option<String>) {
-    match date {
-        Some(date) => assert_eq!(
-            NaiveDate::from_ymd_opt(2024, 6, 10).unwrap(),
-            Data::parse_date(Some(date.as_str()))
-        ),
-        None => assert_eq!(
-            NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
-            Data::parse_date(date.as_deref())
-        ),
+#[test]
+fn test_parse_csv_missing_file() {
+    match parse_csv("does/not/exist.csv") {
+        Err(ParseError::Io { file, .. }) => assert_eq!("does/not/exist.csv", file),
+        other => panic!("Expected 'ParseError::Io'. Got {other:?}"),
+    }
+}
+
+#[test]
+fn test_parse_csv_strict_stops_at_first_error() {
+    match parse_csv(MALFORMED_FILE_PATH) {
+        Err(ParseError::InvalidTransactionType {
+            file,
+            line,
+            column,
+            raw,
+        }) => {
+            assert_eq!(MALFORMED_FILE_PATH, file);
+            assert_eq!(7, line);
+            assert_eq!(1, column);
+            assert_eq!("REFUND", raw);
+        }
+        other => panic!("Expected 'ParseError::InvalidTransactionType'. Got {other:?}"),
     }
 }
 
 #[test]
-#[should_panic(
-    expected = "Attempted to parse date with NaiveDate: \"05/01/2024\""
-)]
-fn test_parse_date_panic() {
-    const INVALID_DATE_FORMAT: &str = "05/01/2024";
-    Data::parse_date(Some(INVALID_DATE_FORMAT));
+fn test_parse_csv_lenient_collects_diagnostics() {
+    let output = parse_csv_with(
+        MALFORMED_FILE_PATH,
+        &StatementFormat::first_bank(),
+        ParseMode::Lenient,
+    )
+    .unwrap();
+
+    let descriptions: Vec<&str> = output
+        .rows
+        .iter()
+        .map(|data| data.description.as_str())
+        .collect();
+    assert_eq!(
+        vec!["[DS]BANK         MTG/HYP", "[IB] SHAUGHNES"],
+        descriptions
+    );
+
+    let lines: Vec<Option<u64>> = output.diagnostics.iter().map(ParseError::line).collect();
+    assert_eq!(vec![Some(7), Some(8), Some(9)], lines);
+    assert!(matches!(
+        &output.diagnostics[1],
+        ParseError::InvalidDate { column: 2, raw, .. } if raw == "06/10/2024"
+    ));
+    assert!(matches!(
+        &output.diagnostics[2],
+        ParseError::InvalidAmount { column: 3, raw, .. } if raw == "n/a"
+    ));
+}
+
+#[test]
+fn test_statement_metadata() {
+    let output = parse_csv_with(
+        TEST_FILE_PATH,
+        &StatementFormat::first_bank(),
+        ParseMode::Strict,
+    )
+    .unwrap();
+    let metadata = output.metadata;
+
+    assert_eq!(
+        NaiveDate::from_ymd_opt(2024, 7, 14).and_then(|date| date.and_hms_opt(16, 48, 14)),
+        metadata.valid_as_of
+    );
+    assert_eq!(
+        "Following data is valid as of 20240714164814 (Year/Month/Day/Hour/Minute/Second)",
+        metadata.preamble[0]
+    );
+    // The format reads columns by index, its header row is just another preamble line
+    assert_eq!(
+        vec![
+            metadata.preamble[0].as_str(),
+            "First Bank Card Transaction Type Date Posted Transaction Amount Description"
+        ],
+        metadata.preamble
+    );
+    assert_eq!(
+        Some((
+            NaiveDate::from_ymd_opt(2024, 6, 3).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 6, 28).unwrap()
+        )),
+        metadata.covered()
+    );
+}
+
+#[test]
+fn test_statement_metadata_without_banner() {
+    let output = parse_bytes(
+        "export.csv",
+        b"Account,Note\nvalid as of yesterday\n",
+        &StatementFormat::first_bank(),
+        ParseMode::Lenient,
+    )
+    .unwrap();
+
+    assert_eq!(None, output.metadata.valid_as_of);
+    assert_eq!(2, output.metadata.preamble.len());
+    assert_eq!(None, output.metadata.covered());
+}
+
+#[test]
+fn test_statement_metadata_after_non_ascii_text() {
+    // Lowercased, every `İ` takes a byte more
+    let output = parse_bytes(
+        "export.csv",
+        "İİİİ BANKASI Valid As Of 20240714164814\n".as_bytes(),
+        &StatementFormat::first_bank(),
+        ParseMode::Lenient,
+    )
+    .unwrap();
+
+    assert_eq!(
+        NaiveDate::from_ymd_opt(2024, 7, 14).and_then(|date| date.and_hms_opt(16, 48, 14)),
+        output.metadata.valid_as_of
+    );
+}
+
+#[rstest]
+#[case("20240610", Some(NaiveDate::from_ymd_opt(2024, 6, 10).unwrap()))]
+#[case("05/01/2024", None)]
+#[case("", None)]
+fn test_parse_date(#[case] date: &str, #[case] expected: Option<NaiveDate>) {
+    assert_eq!(expected, Data::parse_date(date, "%Y%m%d").ok());
+}
+
+#[rstest]
+#[case("[DS]STRATA FEE", Some("DS"))]
+#[case("[IB] SHAUGHNES", Some("IB"))]
+#[case("INTERAC ETRNSFR", None)]
+#[case("[CW INTERAC", None)]
+fn test_channel_code(#[case] description: &str, #[case] expected: Option<&str>) {
+    let mut data = parse_csv(TEST_FILE_PATH).unwrap().remove(0);
+    data.description = description.to_string();
+    assert_eq!(expected, data.channel_code());
+}
+
+#[test]
+fn test_allocations() {
+    let mut data = parse_csv(TEST_FILE_PATH).unwrap().remove(0);
+    data.splits = vec![
+        Split {
+            category: TransactionCategory::new("housing.interest"),
+            amount: Money::new(-87447, Currency::CAD),
+            memo: Some(String::from("interest")),
+        },
+        Split {
+            category: TransactionCategory::new("housing.principal"),
+            amount: Money::new(-50000, Currency::CAD),
+            memo: None,
+        },
+    ];
+
+    let rows = data.allocations();
+
+    assert!(data.splits_balance());
+    assert_eq!(2, rows.len());
+    assert_eq!("[DS]BANK         MTG/HYP interest", rows[0].description);
+    assert_eq!(Money::new(-87447, Currency::CAD), rows[0].amount);
+    assert_eq!(
+        TransactionCategory::new("housing.principal"),
+        rows[1].category
+    );
+    assert_eq!(data.date, rows[1].date);
+    assert!(rows.iter().all(|row| row.splits.is_empty()));
+
+    data.splits[1].amount = Money::new(-40000, Currency::CAD);
+    assert!(!data.splits_balance());
+}
+
+#[rstest]
+#[case("food=-110.00", Some(("food", -11000, None)))]
+#[case(" housing = -40 =Hardware ", Some(("housing", -4000, Some("Hardware"))))]
+#[case("food=-1=", Some(("food", -100, None)))]
+#[case("food", None)]
+#[case("food=ten", None)]
+fn test_parse_split(#[case] text: &str, #[case] expected: Option<(&str, i64, Option<&str>)>) {
+    let expected = expected.map(|(category, amount, memo)| Split {
+        category: TransactionCategory::new(category),
+        amount: Money::new(amount, Currency::CAD),
+        memo: memo.map(str::to_string),
+    });
+    assert_eq!(expected, Split::parse(text, Currency::CAD).ok());
 }
@@ -0,0 +1,317 @@
+use std::{collections::HashSet, error::Error, fmt, fs, io, path::Path};
+
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::money::{Currency, Money, MoneyError};
+use crate::output::Tabular;
+use crate::recurring::{series_key, Cadence, RecurringSeries};
+
+/// A scheduled item as written in the schedule file, see `Schedule`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ItemConfig {
+    description: String,
+    amount: String,
+    currency: Option<Currency>,
+    date: NaiveDate,
+    cadence: Option<Cadence>,
+    until: Option<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScheduleFile {
+    #[serde(default)]
+    scheduled: Vec<ItemConfig>,
+}
+
+/// A known future transaction that does not show up in the history, like a yearly tax bill
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledItem {
+    pub description: String,
+    /// Negative for money going out
+    pub amount: Money,
+    /// The first occurrence
+    pub date: NaiveDate,
+    /// `None` for a one-off item
+    pub cadence: Option<Cadence>,
+    /// Last day the item may occur on
+    pub until: Option<NaiveDate>,
+}
+
+/// User-entered scheduled items, read from a TOML file:
+///
+/// ```toml
+/// [[scheduled]]
+/// description = "[CW]CITY TAX"
+/// amount = "-1167.36"
+/// date = "2024-07-02"
+///
+/// [[scheduled]]
+/// description = "Pay cheque"
+/// amount = "2450.00"
+/// date = "2024-07-05"
+/// cadence = "biweekly"
+/// ```
+///
+/// `cadence` is one of `weekly`, `biweekly`, `monthly` or `annual`, `currency` defaults to CAD.
+#[derive(Debug, Clone, Default)]
+pub struct Schedule {
+    items: Vec<ScheduledItem>,
+}
+
+impl Schedule {
+    pub fn new(items: Vec<ScheduledItem>) -> Schedule {
+        Schedule { items }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Schedule, ScheduleError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|source| ScheduleError::Io {
+            file: path.display().to_string(),
+            source,
+        })?;
+        Schedule::from_toml(&text)
+    }
+
+    pub fn from_toml(text: &str) -> Result<Schedule, ScheduleError> {
+        let file: ScheduleFile = toml::from_str(text).map_err(ScheduleError::Toml)?;
+        let items = file
+            .scheduled
+            .into_iter()
+            .map(|config| {
+                let currency = config.currency.unwrap_or(Currency::CAD);
+                let amount = Money::parse(&config.amount, currency).map_err(|source| {
+                    ScheduleError::InvalidAmount {
+                        item: config.description.clone(),
+                        source,
+                    }
+                })?;
+                Ok(ScheduledItem {
+                    description: config.description,
+                    amount,
+                    date: config.date,
+                    cadence: config.cadence,
+                    until: config.until,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Schedule::new(items))
+    }
+
+    pub fn items(&self) -> &[ScheduledItem] {
+        &self.items
+    }
+}
+
+/// Where a forecast transaction comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventSource {
+    /// A series found by `recurring::Detector`
+    Recurring,
+    /// An item of the `Schedule`
+    Scheduled,
+}
+
+/// One projected transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ForecastEvent {
+    pub date: NaiveDate,
+    pub description: String,
+    pub amount: Money,
+    pub source: EventSource,
+}
+
+/// The projected balance at the end of a day
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ForecastDay {
+    pub date: NaiveDate,
+    pub events: Vec<ForecastEvent>,
+    /// Sum of the day's events
+    pub change: Money,
+    pub balance: Money,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Forecast {
+    /// Balance at the end of the day before the first forecast day
+    pub opening: Money,
+    /// Every day from the day after the opening balance to the end of the horizon
+    pub days: Vec<ForecastDay>,
+}
+
+impl Forecast {
+    /// The first day with the lowest projected balance
+    pub fn lowest(&self) -> Option<&ForecastDay> {
+        self.days.iter().reduce(|lowest, day| {
+            if day.balance < lowest.balance {
+                day
+            } else {
+                lowest
+            }
+        })
+    }
+
+    /// The first day ending with a negative balance
+    pub fn first_overdraft(&self) -> Option<&ForecastDay> {
+        self.days.iter().find(|day| day.balance.is_negative())
+    }
+}
+
+/// Occurrences `skip` or more periods after `anchor`, repeating every `cadence`, between `from`
+/// and `to` inclusive. Each one is counted from the anchor so a clamped month end doesn't move
+/// the ones after it.
+fn occurrences(
+    anchor: NaiveDate,
+    skip: u32,
+    cadence: Option<Cadence>,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<NaiveDate> {
+    let mut dates = Vec::new();
+    for n in skip.. {
+        let date = match cadence {
+            Some(cadence) => cadence.nth(anchor, n),
+            None if n == 0 => anchor,
+            None => break,
+        };
+        if date > to {
+            break;
+        }
+        if date >= from {
+            dates.push(date);
+        }
+    }
+    dates
+}
+
+/// Projects `opening`, the balance at the end of `as_of`, for every day of the next `months`
+///
+/// Recurring series repeat after their latest occurrence at its amount, on the dates counted from
+/// `RecurringSeries::anchor`. A series with the same
+/// `series_key` as a scheduled item is left to the schedule, so it is not projected twice. Series
+/// and scheduled items in another currency than `opening` are left out. Occurrences of an overdue
+/// series that fell before the forecast are not projected.
+pub fn forecast(
+    opening: Money,
+    as_of: NaiveDate,
+    months: u32,
+    series: &[RecurringSeries],
+    schedule: &Schedule,
+) -> Result<Forecast, MoneyError> {
+    let from = as_of + Duration::days(1);
+    let to = Cadence::Monthly.nth(as_of, months);
+
+    let scheduled: HashSet<String> = schedule
+        .items()
+        .iter()
+        .map(|item| series_key(&item.description))
+        .collect();
+    let mut events = Vec::new();
+    for series in series.iter().filter(|series| {
+        series.next_amount.currency() == opening.currency()
+            && !scheduled.contains(&series_key(&series.description))
+    }) {
+        let skip = series.elapsed_periods() + 1;
+        for date in occurrences(series.anchor(), skip, Some(series.cadence), from, to) {
+            events.push(ForecastEvent {
+                date,
+                description: series.description.clone(),
+                amount: series.next_amount,
+                source: EventSource::Recurring,
+            });
+        }
+    }
+    for item in schedule
+        .items()
+        .iter()
+        .filter(|item| item.amount.currency() == opening.currency())
+    {
+        let to = item.until.map_or(to, |until| until.min(to));
+        for date in occurrences(item.date, 0, item.cadence, from, to) {
+            events.push(ForecastEvent {
+                date,
+                description: item.description.clone(),
+                amount: item.amount,
+                source: EventSource::Scheduled,
+            });
+        }
+    }
+    events.sort_by_key(|event| event.date);
+
+    let mut days = Vec::new();
+    let mut balance = opening;
+    let mut events = events.into_iter().peekable();
+    let mut date = from;
+    while date <= to {
+        let mut day = ForecastDay {
+            date,
+            events: Vec::new(),
+            change: Money::zero(opening.currency()),
+            balance,
+        };
+        while let Some(event) = events.next_if(|event| event.date == date) {
+            day.change = day.change.checked_add(event.amount)?;
+            day.events.push(event);
+        }
+        balance = balance.checked_add(day.change)?;
+        day.balance = balance;
+        days.push(day);
+        date += Duration::days(1);
+    }
+
+    Ok(Forecast { opening, days })
+}
+
+impl Tabular for ForecastDay {
+    const HEADERS: &'static [&'static str] = &["Date", "Currency", "Change", "Balance", "Items"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.date.to_string(),
+            self.balance.currency().to_string(),
+            self.change.to_string(),
+            self.balance.to_string(),
+            self.events
+                .iter()
+                .map(|event| format!("{} {}", event.description, event.amount))
+                .collect::<Vec<String>>()
+                .join("; "),
+        ]
+    }
+}
+
+#[derive(Debug)]
+pub enum ScheduleError {
+    Io { file: String, source: io::Error },
+    Toml(toml::de::Error),
+    InvalidAmount { item: String, source: MoneyError },
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScheduleError::Io { file, source } => write!(f, "{file}: {source}"),
+            ScheduleError::Toml(source) => write!(f, "invalid schedule file: {source}"),
+            ScheduleError::InvalidAmount { item, source } => {
+                write!(f, "scheduled item {item:?}: {source}")
+            }
+        }
+    }
+}
+
+impl Error for ScheduleError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ScheduleError::Io { source, .. } => Some(source),
+            ScheduleError::Toml(source) => Some(source),
+            ScheduleError::InvalidAmount { source, .. } => Some(source),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./tests/test.rs"]
+mod test;
@@ -0,0 +1,181 @@
+use super::*;
+
+use crate::csv_parser::Data;
+use crate::recurring::Detector;
+use crate::testing::{cad, date};
+
+const SCHEDULE_TOML: &str = r#"
+[[scheduled]]
+description = "[CW]CITY TAX"
+amount = "-1167.36"
+date = "2024-07-02"
+
+[[scheduled]]
+description = "PAY"
+amount = "1500.00"
+date = "2024-07-05"
+cadence = "biweekly"
+until = "2024-07-25"
+"#;
+
+fn mortgage() -> Vec<RecurringSeries> {
+    let rows: Vec<Data> = ["2024-04-03", "2024-05-03", "2024-06-03"]
+        .iter()
+        .map(|day| Data::new(date(day), cad("-1374.47"), "[DS]BANK MTG/HYP"))
+        .collect();
+    Detector::default().detect(&rows, date("2024-06-30"))
+}
+
+#[test]
+fn test_forecast_finds_lowest_balance() {
+    let schedule = Schedule::from_toml(SCHEDULE_TOML).unwrap();
+
+    let forecast = forecast(
+        cad("2000.00"),
+        date("2024-06-30"),
+        1,
+        &mortgage(),
+        &schedule,
+    )
+    .unwrap();
+
+    assert_eq!(30, forecast.days.len());
+    assert_eq!(date("2024-07-01"), forecast.days[0].date);
+    assert_eq!(cad("2000.00"), forecast.days[0].balance);
+    assert_eq!(cad("832.64"), forecast.days[1].balance);
+
+    let lowest = forecast.lowest().unwrap();
+    assert_eq!(date("2024-07-03"), lowest.date);
+    assert_eq!(cad("-541.83"), lowest.balance);
+    assert_eq!(EventSource::Recurring, lowest.events[0].source);
+    assert_eq!(Some(lowest), forecast.first_overdraft());
+
+    // The second pay cheque is the last one before `until`
+    let last = forecast.days.last().unwrap();
+    assert_eq!(date("2024-07-30"), last.date);
+    assert_eq!(cad("2458.17"), last.balance);
+}
+
+#[test]
+fn test_forecast_repeats_recurring_series() {
+    let forecast = forecast(
+        cad("5000.00"),
+        date("2024-06-30"),
+        3,
+        &mortgage(),
+        &Schedule::default(),
+    )
+    .unwrap();
+
+    let dates: Vec<NaiveDate> = forecast
+        .days
+        .iter()
+        .filter(|day| !day.events.is_empty())
+        .map(|day| day.date)
+        .collect();
+    assert_eq!(
+        vec![date("2024-07-03"), date("2024-08-03"), date("2024-09-03")],
+        dates
+    );
+    assert_eq!(None, forecast.first_overdraft());
+    assert_eq!(
+        vec![
+            "2024-09-03",
+            "CAD",
+            "-1374.47",
+            "876.59",
+            "[DS]BANK MTG/HYP -1374.47"
+        ],
+        forecast.lowest().unwrap().cells()
+    );
+}
+
+#[test]
+fn test_forecast_keeps_month_end_series_on_month_end() {
+    let rows: Vec<Data> = ["2023-11-30", "2023-12-31", "2024-01-31", "2024-02-29"]
+        .iter()
+        .map(|day| Data::new(date(day), cad("-1800.00"), "RENT"))
+        .collect();
+    let rent = Detector::default().detect(&rows, date("2024-02-29"));
+
+    let forecast = forecast(
+        cad("10000.00"),
+        date("2024-02-29"),
+        4,
+        &rent,
+        &Schedule::default(),
+    )
+    .unwrap();
+
+    let dates: Vec<NaiveDate> = forecast
+        .days
+        .iter()
+        .filter(|day| !day.events.is_empty())
+        .map(|day| day.date)
+        .collect();
+    assert_eq!(
+        vec![date("2024-03-31"), date("2024-04-30"), date("2024-05-31"),],
+        dates
+    );
+    assert_eq!(date("2024-06-29"), forecast.days.last().unwrap().date);
+}
+
+#[test]
+fn test_forecast_skips_items_in_other_currencies() {
+    let schedule = Schedule::from_toml(
+        "[[scheduled]]\ndescription = \"X\"\namount = \"-50\"\ncurrency = \"EUR\"\n\
+         date = \"2024-07-02\"",
+    )
+    .unwrap();
+
+    let forecast = forecast(
+        Money::new(100, Currency::USD),
+        date("2024-06-30"),
+        1,
+        &mortgage(),
+        &schedule,
+    )
+    .unwrap();
+
+    assert!(forecast.days.iter().all(|day| day.events.is_empty()));
+}
+
+#[test]
+fn test_scheduled_items_replace_their_detected_series() {
+    let rows: Vec<Data> = ["2023-07-02", "2024-07-02"]
+        .iter()
+        .map(|day| Data::new(date(day), cad("-1100.00"), "[CW]CITY TAX"))
+        .collect();
+    let series = Detector::default().detect(&rows, date("2024-07-31"));
+    assert_eq!(1, series.len());
+    let schedule = Schedule::from_toml(
+        r#"
+[[scheduled]]
+description = "[CW]CITY TAX"
+amount = "-1167.36"
+date = "2025-07-02"
+"#,
+    )
+    .unwrap();
+
+    let forecast = forecast(cad("2000.00"), date("2024-07-31"), 12, &series, &schedule).unwrap();
+
+    let events: Vec<&ForecastEvent> = forecast.days.iter().flat_map(|day| &day.events).collect();
+    assert_eq!(1, events.len());
+    assert_eq!(EventSource::Scheduled, events[0].source);
+    assert_eq!(cad("832.64"), forecast.days.last().unwrap().balance);
+}
+
+#[test]
+fn test_schedule_errors() {
+    assert!(matches!(
+        Schedule::from_toml(
+            "[[scheduled]]\ndescription = \"X\"\namount = \"1.234\"\ndate = \"2024-07-01\""
+        ),
+        Err(ScheduleError::InvalidAmount { .. })
+    ));
+    assert!(matches!(
+        Schedule::from_toml("[[scheduled]]\ndescription = \"X\"\namount = \"1\"\ndate = \"July\""),
+        Err(ScheduleError::Toml(_))
+    ));
+}
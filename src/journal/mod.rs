@@ -0,0 +1,373 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    error::Error,
+    fmt, fs, io,
+    path::Path,
+};
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use crate::account::{Account, AccountType};
+use crate::category::{CategoryTree, TransactionCategory};
+use crate::money::{Currency, Money};
+use crate::store::StoredTransaction;
+
+/// Account the two sides of transfers between accounts are posted to. It balances to zero once
+/// both sides are imported.
+pub const TRANSFER_ACCOUNT: &str = "Assets:Transfers";
+
+/// Top level accounts of plain-text accounting
+const ROOTS: [&str; 5] = ["Assets", "Liabilities", "Equity", "Income", "Expenses"];
+
+/// Plain-text accounting formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JournalFormat {
+    Ledger,
+    Hledger,
+    Beancount,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AccountMapFile {
+    /// Account id to journal account
+    #[serde(default)]
+    accounts: HashMap<String, String>,
+    /// Category id to journal account
+    #[serde(default)]
+    categories: HashMap<String, String>,
+}
+
+/// Which journal account stored accounts and categories are posted to
+///
+/// By default bank accounts become `Assets:Bank:<Id>`, credit cards and lines of credit
+/// `Liabilities:<Id>` and investment accounts `Assets:Investments:<Id>`. A category becomes
+/// `Expenses:<Name>` or, if its transactions add up to a credit, `Income:<Name>`, with a
+/// component per level of the category tree, e.g. `Expenses:Housing:Mortgage`. Transfers go to
+/// `TRANSFER_ACCOUNT`. A TOML file can choose other accounts:
+///
+/// ```toml
+/// [accounts]
+/// visa = "Liabilities:CreditCard:Visa"
+///
+/// [categories]
+/// food = "Expenses:Groceries"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccountMap {
+    accounts: HashMap<String, String>,
+    categories: HashMap<TransactionCategory, String>,
+}
+
+impl AccountMap {
+    /// The default mapping
+    pub fn new() -> AccountMap {
+        AccountMap::default()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<AccountMap, AccountMapError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|source| AccountMapError::Io {
+            file: path.display().to_string(),
+            source,
+        })?;
+        AccountMap::from_toml(&text)
+    }
+
+    pub fn from_toml(text: &str) -> Result<AccountMap, AccountMapError> {
+        let file: AccountMapFile = toml::from_str(text).map_err(AccountMapError::Toml)?;
+        for name in file.accounts.values().chain(file.categories.values()) {
+            if !is_valid_account(name) {
+                return Err(AccountMapError::InvalidAccount(name.clone()));
+            }
+        }
+        Ok(AccountMap {
+            accounts: file.accounts,
+            categories: file
+                .categories
+                .into_iter()
+                .map(|(id, name)| (TransactionCategory::new(&id), name))
+                .collect(),
+        })
+    }
+
+    /// Checks that every mapped category exists in `tree`
+    pub fn validate(&self, tree: &CategoryTree) -> Result<(), AccountMapError> {
+        match self.categories.keys().find(|id| !tree.contains(id)) {
+            Some(id) => Err(AccountMapError::UnknownCategory(id.clone())),
+            None => Ok(()),
+        }
+    }
+
+    /// The journal account of a stored account
+    pub fn asset_account(&self, account: &Account) -> String {
+        if let Some(name) = self.accounts.get(&account.id) {
+            return name.clone();
+        }
+        let parent = match account.account_type {
+            AccountType::CreditCard | AccountType::LineOfCredit => "Liabilities",
+            AccountType::Investment => "Assets:Investments",
+            AccountType::Chequing | AccountType::Savings | AccountType::Other => "Assets:Bank",
+        };
+        format!("{parent}:{}", component(&account.id))
+    }
+
+    /// The journal account of a category, an income account if `income`
+    pub fn category_account(
+        &self,
+        tree: &CategoryTree,
+        category: &TransactionCategory,
+        income: bool,
+    ) -> String {
+        if let Some(name) = self.categories.get(category) {
+            return name.clone();
+        }
+        if *category == TransactionCategory::ACCOUNT_TRANSFERS {
+            return TRANSFER_ACCOUNT.to_string();
+        }
+        let mut names: Vec<String> = tree
+            .ancestors(category)
+            .map(|category| component(&category.name))
+            .collect();
+        if names.is_empty() {
+            names.push(component(category.id()));
+        }
+        names.reverse();
+        let root = if income { "Income" } else { "Expenses" };
+        format!("{root}:{}", names.join(":"))
+    }
+}
+
+/// Whether `name` is an account all three formats accept, e.g. `Expenses:Food:Dining-Out`
+fn is_valid_account(name: &str) -> bool {
+    let mut components = name.split(':');
+    let root = components.next().unwrap_or_default();
+    ROOTS.contains(&root)
+        && components.clone().next().is_some()
+        && components.all(|component| {
+            component
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+                && component
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+/// An account name component made of `text`: its words capitalized and joined, e.g.
+/// `Account Transfers` becomes `AccountTransfers` and `line-of-credit` becomes `LineOfCredit`
+fn component(text: &str) -> String {
+    let words: String = text
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().expect("words are not empty");
+            first.to_ascii_uppercase().to_string() + chars.as_str()
+        })
+        .collect();
+    if words.is_empty() {
+        "Unknown".to_string()
+    } else {
+        words
+    }
+}
+
+/// A journal transaction whose postings balance
+struct Entry<'a> {
+    transaction: &'a StoredTransaction,
+    asset: String,
+    /// Category accounts and what is posted to them, one per split
+    categories: Vec<(String, Money)>,
+}
+
+/// Writes `transactions` as a journal of `format`, one transaction per row
+///
+/// Each transaction posts its amount to the account it was imported into and the opposite
+/// amount to the account of its category, or of each of its splits, so the postings balance.
+/// All amounts are written out rather than left for the program to infer. Beancount journals
+/// start with an `open` directive for every account used, dated at its first transaction.
+pub fn write_journal(
+    writer: &mut dyn io::Write,
+    format: JournalFormat,
+    map: &AccountMap,
+    tree: &CategoryTree,
+    accounts: &[Account],
+    transactions: &[StoredTransaction],
+) -> io::Result<()> {
+    let income = income_categories(transactions);
+    let entries: Vec<Entry> = transactions
+        .iter()
+        .map(|transaction| {
+            let asset = match accounts
+                .iter()
+                .find(|account| account.id == transaction.account)
+            {
+                Some(account) => map.asset_account(account),
+                None => format!("Assets:Bank:{}", component(&transaction.account)),
+            };
+            let categories = transaction
+                .data
+                .allocations()
+                .into_iter()
+                .map(|part| {
+                    let category = &part.category;
+                    let account = map.category_account(tree, category, income.contains(category));
+                    part.amount.checked_neg().map(|amount| (account, amount))
+                })
+                .collect::<Result<_, _>>()
+                .map_err(io::Error::other)?;
+            Ok(Entry {
+                transaction,
+                asset,
+                categories,
+            })
+        })
+        .collect::<io::Result<_>>()?;
+
+    let width = entries
+        .iter()
+        .flat_map(|entry| {
+            std::iter::once(entry.asset.len())
+                .chain(entry.categories.iter().map(|(account, _)| account.len()))
+        })
+        .max()
+        .unwrap_or_default();
+    if format == JournalFormat::Beancount {
+        let mut opened: BTreeMap<&str, NaiveDate> = BTreeMap::new();
+        for entry in &entries {
+            let date = entry.transaction.data.date;
+            let categories = entry.categories.iter().map(|(account, _)| account);
+            for name in std::iter::once(&entry.asset).chain(categories) {
+                let first = opened.entry(name).or_insert(date);
+                *first = (*first).min(date);
+            }
+        }
+        for (name, date) in &opened {
+            writeln!(writer, "{} open {name}", date.format("%Y-%m-%d"))?;
+        }
+        if !opened.is_empty() {
+            writeln!(writer)?;
+        }
+    }
+
+    for (index, entry) in entries.iter().enumerate() {
+        if index > 0 {
+            writeln!(writer)?;
+        }
+        let data = &entry.transaction.data;
+        match format {
+            JournalFormat::Ledger => writeln!(
+                writer,
+                "{} * {}",
+                data.date.format("%Y/%m/%d"),
+                payee(&data.description)
+            )?,
+            JournalFormat::Hledger => writeln!(
+                writer,
+                "{} * {}",
+                data.date.format("%Y-%m-%d"),
+                payee(&data.description)
+            )?,
+            JournalFormat::Beancount => writeln!(
+                writer,
+                "{} * \"{}\"",
+                data.date.format("%Y-%m-%d"),
+                escape(&data.description)
+            )?,
+        }
+        let indent = if format == JournalFormat::Beancount {
+            "  "
+        } else {
+            "    "
+        };
+        let postings = entry
+            .categories
+            .iter()
+            .map(|(account, amount)| (account, *amount))
+            .chain([(&entry.asset, data.amount)]);
+        for (account, amount) in postings {
+            writeln!(
+                writer,
+                "{indent}{account:<width$}  {:>12} {}",
+                amount.to_string(),
+                amount.currency()
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Categories whose transactions add up to a credit, in any currency
+fn income_categories(transactions: &[StoredTransaction]) -> BTreeSet<TransactionCategory> {
+    let mut totals: BTreeMap<(TransactionCategory, Currency), i64> = BTreeMap::new();
+    for data in transactions
+        .iter()
+        .flat_map(|transaction| transaction.data.allocations())
+    {
+        *totals
+            .entry((data.category, data.amount.currency()))
+            .or_default() += data.amount.minor_units();
+    }
+    totals
+        .into_iter()
+        .filter(|(_, total)| *total > 0)
+        .map(|((category, _), _)| category)
+        .collect()
+}
+
+/// A ledger or hledger payee, with the `;` that would start a comment turned into `,`
+fn payee(text: &str) -> String {
+    text.replace(';', ",")
+}
+
+/// Escapes a beancount string
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[derive(Debug)]
+pub enum AccountMapError {
+    Io {
+        file: String,
+        source: io::Error,
+    },
+    Toml(toml::de::Error),
+    /// Not an account name every format accepts
+    InvalidAccount(String),
+    UnknownCategory(TransactionCategory),
+}
+
+impl fmt::Display for AccountMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccountMapError::Io { file, source } => write!(f, "{file}: {source}"),
+            AccountMapError::Toml(source) => write!(f, "invalid account map: {source}"),
+            AccountMapError::InvalidAccount(name) => write!(
+                f,
+                "invalid journal account {name:?}, expected e.g. \"Expenses:Food\" with a \
+                 top level of {}",
+                ROOTS.join(", ")
+            ),
+            AccountMapError::UnknownCategory(id) => {
+                write!(f, "account map names unknown category {id:?}")
+            }
+        }
+    }
+}
+
+impl Error for AccountMapError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AccountMapError::Io { source, .. } => Some(source),
+            AccountMapError::Toml(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./tests/test.rs"]
+mod test;
@@ -0,0 +1,191 @@
+use super::*;
+
+use chrono::NaiveDate;
+
+use crate::csv_parser::Data;
+use crate::money::Money;
+
+const CATEGORIES_TOML: &str = r#"
+[[category]]
+id = "housing"
+name = "Housing"
+
+[[category]]
+id = "housing.mortgage"
+name = "Mortgage"
+parent = "housing"
+
+[[category]]
+id = "salary"
+name = "Salary"
+"#;
+
+fn tree() -> CategoryTree {
+    CategoryTree::from_toml(CATEGORIES_TOML).unwrap()
+}
+
+fn transaction(
+    account: &str,
+    day: &str,
+    amount: &str,
+    description: &str,
+    category: &str,
+) -> StoredTransaction {
+    let mut data = Data::new(
+        NaiveDate::parse_from_str(day, "%Y-%m-%d").unwrap(),
+        Money::parse(amount, Currency::CAD).unwrap(),
+        description,
+    );
+    data.category = TransactionCategory::new(category);
+    StoredTransaction {
+        id: 1,
+        batch_id: 1,
+        account: account.to_string(),
+        fingerprint: String::new(),
+        data,
+    }
+}
+
+fn accounts() -> Vec<Account> {
+    let mut visa = Account::new("visa", Currency::CAD);
+    visa.account_type = AccountType::CreditCard;
+    let mut chequing = Account::new("chequing", Currency::CAD);
+    chequing.account_type = AccountType::Chequing;
+    vec![chequing, visa]
+}
+
+fn transactions() -> Vec<StoredTransaction> {
+    vec![
+        transaction(
+            "chequing",
+            "2024-06-03",
+            "-1374.47",
+            "[DS]BANK MTG/HYP",
+            "housing.mortgage",
+        ),
+        transaction("visa", "2024-06-05", "-12.50", "COFFEE \"BAR\"", "food"),
+        transaction("chequing", "2024-06-14", "2500.00", "PAYROLL", "salary"),
+    ]
+}
+
+fn journal(format: JournalFormat, map: &AccountMap) -> String {
+    let mut written = Vec::new();
+    write_journal(
+        &mut written,
+        format,
+        map,
+        &tree(),
+        &accounts(),
+        &transactions(),
+    )
+    .unwrap();
+    String::from_utf8(written).unwrap()
+}
+
+#[test]
+fn test_ledger() {
+    assert_eq!(
+        "2024/06/03 * [DS]BANK MTG/HYP\n\
+         \x20   Expenses:Housing:Mortgage       1374.47 CAD\n\
+         \x20   Assets:Bank:Chequing           -1374.47 CAD\n\
+         \n\
+         2024/06/05 * COFFEE \"BAR\"\n\
+         \x20   Expenses:Food                     12.50 CAD\n\
+         \x20   Liabilities:Visa                 -12.50 CAD\n\
+         \n\
+         2024/06/14 * PAYROLL\n\
+         \x20   Income:Salary                  -2500.00 CAD\n\
+         \x20   Assets:Bank:Chequing            2500.00 CAD\n",
+        journal(JournalFormat::Ledger, &AccountMap::new())
+    );
+    assert!(journal(JournalFormat::Hledger, &AccountMap::new()).starts_with("2024-06-03 * "));
+}
+
+#[test]
+fn test_semicolons_do_not_start_a_comment() {
+    assert_eq!("PAYROLL, JUNE", payee("PAYROLL; JUNE"));
+    assert_eq!("PAYROLL", payee("PAYROLL"));
+}
+
+#[test]
+fn test_beancount() {
+    let map = AccountMap::from_toml(
+        "[accounts]\nvisa = \"Liabilities:CreditCard:Visa\"\n\n\
+         [categories]\nfood = \"Expenses:Dining-Out\"\n",
+    )
+    .unwrap();
+
+    let text = journal(JournalFormat::Beancount, &map);
+
+    assert!(text.starts_with(
+        "2024-06-03 open Assets:Bank:Chequing\n\
+         2024-06-05 open Expenses:Dining-Out\n\
+         2024-06-03 open Expenses:Housing:Mortgage\n\
+         2024-06-14 open Income:Salary\n\
+         2024-06-05 open Liabilities:CreditCard:Visa\n\n"
+    ));
+    assert!(text.contains(
+        "2024-06-05 * \"COFFEE \\\"BAR\\\"\"\n\
+         \x20 Expenses:Dining-Out                 12.50 CAD\n\
+         \x20 Liabilities:CreditCard:Visa        -12.50 CAD\n"
+    ));
+}
+
+#[test]
+fn test_postings_balance() {
+    let text = journal(JournalFormat::Hledger, &AccountMap::new());
+
+    for entry in text.split("\n\n") {
+        let total: i64 = entry
+            .lines()
+            .skip(1)
+            .map(|posting| {
+                let amount = posting.split_whitespace().nth(1).unwrap();
+                Money::parse(amount, Currency::CAD).unwrap().minor_units()
+            })
+            .sum();
+        assert_eq!(0, total, "{entry}");
+    }
+}
+
+#[test]
+fn test_account_names() {
+    let map = AccountMap::new();
+    let mut line_of_credit = Account::new("home-equity line", Currency::CAD);
+    line_of_credit.account_type = AccountType::LineOfCredit;
+
+    assert_eq!(
+        "Liabilities:HomeEquityLine",
+        map.asset_account(&line_of_credit)
+    );
+    assert_eq!(
+        TRANSFER_ACCOUNT,
+        map.category_account(&tree(), &TransactionCategory::ACCOUNT_TRANSFERS, false)
+    );
+    assert_eq!(
+        "Income:AccountingFees",
+        map.category_account(&tree(), &TransactionCategory::new("accounting fees"), true)
+    );
+}
+
+#[test]
+fn test_account_map_errors() {
+    assert!(matches!(
+        AccountMap::from_toml("[categories]\nfood = \"Food\"\n"),
+        Err(AccountMapError::InvalidAccount(name)) if name == "Food"
+    ));
+    assert!(matches!(
+        AccountMap::from_toml("[categories]\nfood = \"Expenses:dining out\"\n"),
+        Err(AccountMapError::InvalidAccount(_))
+    ));
+    assert!(matches!(
+        AccountMap::from_toml("[categories]\nrent = \"Expenses:Rent\"\n")
+            .unwrap()
+            .validate(&tree()),
+        Err(AccountMapError::UnknownCategory(_))
+    ));
+    assert!(matches!(
+        AccountMap::from_toml("[other]\n"),
+        Err(AccountMapError::Toml(_))
+    ));
+}
@@ -0,0 +1,23 @@
+pub mod account;
+pub mod budget;
+pub mod camt;
+pub mod categorizer;
+pub mod category;
+pub mod channel;
+pub mod cli;
+pub mod csv_parser;
+pub mod forecast;
+pub mod journal;
+pub mod money;
+pub mod ofx;
+pub mod output;
+pub mod payee;
+pub mod qif;
+pub mod reconcile;
+pub mod recurring;
+pub mod report;
+pub mod store;
+pub mod tax;
+#[cfg(test)]
+mod testing;
+pub mod transfer;
@@ -1,4 +1,15 @@
-mod csv_parser;
-fn main() {
-    csv_parser::parse_csv("assets/test_statement.csv").expect("Failed to parse CSV file");
+use std::{io, process::ExitCode};
+
+use clap::Parser;
+use finance_tracker::cli::{self, Cli};
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match cli::run(cli, &mut io::stdout().lock()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -0,0 +1,307 @@
+use std::{cmp::Ordering, fmt, str};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// An ISO 4217 currency code, e.g. `CAD`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Currency([u8; 3]);
+
+impl Currency {
+    pub const CAD: Currency = Currency(*b"CAD");
+    pub const USD: Currency = Currency(*b"USD");
+    pub const EUR: Currency = Currency(*b"EUR");
+
+    /// Builds a currency from a three letter code. Lowercase codes are accepted and normalized.
+    pub fn new(code: &str) -> Result<Currency, MoneyError> {
+        let bytes = code.trim().as_bytes();
+        if bytes.len() != 3 || !bytes.iter().all(u8::is_ascii_alphabetic) {
+            return Err(MoneyError::InvalidCurrency(code.to_string()));
+        }
+        let mut upper = [0u8; 3];
+        for (dst, src) in upper.iter_mut().zip(bytes) {
+            *dst = src.to_ascii_uppercase();
+        }
+        Ok(Currency(upper))
+    }
+
+    pub fn code(&self) -> &str {
+        // Only ASCII letters are ever stored, see `Currency::new`
+        str::from_utf8(&self.0).unwrap()
+    }
+
+    /// Number of digits after the decimal point used by the currency's minor unit
+    pub fn minor_digits(&self) -> u32 {
+        match &self.0 {
+            b"JPY" | b"KRW" | b"ISK" | b"CLP" | b"VND" => 0,
+            b"BHD" | b"KWD" | b"OMR" | b"JOD" | b"TND" => 3,
+            _ => 2,
+        }
+    }
+
+    fn scale(&self) -> i64 {
+        10i64.pow(self.minor_digits())
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl Serialize for Currency {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for Currency {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Currency, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        Currency::new(&code).map_err(de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoneyError {
+    /// The text is not a decimal number
+    InvalidAmount(String),
+    /// The text has more fractional digits than the currency's minor unit can hold
+    TooPrecise {
+        text: String,
+        currency: Currency,
+    },
+    InvalidCurrency(String),
+    CurrencyMismatch {
+        left: Currency,
+        right: Currency,
+    },
+    Overflow,
+    DivisionByZero,
+}
+
+impl fmt::Display for MoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoneyError::InvalidAmount(text) => write!(f, "invalid amount: {text:?}"),
+            MoneyError::TooPrecise { text, currency } => write!(
+                f,
+                "amount {text:?} has more than {} decimal places for {currency}",
+                currency.minor_digits()
+            ),
+            MoneyError::InvalidCurrency(code) => write!(f, "invalid currency code: {code:?}"),
+            MoneyError::CurrencyMismatch { left, right } => {
+                write!(f, "cannot combine amounts in {left} and {right}")
+            }
+            MoneyError::Overflow => write!(f, "amount overflowed"),
+            MoneyError::DivisionByZero => write!(f, "amount divided by zero"),
+        }
+    }
+}
+
+impl std::error::Error for MoneyError {}
+
+/// An exact amount of money, stored as an integer count of the currency's minor unit (e.g. cents)
+///
+/// All arithmetic is checked: mixing currencies or overflowing returns a `MoneyError` instead of
+/// silently producing a wrong total.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Money {
+    minor_units: i64,
+    currency: Currency,
+}
+
+impl Money {
+    pub fn new(minor_units: i64, currency: Currency) -> Money {
+        Money {
+            minor_units,
+            currency,
+        }
+    }
+
+    pub fn zero(currency: Currency) -> Money {
+        Money::new(0, currency)
+    }
+
+    /// Parses a decimal amount as it appears in a statement, e.g. `-1374.47` or `-80.0`
+    ///
+    /// The conversion is exact. Fractional digits beyond the currency's minor unit are only
+    /// accepted when they are zeros, anything else is rejected rather than rounded.
+    pub fn parse(text: &str, currency: Currency) -> Result<Money, MoneyError> {
+        let invalid = || MoneyError::InvalidAmount(text.to_string());
+        let trimmed = text.trim();
+        let (negative, unsigned) = match trimmed.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+        };
+        let (whole, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        if whole.is_empty() && fraction.is_empty() {
+            return Err(invalid());
+        }
+        if !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        let digits = currency.minor_digits() as usize;
+        let (kept, dropped) = fraction.split_at(fraction.len().min(digits));
+        if dropped.chars().any(|c| c != '0') {
+            return Err(MoneyError::TooPrecise {
+                text: text.to_string(),
+                currency,
+            });
+        }
+
+        let mut minor_units: i64 = 0;
+        let padding = std::iter::repeat_n('0', digits - kept.len());
+        for c in whole.chars().chain(kept.chars()).chain(padding) {
+            let digit = i64::from(c.to_digit(10).unwrap());
+            minor_units = minor_units
+                .checked_mul(10)
+                .and_then(|units| units.checked_add(digit))
+                .ok_or(MoneyError::Overflow)?;
+        }
+        if negative {
+            minor_units = -minor_units;
+        }
+
+        Ok(Money::new(minor_units, currency))
+    }
+
+    pub fn minor_units(&self) -> i64 {
+        self.minor_units
+    }
+
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.minor_units == 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.minor_units < 0
+    }
+
+    pub fn is_positive(&self) -> bool {
+        self.minor_units > 0
+    }
+
+    pub fn checked_add(self, other: Money) -> Result<Money, MoneyError> {
+        self.same_currency(&other)?;
+        self.minor_units
+            .checked_add(other.minor_units)
+            .map(|units| Money::new(units, self.currency))
+            .ok_or(MoneyError::Overflow)
+    }
+
+    pub fn checked_sub(self, other: Money) -> Result<Money, MoneyError> {
+        self.same_currency(&other)?;
+        self.minor_units
+            .checked_sub(other.minor_units)
+            .map(|units| Money::new(units, self.currency))
+            .ok_or(MoneyError::Overflow)
+    }
+
+    pub fn checked_neg(self) -> Result<Money, MoneyError> {
+        self.minor_units
+            .checked_neg()
+            .map(|units| Money::new(units, self.currency))
+            .ok_or(MoneyError::Overflow)
+    }
+
+    pub fn checked_abs(self) -> Result<Money, MoneyError> {
+        self.minor_units
+            .checked_abs()
+            .map(|units| Money::new(units, self.currency))
+            .ok_or(MoneyError::Overflow)
+    }
+
+    pub fn checked_mul(self, factor: i64) -> Result<Money, MoneyError> {
+        self.minor_units
+            .checked_mul(factor)
+            .map(|units| Money::new(units, self.currency))
+            .ok_or(MoneyError::Overflow)
+    }
+
+    /// Divides by `divisor`, rounding half away from zero to the nearest minor unit
+    pub fn checked_div(self, divisor: i64) -> Result<Money, MoneyError> {
+        if divisor == 0 {
+            return Err(MoneyError::DivisionByZero);
+        }
+        let quotient = self.minor_units / divisor;
+        let remainder = self.minor_units % divisor;
+        let round_away = remainder.unsigned_abs() * 2 >= divisor.unsigned_abs();
+        let units = if !round_away {
+            Some(quotient)
+        } else if (self.minor_units < 0) != (divisor < 0) {
+            quotient.checked_sub(1)
+        } else {
+            quotient.checked_add(1)
+        };
+        units
+            .map(|units| Money::new(units, self.currency))
+            .ok_or(MoneyError::Overflow)
+    }
+
+    /// Adds up `amounts`, starting from zero in `currency`
+    pub fn checked_sum<I>(amounts: I, currency: Currency) -> Result<Money, MoneyError>
+    where
+        I: IntoIterator<Item = Money>,
+    {
+        amounts
+            .into_iter()
+            .try_fold(Money::zero(currency), Money::checked_add)
+    }
+
+    fn same_currency(&self, other: &Money) -> Result<(), MoneyError> {
+        if self.currency == other.currency {
+            Ok(())
+        } else {
+            Err(MoneyError::CurrencyMismatch {
+                left: self.currency,
+                right: other.currency,
+            })
+        }
+    }
+}
+
+/// Amounts in different currencies are not comparable
+impl PartialOrd for Money {
+    fn partial_cmp(&self, other: &Money) -> Option<Ordering> {
+        (self.currency == other.currency).then(|| self.minor_units.cmp(&other.minor_units))
+    }
+}
+
+/// Serializes as the decimal text of `Display`, which keeps the amount exact in JSON. The currency
+/// is left to the surrounding structure.
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Formats the amount with exactly the currency's minor digits, e.g. `-1374.47`, without the
+/// currency code
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.is_negative() { "-" } else { "" };
+        let scale = self.currency.scale().unsigned_abs();
+        let units = self.minor_units.unsigned_abs();
+        let digits = self.currency.minor_digits() as usize;
+        let text = if digits == 0 {
+            format!("{sign}{units}")
+        } else {
+            format!("{sign}{}.{:0digits$}", units / scale, units % scale)
+        };
+        f.pad(&text)
+    }
+}
+
+#[cfg(test)]
+#[path = "./tests/test.rs"]
+mod test;
@@ -0,0 +1,118 @@
+use super::*;
+use rstest::rstest;
+
+#[rstest]
+#[case("-1374.47", -137447)]
+#[case("-80.0", -8000)]
+#[case("521.3", 52130)]
+#[case("+30.95", 3095)]
+#[case("12", 1200)]
+#[case(".5", 50)]
+#[case("7.500", 750)]
+fn test_parse(#[case] text: &str, #[case] minor_units: i64) {
+    assert_eq!(
+        Money::new(minor_units, Currency::CAD),
+        Money::parse(text, Currency::CAD).unwrap()
+    );
+}
+
+#[rstest]
+#[case("")]
+#[case("-")]
+#[case("abc")]
+#[case("1.2.3")]
+#[case("1,374.47")]
+fn test_parse_invalid(#[case] text: &str) {
+    assert_eq!(
+        Err(MoneyError::InvalidAmount(text.to_string())),
+        Money::parse(text, Currency::CAD)
+    );
+}
+
+#[test]
+fn test_parse_rejects_rounding() {
+    assert_eq!(
+        Err(MoneyError::TooPrecise {
+            text: String::from("0.005"),
+            currency: Currency::CAD
+        }),
+        Money::parse("0.005", Currency::CAD)
+    );
+}
+
+#[rstest]
+#[case("-1374.47", "-1374.47")]
+#[case("-80.0", "-80.00")]
+#[case("0.05", "0.05")]
+#[case("-0.05", "-0.05")]
+fn test_display_round_trip(#[case] text: &str, #[case] expected: &str) {
+    let money = Money::parse(text, Currency::CAD).unwrap();
+    assert_eq!(expected, money.to_string());
+    assert_eq!(
+        money,
+        Money::parse(&money.to_string(), Currency::CAD).unwrap()
+    );
+}
+
+#[test]
+fn test_checked_sum_reconciles_to_the_cent() {
+    let amounts = ["-1374.47", "-231.97", "-80.0", "521.3", "0.1", "0.2"]
+        .iter()
+        .map(|text| Money::parse(text, Currency::CAD).unwrap());
+
+    assert_eq!(
+        Money::new(-116484, Currency::CAD),
+        Money::checked_sum(amounts, Currency::CAD).unwrap()
+    );
+}
+
+#[test]
+fn test_checked_arithmetic_errors() {
+    let cad = Money::new(100, Currency::CAD);
+    let usd = Money::new(100, Currency::USD);
+
+    assert_eq!(
+        Err(MoneyError::CurrencyMismatch {
+            left: Currency::CAD,
+            right: Currency::USD
+        }),
+        cad.checked_add(usd)
+    );
+    assert_eq!(None, cad.partial_cmp(&usd));
+    assert_eq!(
+        Err(MoneyError::Overflow),
+        Money::new(i64::MAX, Currency::CAD).checked_add(cad)
+    );
+}
+
+#[test]
+fn test_currency() {
+    assert_eq!(Currency::CAD, Currency::new("cad").unwrap());
+    assert_eq!(0, Currency::new("JPY").unwrap().minor_digits());
+    assert!(Currency::new("CA").is_err());
+}
+
+#[rstest]
+#[case(1000, 3, 333)]
+#[case(1001, 2, 501)]
+#[case(-1001, 2, -501)]
+#[case(-1000, 3, -333)]
+#[case(1000, -4, -250)]
+fn test_checked_div_rounds_half_away_from_zero(
+    #[case] minor_units: i64,
+    #[case] divisor: i64,
+    #[case] expected: i64,
+) {
+    assert_eq!(
+        Ok(Money::new(expected, Currency::CAD)),
+        Money::new(minor_units, Currency::CAD).checked_div(divisor)
+    );
+}
+
+#[test]
+fn test_checked_div_by_zero() {
+    assert_eq!(
+        Err(MoneyError::DivisionByZero),
+        Money::new(100, Currency::CAD).checked_div(0)
+    );
+}
@@ -0,0 +1,400 @@
+use std::{collections::HashMap, error::Error, fmt, fs, io, path::Path};
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::account::mask_number;
+use crate::csv_parser::{Data, ParseOutput, StatementBalance};
+use crate::money::{Currency, Money, MoneyError};
+
+/// Name of the OFX format in import summaries and `import --format`
+pub const OFX_FORMAT: &str = "ofx";
+
+/// How many bytes `is_ofx` looks at
+const SNIFF_LENGTH: usize = 1024;
+
+/// Whether `contents` looks like an OFX or QFX download, either the SGML (1.x) or XML (2.x) kind
+pub fn is_ofx(contents: &[u8]) -> bool {
+    let start =
+        String::from_utf8_lossy(&contents[..contents.len().min(SNIFF_LENGTH)]).to_uppercase();
+    start
+        .trim_start_matches('\u{feff}')
+        .trim_start()
+        .starts_with("OFXHEADER")
+        || start.contains("<OFX>")
+        || start.contains("<?OFX")
+}
+
+/// Whether the file at `file_path` is an OFX or QFX download, judged by its extension or, failing
+/// that, its contents
+pub fn is_ofx_file(file_path: &str) -> bool {
+    let extension = Path::new(file_path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    matches!(extension.as_deref(), Some("ofx" | "qfx"))
+        || fs::read(file_path).is_ok_and(|contents| is_ofx(&contents))
+}
+
+/// Reads the bank and credit card statements of an OFX or QFX file
+///
+/// Every `STMTTRN` becomes a row dated by its `DTPOSTED`, with the `FITID` as its
+/// `transaction_id`, `DTUSER` as its `user_date` and `NAME` and `MEMO` as its description. Rows
+/// carry the masked `ACCTID` of their statement. `DTSERVER` is used as the time the data is valid
+/// as of and the header lines end up in the metadata preamble. If the file holds several
+/// statements, the balances are those of the last one.
+pub fn parse_ofx(file_path: &str) -> Result<ParseOutput, OfxError> {
+    let contents = fs::read(file_path).map_err(|source| OfxError::Io {
+        file: file_path.to_string(),
+        source,
+    })?;
+    parse_str(file_path, &String::from_utf8_lossy(&contents))
+}
+
+fn parse_str(file_path: &str, contents: &str) -> Result<ParseOutput, OfxError> {
+    let not_ofx = || OfxError::NotOfx {
+        file: file_path.to_string(),
+    };
+    let start = contents
+        .to_ascii_uppercase()
+        .find("<OFX>")
+        .ok_or_else(not_ofx)?;
+    let header = &contents[..start];
+    let body = &contents[start..];
+
+    let mut output = ParseOutput::default();
+    output.metadata.preamble = header
+        .lines()
+        .map(|line| line.trim_start_matches('\u{feff}').trim())
+        .filter(|line| !line.is_empty() && !line.starts_with("<?"))
+        .map(str::to_string)
+        .collect();
+
+    let mut statement = Statement::default();
+    let mut aggregate: Option<(String, HashMap<String, String>)> = None;
+    for event in events(body) {
+        match event {
+            Event::Start(name) => match name.as_str() {
+                "STMTRS" | "CCSTMTRS" => statement = Statement::default(),
+                "BANKACCTFROM" | "CCACCTFROM" => statement.in_account = true,
+                "STMTTRN" | "LEDGERBAL" | "AVAILBAL" => aggregate = Some((name, HashMap::new())),
+                _ => {}
+            },
+            Event::Leaf(name, value) => match &mut aggregate {
+                Some((_, fields)) => {
+                    fields.insert(name, value);
+                }
+                None => match name.as_str() {
+                    "DTSERVER" => output.metadata.valid_as_of = parse_date_time(&value),
+                    "CURDEF" => {
+                        let currency =
+                            Currency::new(&value).map_err(|source| OfxError::InvalidCurrency {
+                                file: file_path.to_string(),
+                                raw: value.clone(),
+                                source,
+                            })?;
+                        statement.currency = Some(currency);
+                    }
+                    "ACCTID" if statement.in_account => {
+                        statement.account_number = mask_number(&value)
+                    }
+                    _ => {}
+                },
+            },
+            Event::End(name) => {
+                if matches!(name.as_str(), "BANKACCTFROM" | "CCACCTFROM") {
+                    statement.in_account = false;
+                }
+                if aggregate.as_ref().is_none_or(|(open, _)| *open != name) {
+                    continue;
+                }
+                let Some((_, fields)) = aggregate.take() else {
+                    continue;
+                };
+                let element = Element {
+                    file: file_path,
+                    name: &name,
+                    index: output.rows.len() + 1,
+                    fields: &fields,
+                };
+                let currency = statement.currency.ok_or_else(|| OfxError::MissingElement {
+                    file: file_path.to_string(),
+                    element: "CURDEF",
+                    context: name.clone(),
+                })?;
+                match name.as_str() {
+                    "STMTTRN" => {
+                        let mut data = element.transaction(currency)?;
+                        data.account_number = statement.account_number.clone();
+                        output.metadata.cover(data.date);
+                        output.rows.push(data);
+                    }
+                    "LEDGERBAL" => {
+                        output.metadata.ledger_balance = Some(element.balance(currency)?)
+                    }
+                    _ => output.metadata.available_balance = Some(element.balance(currency)?),
+                }
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// What is known about the statement being read
+#[derive(Default)]
+struct Statement {
+    currency: Option<Currency>,
+    account_number: Option<String>,
+    /// Inside `BANKACCTFROM` or `CCACCTFROM`, where `ACCTID` is the statement's account
+    in_account: bool,
+}
+
+/// A markup event of the OFX body. Element names are uppercased.
+#[derive(Debug, PartialEq, Eq)]
+enum Event {
+    /// An aggregate, i.e. an element containing other elements, starts
+    Start(String),
+    /// An element ends. Leaf elements only have end tags in the XML flavour.
+    End(String),
+    /// An element with a value. In the SGML flavour these have no end tag.
+    Leaf(String, String),
+}
+
+/// Splits the body into events, skipping processing instructions and comments
+fn events(body: &str) -> Vec<Event> {
+    let mut events = Vec::new();
+    let mut rest = body;
+    while let Some(open) = rest.find('<') {
+        let after = &rest[open + 1..];
+        let Some(close) = after.find('>') else {
+            break;
+        };
+        let tag = after[..close].trim();
+        rest = &after[close + 1..];
+        if tag.starts_with('?') || tag.starts_with('!') || tag.ends_with('/') {
+            continue;
+        }
+        if let Some(name) = tag.strip_prefix('/') {
+            events.push(Event::End(name.trim().to_uppercase()));
+            continue;
+        }
+        let name = tag.to_uppercase();
+        let text = rest[..rest.find('<').unwrap_or(rest.len())].trim();
+        if text.is_empty() {
+            events.push(Event::Start(name));
+        } else {
+            events.push(Event::Leaf(name, unescape(text)));
+        }
+    }
+    events
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+/// The date of an OFX date time such as `20240714164814.000[-5:EST]`. Time zones are ignored,
+/// statements use the bank's local time.
+fn parse_date(text: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(text.get(..8)?, "%Y%m%d").ok()
+}
+
+fn parse_date_time(text: &str) -> Option<NaiveDateTime> {
+    match text.get(..14) {
+        Some(digits) => NaiveDateTime::parse_from_str(digits, "%Y%m%d%H%M%S").ok(),
+        None => parse_date(text)?.and_hms_opt(0, 0, 0),
+    }
+}
+
+/// A closed `STMTTRN`, `LEDGERBAL` or `AVAILBAL` aggregate
+struct Element<'a> {
+    file: &'a str,
+    name: &'a str,
+    /// 1-based position of the transaction, for errors
+    index: usize,
+    fields: &'a HashMap<String, String>,
+}
+
+impl Element<'_> {
+    fn context(&self) -> String {
+        match self.name {
+            "STMTTRN" => match self.fields.get("FITID") {
+                Some(fitid) => format!("STMTTRN {fitid:?}"),
+                None => format!("STMTTRN #{}", self.index),
+            },
+            name => name.to_string(),
+        }
+    }
+
+    fn field(&self, element: &'static str) -> Result<&str, OfxError> {
+        self.fields
+            .get(element)
+            .map(String::as_str)
+            .ok_or_else(|| OfxError::MissingElement {
+                file: self.file.to_string(),
+                element,
+                context: self.context(),
+            })
+    }
+
+    fn date(&self, element: &'static str) -> Result<NaiveDate, OfxError> {
+        let raw = self.field(element)?;
+        parse_date(raw).ok_or_else(|| OfxError::InvalidDate {
+            file: self.file.to_string(),
+            element,
+            context: self.context(),
+            raw: raw.to_string(),
+        })
+    }
+
+    fn amount(&self, element: &'static str, currency: Currency) -> Result<Money, OfxError> {
+        let raw = self.field(element)?;
+        with_decimal_point(raw)
+            .ok_or_else(|| MoneyError::InvalidAmount(raw.to_string()))
+            .and_then(|text| Money::parse(&text, currency))
+            .map_err(|source| OfxError::InvalidAmount {
+                file: self.file.to_string(),
+                element,
+                context: self.context(),
+                raw: raw.to_string(),
+                source,
+            })
+    }
+
+    fn transaction(&self, currency: Currency) -> Result<Data, OfxError> {
+        let date = self.date("DTPOSTED")?;
+        let amount = self.amount("TRNAMT", currency)?;
+        let description = ["NAME", "MEMO"]
+            .iter()
+            .filter_map(|element| self.fields.get(*element))
+            .map(|text| text.trim())
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<&str>>()
+            .join(" ");
+        let description = if description.is_empty() {
+            self.fields.get("TRNTYPE").cloned().unwrap_or_default()
+        } else {
+            description
+        };
+
+        let mut data = Data::new(date, amount, &description);
+        data.transaction_id = self
+            .fields
+            .get("FITID")
+            .map(|fitid| fitid.trim().to_string());
+        data.user_date = match self.fields.get("DTUSER") {
+            Some(_) => Some(self.date("DTUSER")?).filter(|user_date| *user_date != date),
+            None => None,
+        };
+        Ok(data)
+    }
+
+    fn balance(&self, currency: Currency) -> Result<StatementBalance, OfxError> {
+        Ok(StatementBalance {
+            amount: self.amount("BALAMT", currency)?,
+            date: self.date("DTASOF")?,
+        })
+    }
+}
+
+/// Everything that can go wrong while reading an OFX file
+///
+/// `context` names the aggregate holding the offending element, e.g. `STMTTRN "20240603001"`.
+#[derive(Debug)]
+pub enum OfxError {
+    /// The file could not be opened or read
+    Io { file: String, source: io::Error },
+    /// The file has no `<OFX>` element
+    NotOfx { file: String },
+    MissingElement {
+        file: String,
+        element: &'static str,
+        context: String,
+    },
+    InvalidCurrency {
+        file: String,
+        raw: String,
+        source: MoneyError,
+    },
+    InvalidDate {
+        file: String,
+        element: &'static str,
+        context: String,
+        raw: String,
+    },
+    InvalidAmount {
+        file: String,
+        element: &'static str,
+        context: String,
+        raw: String,
+        source: MoneyError,
+    },
+}
+
+impl fmt::Display for OfxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OfxError::Io { file, source } => write!(f, "{file}: {source}"),
+            OfxError::NotOfx { file } => write!(f, "{file}: not an OFX file, no <OFX> element"),
+            OfxError::MissingElement {
+                file,
+                element,
+                context,
+            } => write!(f, "{file}: {context}: missing {element}"),
+            OfxError::InvalidCurrency { file, raw, source } => {
+                write!(f, "{file}: CURDEF: {source} (got {raw:?})")
+            }
+            OfxError::InvalidDate {
+                file,
+                element,
+                context,
+                raw,
+            } => write!(
+                f,
+                "{file}: {context}: {element}: expected a date like 20240714, got {raw:?}"
+            ),
+            OfxError::InvalidAmount {
+                file,
+                element,
+                context,
+                raw,
+                source,
+            } => write!(f, "{file}: {context}: {element}: {source} (got {raw:?})"),
+        }
+    }
+}
+
+impl Error for OfxError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            OfxError::Io { source, .. } => Some(source),
+            OfxError::InvalidCurrency { source, .. } => Some(source),
+            OfxError::InvalidAmount { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// `raw` with the decimal comma some banks write, e.g. `-42,10`, turned into a point
+///
+/// A comma is only taken as the decimal separator when it is the only separator and one or two
+/// digits follow it. Returns `None` for any other comma, like the thousands separator of
+/// `-1,000`, which would otherwise import an amount 1000 times too small.
+fn with_decimal_point(raw: &str) -> Option<String> {
+    if !raw.contains(',') {
+        return Some(raw.to_string());
+    }
+    let (whole, fraction) = raw.split_once(',')?;
+    let decimal = !whole.contains('.')
+        && (1..=2).contains(&fraction.len())
+        && fraction.chars().all(|c| c.is_ascii_digit());
+    decimal.then(|| format!("{whole}.{fraction}"))
+}
+
+#[cfg(test)]
+#[path = "./tests/test.rs"]
+mod test;
@@ -0,0 +1,147 @@
+use super::*;
+use rstest::rstest;
+
+use crate::testing::{cad, date};
+
+const TEST_FILE_PATH: &str = "src/ofx/tests/test_statement.ofx";
+
+const XML_STATEMENT: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<?OFX OFXHEADER="200" VERSION="220" SECURITY="NONE" OLDFILEUID="NONE" NEWFILEUID="NONE"?>
+<OFX>
+  <SIGNONMSGSRSV1><SONRS><DTSERVER>20240702</DTSERVER></SONRS></SIGNONMSGSRSV1>
+  <CREDITCARDMSGSRSV1><CCSTMTTRNRS><CCSTMTRS>
+    <CURDEF>USD</CURDEF>
+    <CCACCTFROM><ACCTID>4111-1111-1111-1234</ACCTID></CCACCTFROM>
+    <BANKTRANLIST>
+      <STMTTRN>
+        <TRNTYPE>DEBIT</TRNTYPE>
+        <DTPOSTED>20240615000000.000[-5:EST]</DTPOSTED>
+        <TRNAMT>-42,10</TRNAMT>
+        <FITID>A1</FITID>
+        <MEMO>BOOKS</MEMO>
+      </STMTTRN>
+    </BANKTRANLIST>
+  </CCSTMTRS></CCSTMTTRNRS></CREDITCARDMSGSRSV1>
+</OFX>
+"#;
+
+#[test]
+fn test_parse_ofx() {
+    let output = parse_ofx(TEST_FILE_PATH).unwrap();
+
+    assert_eq!(3, output.rows.len());
+    let coffee = &output.rows[1];
+    assert_eq!(date("2024-06-11"), coffee.date);
+    assert_eq!(Some(date("2024-06-09")), coffee.user_date);
+    assert_eq!(cad("-12.50"), coffee.amount);
+    assert_eq!("COFFEE & CO VISA DEBIT", coffee.description);
+    assert_eq!(Some("20240611002"), coffee.transaction_id.as_deref());
+    assert_eq!(Some("************3055"), coffee.account_number.as_deref());
+    // Same day as posted
+    assert_eq!(None, output.rows[2].user_date);
+
+    let metadata = output.metadata;
+    assert_eq!(
+        date("2024-07-14").and_hms_opt(16, 48, 14),
+        metadata.valid_as_of
+    );
+    assert_eq!("OFXHEADER:100", metadata.preamble[0]);
+    assert_eq!(
+        Some((date("2024-06-03"), date("2024-06-28"))),
+        metadata.covered()
+    );
+    assert_eq!(
+        Some(StatementBalance {
+            amount: cad("4134.33"),
+            date: date("2024-06-30"),
+        }),
+        metadata.ledger_balance
+    );
+    assert_eq!(
+        Some(cad("4034.33")),
+        metadata.available_balance.map(|balance| balance.amount)
+    );
+}
+
+#[test]
+fn test_parse_xml_credit_card_statement() {
+    let output = parse_str("card.ofx", XML_STATEMENT).unwrap();
+
+    assert_eq!(1, output.rows.len());
+    let data = &output.rows[0];
+    assert_eq!(date("2024-06-15"), data.date);
+    assert_eq!(Money::parse("-42.10", Currency::USD).unwrap(), data.amount);
+    assert_eq!("BOOKS", data.description);
+    assert_eq!(Some("************1234"), data.account_number.as_deref());
+    assert_eq!(
+        date("2024-07-02").and_hms_opt(0, 0, 0),
+        output.metadata.valid_as_of
+    );
+    assert!(output.metadata.preamble.is_empty());
+}
+
+#[test]
+fn test_parse_errors() {
+    let without_amount = XML_STATEMENT.replace("<TRNAMT>-42,10</TRNAMT>", "");
+    let without_currency = XML_STATEMENT.replace("<CURDEF>USD</CURDEF>", "");
+
+    assert_eq!(
+        "card.ofx: STMTTRN \"A1\": missing TRNAMT",
+        parse_str("card.ofx", &without_amount)
+            .unwrap_err()
+            .to_string()
+    );
+    assert!(matches!(
+        parse_str("card.ofx", &without_currency),
+        Err(OfxError::MissingElement {
+            element: "CURDEF",
+            ..
+        })
+    ));
+    assert!(matches!(
+        parse_str("card.ofx", "Date,Amount\n"),
+        Err(OfxError::NotOfx { .. })
+    ));
+    assert!(matches!(parse_ofx("missing.ofx"), Err(OfxError::Io { .. })));
+}
+
+#[rstest]
+#[case("-42.10", Some("-42.10"))]
+#[case("-42,10", Some("-42.10"))]
+#[case("-42,1", Some("-42.1"))]
+#[case("-1,000", None)]
+#[case("-1,000.00", None)]
+#[case("-1.000,00", None)]
+#[case("1,2,3", None)]
+fn test_with_decimal_point(#[case] raw: &str, #[case] expected: Option<&str>) {
+    assert_eq!(expected, with_decimal_point(raw).as_deref());
+}
+
+#[test]
+fn test_thousands_separator_is_rejected() {
+    let statement = XML_STATEMENT.replace("<TRNAMT>-42,10</TRNAMT>", "<TRNAMT>-1,000</TRNAMT>");
+
+    assert!(matches!(
+        parse_str("card.ofx", &statement),
+        Err(OfxError::InvalidAmount {
+            element: "TRNAMT",
+            source: MoneyError::InvalidAmount(_),
+            ..
+        })
+    ));
+}
+
+#[rstest]
+#[case("OFXHEADER:100\nDATA:OFXSGML\n", true)]
+#[case("\u{feff}<?xml version=\"1.0\"?>\n<?OFX OFXHEADER=\"200\"?>", true)]
+#[case("<ofx><signonmsgsrsv1>", true)]
+#[case("First Bank Card,Transaction Type,Date Posted", false)]
+fn test_is_ofx(#[case] contents: &str, #[case] expected: bool) {
+    assert_eq!(expected, is_ofx(contents.as_bytes()));
+}
+
+#[test]
+fn test_is_ofx_file() {
+    assert!(is_ofx_file(TEST_FILE_PATH));
+    assert!(!is_ofx_file("src/csv_parser/tests/test_statement.csv"));
+}
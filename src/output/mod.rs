@@ -0,0 +1,154 @@
+use std::{error::Error, fmt};
+
+use serde::Serialize;
+
+/// How command results are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Aligned columns for the terminal
+    #[default]
+    Table,
+    Json,
+    Csv,
+}
+
+/// A row type that can be shown as a table or CSV, JSON output uses its `Serialize` impl
+pub trait Tabular: Serialize {
+    const HEADERS: &'static [&'static str];
+
+    /// One cell per header
+    fn cells(&self) -> Vec<String>;
+}
+
+/// Renders `rows` in `format`, without a trailing newline
+pub fn render<T: Tabular>(rows: &[T], format: OutputFormat) -> Result<String, OutputError> {
+    match format {
+        OutputFormat::Table => Ok(table(T::HEADERS, rows.iter().map(Tabular::cells))),
+        OutputFormat::Json => serde_json::to_string_pretty(rows).map_err(OutputError::Json),
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            writer.write_record(T::HEADERS).map_err(OutputError::Csv)?;
+            for row in rows {
+                writer.write_record(row.cells()).map_err(OutputError::Csv)?;
+            }
+            let bytes = writer
+                .into_inner()
+                .map_err(|e| OutputError::Csv(e.into_error().into()))?;
+            let text = String::from_utf8(bytes).expect("CSV output is built from strings");
+            Ok(text.trim_end().to_string())
+        }
+    }
+}
+
+/// Lays out cells in columns separated by two spaces. Cells that look like amounts are right
+/// aligned so the decimal points line up.
+fn table<I>(headers: &[&str], rows: I) -> String
+where
+    I: IntoIterator<Item = Vec<String>>,
+{
+    let rows: Vec<Vec<String>> = rows.into_iter().collect();
+    let mut widths: Vec<usize> = headers
+        .iter()
+        .map(|header| header.chars().count())
+        .collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let line = |cells: &[String]| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| {
+                if is_numeric(cell) {
+                    format!("{cell:>width$}")
+                } else {
+                    format!("{cell:<width$}")
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    let headers: Vec<String> = headers.iter().map(|header| header.to_string()).collect();
+    let separator: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+    let mut lines = vec![line(&headers), line(&separator)];
+    lines.extend(rows.iter().map(|row| line(row)));
+    lines.join("\n")
+}
+
+/// Renders `rows` as an HTML `<table>`, amounts get the `amount` class so a stylesheet can align
+/// them
+pub fn html_table<T: Tabular>(rows: &[T]) -> String {
+    let mut html = String::from("<table>\n<thead>\n<tr>");
+    for header in T::HEADERS {
+        html.push_str(&format!("<th>{}</th>", escape_html(header)));
+    }
+    html.push_str("</tr>\n</thead>\n<tbody>\n");
+    for row in rows {
+        html.push_str("<tr>");
+        for cell in row.cells() {
+            if is_numeric(&cell) {
+                html.push_str(&format!("<td class=\"amount\">{}</td>", escape_html(&cell)));
+            } else {
+                html.push_str(&format!("<td>{}</td>", escape_html(&cell)));
+            }
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</tbody>\n</table>");
+    html
+}
+
+/// Escapes the characters with a meaning in HTML text and attribute values
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn is_numeric(cell: &str) -> bool {
+    let digits = cell.trim_start_matches(['-', '+']).trim_end_matches('%');
+    !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit() || c == '.')
+}
+
+#[derive(Debug)]
+pub enum OutputError {
+    Json(serde_json::Error),
+    Csv(csv::Error),
+}
+
+impl fmt::Display for OutputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputError::Json(source) => write!(f, "could not write JSON: {source}"),
+            OutputError::Csv(source) => write!(f, "could not write CSV: {source}"),
+        }
+    }
+}
+
+impl Error for OutputError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            OutputError::Json(source) => Some(source),
+            OutputError::Csv(source) => Some(source),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./tests/test.rs"]
+mod test;
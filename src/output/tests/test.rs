@@ -0,0 +1,69 @@
+use super::*;
+
+#[derive(Serialize)]
+struct Total {
+    category: String,
+    amount: String,
+}
+
+impl Tabular for Total {
+    const HEADERS: &'static [&'static str] = &["Category", "Amount"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![self.category.clone(), self.amount.clone()]
+    }
+}
+
+fn totals() -> Vec<Total> {
+    vec![
+        Total {
+            category: String::from("bills"),
+            amount: String::from("-1606.44"),
+        },
+        Total {
+            category: String::from("other, misc"),
+            amount: String::from("521.30"),
+        },
+    ]
+}
+
+#[test]
+fn test_render_table() {
+    assert_eq!(
+        "Category     Amount\n\
+         -----------  --------\n\
+         bills        -1606.44\n\
+         other, misc    521.30",
+        render(&totals(), OutputFormat::Table).unwrap()
+    );
+}
+
+#[test]
+fn test_render_csv() {
+    assert_eq!(
+        "Category,Amount\nbills,-1606.44\n\"other, misc\",521.30",
+        render(&totals(), OutputFormat::Csv).unwrap()
+    );
+}
+
+#[test]
+fn test_render_json() {
+    let json: serde_json::Value =
+        serde_json::from_str(&render(&totals(), OutputFormat::Json).unwrap()).unwrap();
+    assert_eq!("-1606.44", json[0]["amount"]);
+    assert_eq!("other, misc", json[1]["category"]);
+}
+
+#[test]
+fn test_html_table() {
+    let mut rows = totals();
+    rows[1].category = String::from("<b>\"R&D\"</b>");
+
+    assert_eq!(
+        "<table>\n<thead>\n<tr><th>Category</th><th>Amount</th></tr>\n</thead>\n<tbody>\n\
+         <tr><td>bills</td><td class=\"amount\">-1606.44</td></tr>\n\
+         <tr><td>&lt;b&gt;&quot;R&amp;D&quot;&lt;/b&gt;</td><td class=\"amount\">521.30</td></tr>\n\
+         </tbody>\n</table>",
+        html_table(&rows)
+    );
+}
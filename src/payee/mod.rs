@@ -0,0 +1,179 @@
+use std::{collections::HashMap, error::Error, fmt, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+/// A description split into its parts, e.g. `[CW] TF 000123456789` has channel `CW`, payee `TF`
+/// and reference `000123456789`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Description {
+    /// The bracketed channel code the description starts with
+    pub channel: Option<String>,
+    /// The merchant or payee, with runs of spaces collapsed and reference tokens removed, or its
+    /// alias
+    pub payee: String,
+    /// Reference numbers in the description, in order
+    pub references: Vec<String>,
+}
+
+impl Description {
+    /// Splits `text` without applying aliases
+    pub fn parse(text: &str) -> Description {
+        let mut rest = text.trim();
+        let mut channel = None;
+        if let Some((code, after)) = rest
+            .strip_prefix('[')
+            .and_then(|inner| inner.split_once(']'))
+        {
+            let code = code.trim();
+            if !code.is_empty() && !code.contains(char::is_whitespace) {
+                channel = Some(code.to_string());
+                rest = after;
+            }
+        }
+
+        let mut words = Vec::new();
+        let mut references = Vec::new();
+        for word in rest.split_whitespace() {
+            if is_reference(word) {
+                references.push(word.to_string());
+            } else {
+                words.push(word);
+            }
+        }
+        // A description made of references only is its own payee
+        let payee = if words.is_empty() {
+            references.join(" ")
+        } else {
+            words.join(" ")
+        };
+        Description {
+            channel,
+            payee,
+            references,
+        }
+    }
+}
+
+/// Whether `word` is a reference number rather than part of a name: at least four digits making
+/// up at least half of its letters and digits, e.g. `000123456789`, `#4471` or `REF20240611`
+fn is_reference(word: &str) -> bool {
+    let digits = word.chars().filter(char::is_ascii_digit).count();
+    let alphanumeric = word.chars().filter(|c| c.is_alphanumeric()).count();
+    digits >= 4 && digits * 2 >= alphanumeric
+}
+
+/// The form payee names are compared in: upper case with single spaces
+fn key(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_uppercase()
+}
+
+/// Whether two payee names are the same, ignoring case and spacing
+pub fn same_payee(a: &str, b: &str) -> bool {
+    key(a) == key(b)
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AliasesFile {
+    #[serde(default)]
+    aliases: HashMap<String, String>,
+}
+
+/// Names to show instead of the payees banks write, read from a TOML file the user edits:
+///
+/// ```toml
+/// [aliases]
+/// "LIFESTYL MSP/DIV" = "Lifestyle Markets dividend"
+/// "INTERAC ETRNSFR SENT BROTHER" = "Transfer to brother"
+/// ```
+///
+/// Keys are cleaned payees as `Description::parse` returns them, matched ignoring case and
+/// spacing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Aliases {
+    aliases: HashMap<String, String>,
+}
+
+impl Aliases {
+    /// An empty table, payees are shown as cleaned
+    pub fn new() -> Aliases {
+        Aliases::default()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Aliases, AliasError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|source| AliasError::Io {
+            file: path.display().to_string(),
+            source,
+        })?;
+        Aliases::from_toml(&text)
+    }
+
+    pub fn from_toml(text: &str) -> Result<Aliases, AliasError> {
+        let file: AliasesFile = toml::from_str(text).map_err(AliasError::Toml)?;
+        let mut aliases = Aliases::new();
+        for (payee, alias) in file.aliases {
+            if payee.trim().is_empty() || alias.trim().is_empty() {
+                return Err(AliasError::Empty(payee));
+            }
+            aliases.insert(&payee, &alias);
+        }
+        Ok(aliases)
+    }
+
+    pub fn insert(&mut self, payee: &str, alias: &str) {
+        self.aliases.insert(key(payee), alias.trim().to_string());
+    }
+
+    /// The alias of a cleaned payee
+    pub fn get(&self, payee: &str) -> Option<&str> {
+        self.aliases.get(&key(payee)).map(String::as_str)
+    }
+
+    /// Splits `text` and replaces its payee by its alias, if it has one
+    pub fn normalize(&self, text: &str) -> Description {
+        let mut description = Description::parse(text);
+        if let Some(alias) = self.get(&description.payee) {
+            description.payee = alias.to_string();
+        }
+        description
+    }
+}
+
+#[derive(Debug)]
+pub enum AliasError {
+    Io {
+        file: String,
+        source: io::Error,
+    },
+    Toml(toml::de::Error),
+    /// A payee or alias is blank
+    Empty(String),
+}
+
+impl fmt::Display for AliasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AliasError::Io { file, source } => write!(f, "{file}: {source}"),
+            AliasError::Toml(source) => write!(f, "invalid aliases file: {source}"),
+            AliasError::Empty(payee) => write!(f, "blank payee alias {payee:?}"),
+        }
+    }
+}
+
+impl Error for AliasError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AliasError::Io { source, .. } => Some(source),
+            AliasError::Toml(source) => Some(source),
+            AliasError::Empty(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./tests/test.rs"]
+mod test;
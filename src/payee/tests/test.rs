@@ -0,0 +1,79 @@
+use super::*;
+use rstest::rstest;
+
+use crate::csv_parser::parse_csv;
+
+const TEST_FILE_PATH: &str = "src/csv_parser/tests/test_statement.csv";
+
+const ALIASES_TOML: &str = r#"
+[aliases]
+"lifestyl msp/div" = "Lifestyle Markets dividend"
+"INTERAC ETRNSFR SENT   BROTHER" = "Transfer to brother"
+"#;
+
+#[rstest]
+#[case("[DS]BANK         MTG/HYP", Some("DS"), "BANK MTG/HYP", &[])]
+#[case("[CW]INTERAC ETRNSFR SENT     BROTHER", Some("CW"), "INTERAC ETRNSFR SENT BROTHER", &[])]
+#[case("[IB] SHAUGHNES", Some("IB"), "SHAUGHNES", &[])]
+#[case("[CW] TF 000123456789", Some("CW"), "TF", &["000123456789"])]
+#[case("COFFEE & CO #4471 VANCOUVER", None, "COFFEE & CO VANCOUVER", &["#4471"])]
+#[case("PAYROLL REF20240611", None, "PAYROLL", &["REF20240611"])]
+#[case("7-ELEVEN 2024", None, "7-ELEVEN", &["2024"])]
+#[case("000123456789", None, "000123456789", &["000123456789"])]
+#[case("[NOT A CODE] RENT", None, "[NOT A CODE] RENT", &[])]
+fn test_parse(
+    #[case] text: &str,
+    #[case] channel: Option<&str>,
+    #[case] payee: &str,
+    #[case] references: &[&str],
+) {
+    let description = Description::parse(text);
+
+    assert_eq!(channel, description.channel.as_deref());
+    assert_eq!(payee, description.payee);
+    assert_eq!(references, description.references.as_slice());
+}
+
+#[test]
+fn test_normalize_sample_statement() {
+    let aliases = Aliases::from_toml(ALIASES_TOML).unwrap();
+    let rows = parse_csv(TEST_FILE_PATH).unwrap();
+
+    let payees: Vec<String> = rows
+        .iter()
+        .map(|data| aliases.normalize(&data.description).payee)
+        .collect();
+
+    assert_eq!(
+        vec![
+            "BANK MTG/HYP",
+            "STRATA FEE",
+            "Transfer to brother",
+            "Lifestyle Markets dividend",
+            "SHAUGHNES",
+            "TF",
+            "CITY TAX",
+            "PREMIUM PLAN",
+            "FULL PLAN FEE REBATE",
+        ],
+        payees
+    );
+}
+
+#[test]
+fn test_aliases() {
+    let mut aliases = Aliases::new();
+    aliases.insert("city  tax", "City of Vancouver");
+
+    assert_eq!(Some("City of Vancouver"), aliases.get("CITY TAX"));
+    assert_eq!(None, aliases.get("CITY"));
+    assert!(same_payee("Strata  fee", "STRATA FEE"));
+    assert!(matches!(
+        Aliases::from_toml("[aliases]\n\"CITY TAX\" = \" \"\n"),
+        Err(AliasError::Empty(payee)) if payee == "CITY TAX"
+    ));
+    assert!(matches!(
+        Aliases::from_toml("[payees]\n"),
+        Err(AliasError::Toml(_))
+    ));
+}
@@ -0,0 +1,490 @@
+use std::{error::Error, fmt, fs, io, path::Path};
+
+use chrono::NaiveDate;
+
+use crate::account::AccountType;
+use crate::category::{CategoryTree, TransactionCategory};
+use crate::csv_parser::{Data, Split};
+use crate::money::{Currency, Money, MoneyError};
+
+/// Name of the QIF format in import summaries and `import --format`
+pub const QIF_FORMAT: &str = "qif";
+
+/// `Data::categorized_by` of rows filed by the category line of a QIF file
+pub const QIF_RULE: &str = "qif";
+
+/// Separator of parent and child category names in QIF category lines, e.g. `Housing:Mortgage`
+const CATEGORY_SEPARATOR: char = ':';
+
+/// Whether `contents` looks like a QIF file, i.e. starts with a `!Type`, `!Account` or `!Option`
+/// header
+pub fn is_qif(contents: &[u8]) -> bool {
+    let start = String::from_utf8_lossy(&contents[..contents.len().min(64)]).to_lowercase();
+    let start = start.trim_start_matches('\u{feff}').trim_start();
+    ["!type:", "!account", "!option:"]
+        .iter()
+        .any(|header| start.starts_with(header))
+}
+
+/// Whether the file at `file_path` is a QIF file, judged by its extension or, failing that, its
+/// contents
+pub fn is_qif_file(file_path: &str) -> bool {
+    let extension = Path::new(file_path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    extension.as_deref() == Some("qif")
+        || fs::read(file_path).is_ok_and(|contents| is_qif(&contents))
+}
+
+/// How the numbers of a QIF date are ordered. QIF files are written with the exporting program's
+/// locale, so this can't be told from the file itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DateOrder {
+    /// `06/03/2024` is June 3rd
+    #[default]
+    MonthFirst,
+    /// `03/06/2024` is June 3rd
+    DayFirst,
+}
+
+/// A QIF transaction record
+#[derive(Debug, Clone, PartialEq)]
+pub struct QifTransaction {
+    /// The payee is the description, the category line the category and the `S`, `E` and `$`
+    /// lines of split transactions the splits
+    pub data: Data,
+    pub memo: Option<String>,
+    /// Check or reference number
+    pub number: Option<String>,
+}
+
+impl QifTransaction {
+    /// A transaction without memo or number
+    pub fn new(data: Data) -> QifTransaction {
+        QifTransaction {
+            data,
+            memo: None,
+            number: None,
+        }
+    }
+}
+
+/// Reads the bank, cash, credit card and other asset or liability transactions of a QIF file
+///
+/// Amounts are in `currency`, QIF files don't say. Category lines are matched against the names
+/// of `tree`, e.g. `Housing:Mortgage` against `Housing > Mortgage`, falling back to category ids
+/// and then to the nearest matching parent. Transfers to other accounts, written as `[Savings]`,
+/// are filed under `ACCOUNT_TRANSFERS`. Investment, category list and memorized transaction
+/// sections are skipped.
+pub fn read_qif(
+    file_path: &str,
+    currency: Currency,
+    tree: &CategoryTree,
+    order: DateOrder,
+) -> Result<Vec<QifTransaction>, QifError> {
+    let contents = fs::read(file_path).map_err(|source| QifError::Io {
+        file: file_path.to_string(),
+        source,
+    })?;
+    let reader = Reader {
+        file: file_path,
+        currency,
+        tree,
+        order,
+    };
+    reader.read(&String::from_utf8_lossy(&contents))
+}
+
+/// Settings for reading one file
+struct Reader<'a> {
+    file: &'a str,
+    currency: Currency,
+    tree: &'a CategoryTree,
+    order: DateOrder,
+}
+
+/// A line of a record: its 1-based line number, field code and value
+type Field<'a> = (u64, char, &'a str);
+
+impl Reader<'_> {
+    fn read(&self, contents: &str) -> Result<Vec<QifTransaction>, QifError> {
+        let mut transactions = Vec::new();
+        let mut in_transactions = false;
+        let mut record: Vec<Field> = Vec::new();
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim_start_matches('\u{feff}').trim_end();
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('!') {
+                let header = header.trim().to_lowercase();
+                if let Some(kind) = header.strip_prefix("type:") {
+                    in_transactions =
+                        matches!(kind.trim(), "bank" | "cash" | "ccard" | "oth a" | "oth l");
+                } else if header == "account" {
+                    in_transactions = false;
+                }
+                record.clear();
+                continue;
+            }
+            if line.trim() == "^" {
+                if in_transactions && !record.is_empty() {
+                    transactions.push(self.transaction(&record)?);
+                }
+                record.clear();
+                continue;
+            }
+            let mut chars = line.chars();
+            let code = chars.next().expect("blank lines are skipped");
+            record.push((index as u64 + 1, code, chars.as_str().trim()));
+        }
+        // Tolerate a missing `^` after the last record
+        if in_transactions && !record.is_empty() {
+            transactions.push(self.transaction(&record)?);
+        }
+        Ok(transactions)
+    }
+
+    fn transaction(&self, record: &[Field]) -> Result<QifTransaction, QifError> {
+        let first_line = record[0].0;
+        let find = |code: char| record.iter().find(|(_, field, _)| *field == code);
+        let text = |code: char| {
+            find(code)
+                .map(|(_, _, value)| value.to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let missing = |field: char| QifError::MissingField {
+            file: self.file.to_string(),
+            line: first_line,
+            field,
+        };
+
+        let &(line, _, raw) = find('D').ok_or_else(|| missing('D'))?;
+        let date = self.date(line, raw)?;
+        let &(line, _, raw) = find('T')
+            .or_else(|| find('U'))
+            .ok_or_else(|| missing('T'))?;
+        let amount = self.amount(line, raw)?;
+        let payee = text('P');
+        let memo = text('M');
+        let description = payee.as_deref().or(memo.as_deref()).unwrap_or_default();
+
+        let mut data = Data::new(date, amount, description);
+        if let Some(category) = text('L') {
+            data.category = self.category(&category);
+            data.categorized_by = Some(QIF_RULE.to_string());
+        }
+
+        let mut splits = Vec::new();
+        // Category, memo and amount of the split being read, with the line it starts on
+        let mut split: Option<(u64, TransactionCategory, Option<String>, Option<Money>)> = None;
+        for &(line, code, value) in record {
+            match code {
+                'S' => {
+                    if let Some(done) = split.take() {
+                        splits.push(self.split(done)?);
+                    }
+                    split = Some((line, self.category(value), None, None));
+                }
+                'E' => {
+                    if let Some((_, _, memo, _)) = &mut split {
+                        *memo = Some(value.to_string()).filter(|memo| !memo.is_empty());
+                    }
+                }
+                '$' => {
+                    if let Some((_, _, _, amount)) = &mut split {
+                        *amount = Some(self.amount(line, value)?);
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some(done) = split.take() {
+            splits.push(self.split(done)?);
+        }
+        if !splits.is_empty() {
+            let total = Money::checked_sum(splits.iter().map(|split| split.amount), self.currency)
+                .map_err(|source| QifError::InvalidAmount {
+                    file: self.file.to_string(),
+                    line: first_line,
+                    raw: "$".to_string(),
+                    source,
+                })?;
+            if total != amount {
+                return Err(QifError::UnbalancedSplits {
+                    file: self.file.to_string(),
+                    line: first_line,
+                    amount,
+                    splits: total,
+                });
+            }
+        }
+
+        data.splits = splits;
+        Ok(QifTransaction {
+            data,
+            memo,
+            number: text('N'),
+        })
+    }
+
+    fn split(
+        &self,
+        (line, category, memo, amount): (u64, TransactionCategory, Option<String>, Option<Money>),
+    ) -> Result<Split, QifError> {
+        let amount = amount.ok_or_else(|| QifError::MissingField {
+            file: self.file.to_string(),
+            line,
+            field: '$',
+        })?;
+        Ok(Split {
+            category,
+            amount,
+            memo,
+        })
+    }
+
+    fn date(&self, line: u64, raw: &str) -> Result<NaiveDate, QifError> {
+        parse_date(raw, self.order).ok_or_else(|| QifError::InvalidDate {
+            file: self.file.to_string(),
+            line,
+            raw: raw.to_string(),
+        })
+    }
+
+    fn amount(&self, line: u64, raw: &str) -> Result<Money, QifError> {
+        without_thousands_separators(raw)
+            .ok_or_else(|| MoneyError::InvalidAmount(raw.to_string()))
+            .and_then(|text| Money::parse(&text, self.currency))
+            .map_err(|source| QifError::InvalidAmount {
+                file: self.file.to_string(),
+                line,
+                raw: raw.to_string(),
+                source,
+            })
+    }
+
+    fn category(&self, text: &str) -> TransactionCategory {
+        // A class may follow the category after a slash
+        let text = text.split('/').next().unwrap_or_default().trim();
+        if text.starts_with('[') && text.ends_with(']') {
+            return TransactionCategory::ACCOUNT_TRANSFERS;
+        }
+        let names: Vec<String> = text
+            .split(CATEGORY_SEPARATOR)
+            .map(|name| name.trim().to_lowercase())
+            .collect();
+        let by_names = |length: usize| {
+            self.tree
+                .iter()
+                .find(|category| category_names(self.tree, &category.id) == names[..length])
+        };
+        let by_id = || {
+            self.tree
+                .iter()
+                .find(|category| category.id.id() == text.to_lowercase())
+        };
+        by_names(names.len())
+            .or_else(by_id)
+            .or_else(|| (1..names.len()).rev().find_map(by_names))
+            .map_or(TransactionCategory::OTHER, |category| category.id.clone())
+    }
+}
+
+/// `raw` without the commas grouping thousands, e.g. `1,374.47` becomes `1374.47`
+///
+/// Returns `None` for any other comma, like the decimal comma of `-12,50`, which can't be told
+/// apart from a misplaced separator and would otherwise import an amount 100 times too large.
+fn without_thousands_separators(raw: &str) -> Option<String> {
+    let (whole, fraction) = raw.split_once('.').unwrap_or((raw, ""));
+    if fraction.contains(',') {
+        return None;
+    }
+    let mut groups = whole.split(',');
+    groups.next();
+    if !groups.all(|group| group.len() == 3 && group.chars().all(|c| c.is_ascii_digit())) {
+        return None;
+    }
+    Some(raw.replace(',', ""))
+}
+
+/// The lowercased names of a category and its ancestors, root first
+fn category_names(tree: &CategoryTree, id: &TransactionCategory) -> Vec<String> {
+    let mut names: Vec<String> = tree
+        .ancestors(id)
+        .map(|category| category.name.to_lowercase())
+        .collect();
+    names.reverse();
+    names
+}
+
+/// Parses the date styles QIF writers use, e.g. `6/ 3/24`, `06/03'2024`, `6-3-2024` and
+/// `2024-06-03`. Two digit years are taken as 1970 to 2069.
+fn parse_date(raw: &str, order: DateOrder) -> Option<NaiveDate> {
+    let numbers: Vec<u32> = raw
+        .split(['/', '-', '.', '\''])
+        .map(|part| part.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    let [first, second, third] = numbers[..] else {
+        return None;
+    };
+    if first > 999 {
+        return NaiveDate::from_ymd_opt(first as i32, second, third);
+    }
+    let year = match third {
+        0..=69 => 2000 + third,
+        70..=99 => 1900 + third,
+        _ => third,
+    };
+    let (month, day) = match order {
+        DateOrder::MonthFirst => (first, second),
+        DateOrder::DayFirst => (second, first),
+    };
+    NaiveDate::from_ymd_opt(year as i32, month, day)
+}
+
+/// The QIF section type of an account, e.g. `CCard`
+fn section_type(account_type: AccountType) -> &'static str {
+    match account_type {
+        AccountType::CreditCard => "CCard",
+        AccountType::LineOfCredit => "Oth L",
+        AccountType::Investment => "Oth A",
+        AccountType::Chequing | AccountType::Savings | AccountType::Other => "Bank",
+    }
+}
+
+/// Writes `transactions` as one QIF account section
+///
+/// With a `name`, the section starts with an `!Account` record so programs importing several
+/// sections know which account each belongs to. Dates are written month first with four digit
+/// years and categories as their names joined by colons, e.g. `Housing:Mortgage`.
+pub fn write_qif(
+    writer: &mut dyn io::Write,
+    name: Option<&str>,
+    account_type: AccountType,
+    transactions: &[QifTransaction],
+    tree: &CategoryTree,
+) -> io::Result<()> {
+    let kind = section_type(account_type);
+    if let Some(name) = name {
+        writeln!(writer, "!Account\nN{name}\nT{kind}\n^")?;
+    }
+    writeln!(writer, "!Type:{kind}")?;
+    for transaction in transactions {
+        let data = &transaction.data;
+        writeln!(writer, "D{}", data.date.format("%m/%d/%Y"))?;
+        writeln!(writer, "T{}", data.amount)?;
+        if let Some(number) = &transaction.number {
+            writeln!(writer, "N{number}")?;
+        }
+        writeln!(writer, "P{}", data.description)?;
+        if let Some(memo) = &transaction.memo {
+            writeln!(writer, "M{memo}")?;
+        }
+        writeln!(writer, "L{}", category_line(tree, &data.category))?;
+        for split in &data.splits {
+            writeln!(writer, "S{}", category_line(tree, &split.category))?;
+            if let Some(memo) = &split.memo {
+                writeln!(writer, "E{memo}")?;
+            }
+            writeln!(writer, "${}", split.amount)?;
+        }
+        writeln!(writer, "^")?;
+    }
+    Ok(())
+}
+
+/// The QIF category line of a category, its names joined by colons. Unknown ids are written as
+/// is.
+fn category_line(tree: &CategoryTree, id: &TransactionCategory) -> String {
+    let mut names: Vec<&str> = tree
+        .ancestors(id)
+        .map(|category| category.name.as_str())
+        .collect();
+    if names.is_empty() {
+        return id.to_string();
+    }
+    names.reverse();
+    names.join(&CATEGORY_SEPARATOR.to_string())
+}
+
+/// Everything that can go wrong while reading a QIF file
+///
+/// `line` is the 1-based line of the offending field, or of the record's first line for errors
+/// about the whole record.
+#[derive(Debug)]
+pub enum QifError {
+    /// The file could not be opened or read
+    Io { file: String, source: io::Error },
+    /// A record lacks its date (`D`), amount (`T`) or a split's amount (`$`)
+    MissingField {
+        file: String,
+        line: u64,
+        field: char,
+    },
+    InvalidDate {
+        file: String,
+        line: u64,
+        raw: String,
+    },
+    InvalidAmount {
+        file: String,
+        line: u64,
+        raw: String,
+        source: MoneyError,
+    },
+    /// The splits of a transaction don't add up to its amount
+    UnbalancedSplits {
+        file: String,
+        line: u64,
+        amount: Money,
+        splits: Money,
+    },
+}
+
+impl fmt::Display for QifError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QifError::Io { file, source } => write!(f, "{file}: {source}"),
+            QifError::MissingField { file, line, field } => {
+                write!(f, "{file}:{line}: record has no {field} line")
+            }
+            QifError::InvalidDate { file, line, raw } => {
+                write!(
+                    f,
+                    "{file}:{line}: expected a date like 06/03/2024, got {raw:?}"
+                )
+            }
+            QifError::InvalidAmount {
+                file,
+                line,
+                raw,
+                source,
+            } => write!(f, "{file}:{line}: {source} (got {raw:?})"),
+            QifError::UnbalancedSplits {
+                file,
+                line,
+                amount,
+                splits,
+            } => write!(
+                f,
+                "{file}:{line}: splits add up to {splits}, the transaction amount is {amount}"
+            ),
+        }
+    }
+}
+
+impl Error for QifError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            QifError::Io { source, .. } => Some(source),
+            QifError::InvalidAmount { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./tests/test.rs"]
+mod test;
@@ -0,0 +1,219 @@
+use super::*;
+use rstest::rstest;
+
+use crate::testing::{cad, date};
+
+const TEST_FILE_PATH: &str = "src/qif/tests/test_statement.qif";
+
+const CATEGORIES_TOML: &str = r#"
+[[category]]
+id = "housing"
+name = "Housing"
+
+[[category]]
+id = "housing.mortgage"
+name = "Mortgage"
+parent = "housing"
+"#;
+
+fn tree() -> CategoryTree {
+    CategoryTree::from_toml(CATEGORIES_TOML).unwrap()
+}
+
+fn read(contents: &str) -> Result<Vec<QifTransaction>, QifError> {
+    let tree = tree();
+    let reader = Reader {
+        file: "test.qif",
+        currency: Currency::CAD,
+        tree: &tree,
+        order: DateOrder::MonthFirst,
+    };
+    reader.read(contents)
+}
+
+#[test]
+fn test_read_qif() {
+    let transactions = read_qif(
+        TEST_FILE_PATH,
+        Currency::CAD,
+        &tree(),
+        DateOrder::MonthFirst,
+    )
+    .unwrap();
+
+    // The category list section is skipped
+    assert_eq!(4, transactions.len());
+    let mortgage = &transactions[0].data;
+    assert_eq!(date("2024-06-03"), mortgage.date);
+    assert_eq!(cad("-1374.47"), mortgage.amount);
+    assert_eq!(
+        TransactionCategory::new("housing.mortgage"),
+        mortgage.category
+    );
+    assert_eq!(Some(QIF_RULE), mortgage.categorized_by.as_deref());
+
+    let transfer = &transactions[1];
+    assert_eq!(date("2024-06-10"), transfer.data.date);
+    assert_eq!(
+        TransactionCategory::ACCOUNT_TRANSFERS,
+        transfer.data.category
+    );
+    assert_eq!(Some("1042"), transfer.number.as_deref());
+    assert_eq!(Some("Birthday gift"), transfer.memo.as_deref());
+
+    let costco = &transactions[2];
+    assert_eq!(
+        vec![
+            Split {
+                category: TransactionCategory::FOOD,
+                amount: cad("-110.00"),
+                memo: Some("Groceries".to_string()),
+            },
+            Split {
+                category: TransactionCategory::new("housing"),
+                amount: cad("-40.00"),
+                memo: Some("Hardware".to_string()),
+            },
+        ],
+        costco.data.splits
+    );
+
+    // Unknown categories are left uncategorized, the last record has no `^`
+    assert_eq!(TransactionCategory::OTHER, transactions[3].data.category);
+    assert_eq!(cad("521.30"), transactions[3].data.amount);
+}
+
+#[test]
+fn test_split_allocations() {
+    let transactions = read_qif(
+        TEST_FILE_PATH,
+        Currency::CAD,
+        &tree(),
+        DateOrder::MonthFirst,
+    )
+    .unwrap();
+
+    let costco = &transactions[2].data;
+    let rows = costco.allocations();
+
+    assert!(costco.splits_balance());
+    assert_eq!(2, rows.len());
+    assert_eq!("COSTCO Groceries", rows[0].description);
+    assert_eq!(cad("-110.00"), rows[0].amount);
+    assert_eq!(TransactionCategory::FOOD, rows[0].category);
+    assert_eq!(Some(QIF_RULE), rows[0].categorized_by.as_deref());
+    assert_eq!(TransactionCategory::new("housing"), rows[1].category);
+    assert_eq!(
+        vec![transactions[0].data.clone()],
+        transactions[0].data.allocations()
+    );
+}
+
+#[test]
+fn test_read_errors() {
+    let unbalanced =
+        read("!Type:Bank\nD06/15/2024\nT-150.00\nSFood\n$-100.00\nSHousing\n$-40.00\n^\n");
+    let split_without_amount = read("!Type:Bank\nD06/15/2024\nT-150.00\nSFood\n^\n");
+    let without_amount = read("!Type:CCard\nD06/15/2024\nPCOFFEE\n^\n");
+    let bad_date = read("!Type:Bank\nD2024/15/06\nT1.00\n^\n");
+
+    assert_eq!(
+        "test.qif:2: splits add up to -140.00, the transaction amount is -150.00",
+        unbalanced.unwrap_err().to_string()
+    );
+    assert!(matches!(
+        split_without_amount,
+        Err(QifError::MissingField {
+            line: 4,
+            field: '$',
+            ..
+        })
+    ));
+    assert!(matches!(
+        without_amount,
+        Err(QifError::MissingField {
+            line: 2,
+            field: 'T',
+            ..
+        })
+    ));
+    assert!(matches!(
+        bad_date,
+        Err(QifError::InvalidDate { line: 2, .. })
+    ));
+    assert!(read("!Type:Invst\nD06/15/2024\nNBuy\n^\n")
+        .unwrap()
+        .is_empty());
+}
+
+#[rstest]
+#[case("-1,374.47", Some("-1374.47"))]
+#[case("12,345,678", Some("12345678"))]
+#[case("-80.0", Some("-80.0"))]
+#[case("-12,50", None)]
+#[case("1.234,56", None)]
+#[case("1,2345.00", None)]
+fn test_thousands_separators(#[case] raw: &str, #[case] expected: Option<&str>) {
+    assert_eq!(
+        expected.map(str::to_string),
+        without_thousands_separators(raw)
+    );
+}
+
+#[test]
+fn test_decimal_comma_is_rejected() {
+    let decimal_comma = read("!Type:Bank\nD03/06/2024\nT-12,50\nPBAKERY\n^\n");
+
+    assert!(matches!(
+        decimal_comma,
+        Err(QifError::InvalidAmount { line: 3, ref raw, .. }) if raw == "-12,50"
+    ));
+}
+
+#[rstest]
+#[case("06/03/2024", DateOrder::MonthFirst, Some("2024-06-03"))]
+#[case("6/ 3'24", DateOrder::MonthFirst, Some("2024-06-03"))]
+#[case("6-3-99", DateOrder::MonthFirst, Some("1999-06-03"))]
+#[case("03.06.2024", DateOrder::DayFirst, Some("2024-06-03"))]
+#[case("2024-06-03", DateOrder::DayFirst, Some("2024-06-03"))]
+#[case("13/03/2024", DateOrder::MonthFirst, None)]
+#[case("June 3", DateOrder::MonthFirst, None)]
+fn test_parse_date(#[case] raw: &str, #[case] order: DateOrder, #[case] expected: Option<&str>) {
+    assert_eq!(expected.map(date), parse_date(raw, order));
+}
+
+#[test]
+fn test_write_qif_round_trip() {
+    let tree = tree();
+    let transactions =
+        read_qif(TEST_FILE_PATH, Currency::CAD, &tree, DateOrder::MonthFirst).unwrap();
+    let mut written = Vec::new();
+
+    write_qif(
+        &mut written,
+        Some("chequing"),
+        AccountType::Chequing,
+        &transactions[..3],
+        &tree,
+    )
+    .unwrap();
+
+    let text = String::from_utf8(written).unwrap();
+    assert!(text.starts_with(
+        "!Account\nNchequing\nTBank\n^\n!Type:Bank\n\
+         D06/03/2024\nT-1374.47\nPBANK MTG/HYP\nLHousing:Mortgage\n^\n"
+    ));
+    assert!(text.contains("LAccount Transfers\n"));
+    assert!(text.ends_with("SFood\nEGroceries\n$-110.00\nSHousing\nEHardware\n$-40.00\n^\n"));
+    // The account record is skipped on the way back in
+    let read_back = read(&text).unwrap();
+    assert_eq!(transactions[..3], read_back[..]);
+}
+
+#[rstest]
+#[case("!Type:Bank\nD06/03/2024", true)]
+#[case("\u{feff}!Option:AutoSwitch\n!Account", true)]
+#[case("First Bank Card,Transaction Type", false)]
+fn test_is_qif(#[case] contents: &str, #[case] expected: bool) {
+    assert_eq!(expected, is_qif(contents.as_bytes()));
+}
@@ -0,0 +1,214 @@
+use std::{fmt, str::FromStr};
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::csv_parser::Data;
+use crate::money::{Currency, Money, MoneyError};
+use crate::output::Tabular;
+
+/// A balance the account is known to have had at the end of a day, e.g. the closing balance of a
+/// paper statement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Checkpoint {
+    pub date: NaiveDate,
+    pub balance: Money,
+}
+
+impl Checkpoint {
+    /// Parses `2024-06-30=1234.56`
+    pub fn parse(text: &str, currency: Currency) -> Result<Checkpoint, String> {
+        let (date, balance) = text
+            .split_once('=')
+            .ok_or_else(|| format!("expected DATE=BALANCE, got {text:?}"))?;
+        Ok(Checkpoint {
+            date: NaiveDate::from_str(date.trim()).map_err(|e| format!("{date:?}: {e}"))?,
+            balance: Money::parse(balance, currency).map_err(|e| e.to_string())?,
+        })
+    }
+}
+
+/// A transaction with the balance it leaves the account with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BalanceEntry {
+    pub date: NaiveDate,
+    pub description: String,
+    pub amount: Money,
+    /// Opening balance plus every transaction up to this one
+    pub balance: Money,
+    /// Balance printed on the statement, when it has a balance column
+    pub reported: Option<Money>,
+}
+
+/// Where an expected balance comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckSource {
+    /// The balance column of the row at this index of `Reconciliation::entries`
+    Row(usize),
+    Checkpoint,
+}
+
+/// An expected balance compared to the computed one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Check {
+    pub date: NaiveDate,
+    pub source: CheckSource,
+    pub expected: Money,
+    pub computed: Money,
+}
+
+impl Check {
+    pub fn agrees(&self) -> bool {
+        self.expected == self.computed
+    }
+
+    /// Expected minus computed, positive when transactions are missing that brought money in
+    pub fn difference(&self) -> Result<Money, MoneyError> {
+        self.expected.checked_sub(self.computed)
+    }
+}
+
+/// The running balance of an account, checked against every known balance
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Reconciliation {
+    pub opening: Money,
+    pub entries: Vec<BalanceEntry>,
+    /// In date order, a day's row checks before its checkpoint
+    pub checks: Vec<Check>,
+}
+
+impl Reconciliation {
+    pub fn closing(&self) -> Money {
+        self.entries
+            .last()
+            .map_or(self.opening, |entry| entry.balance)
+    }
+
+    /// The first check that failed, with the last one that agreed before it. A transaction was
+    /// dropped, duplicated or changed between the two.
+    pub fn first_divergence(&self) -> Option<(Option<&Check>, &Check)> {
+        let index = self.checks.iter().position(|check| !check.agrees())?;
+        let last_good = self.checks[..index]
+            .iter()
+            .rev()
+            .find(|check| check.agrees());
+        Some((last_good, &self.checks[index]))
+    }
+}
+
+/// Computes the running balance of `rows`, one account's transactions in statement order, from
+/// `opening` and compares it to the rows' reported balances and to `checkpoints`
+pub fn reconcile<'a, I>(
+    opening: Money,
+    rows: I,
+    checkpoints: &[Checkpoint],
+) -> Result<Reconciliation, MoneyError>
+where
+    I: IntoIterator<Item = &'a Data>,
+{
+    let mut checkpoints = checkpoints.to_vec();
+    checkpoints.sort_by_key(|checkpoint| checkpoint.date);
+    let mut checkpoints = checkpoints.into_iter().peekable();
+
+    let mut entries = Vec::new();
+    let mut checks = Vec::new();
+    let mut balance = opening;
+    for data in rows {
+        while let Some(checkpoint) = checkpoints.next_if(|checkpoint| checkpoint.date < data.date) {
+            checks.push(Check {
+                date: checkpoint.date,
+                source: CheckSource::Checkpoint,
+                expected: checkpoint.balance,
+                computed: balance,
+            });
+        }
+        balance = balance.checked_add(data.amount)?;
+        if let Some(reported) = data.balance {
+            checks.push(Check {
+                date: data.date,
+                source: CheckSource::Row(entries.len()),
+                expected: reported,
+                computed: balance,
+            });
+        }
+        entries.push(BalanceEntry {
+            date: data.date,
+            description: data.description.clone(),
+            amount: data.amount,
+            balance,
+            reported: data.balance,
+        });
+    }
+    for checkpoint in checkpoints {
+        checks.push(Check {
+            date: checkpoint.date,
+            source: CheckSource::Checkpoint,
+            expected: checkpoint.balance,
+            computed: balance,
+        });
+    }
+
+    Ok(Reconciliation {
+        opening,
+        entries,
+        checks,
+    })
+}
+
+impl fmt::Display for Reconciliation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let currency = self.opening.currency();
+        match self.first_divergence() {
+            None => write!(
+                f,
+                "closing balance {} {currency}, {} of {} checks agree",
+                self.closing(),
+                self.checks.len(),
+                self.checks.len()
+            ),
+            Some((last_good, check)) => {
+                let difference = check
+                    .difference()
+                    .map_or_else(|e| e.to_string(), |difference| difference.to_string());
+                write!(
+                    f,
+                    "first divergence on {}: expected {} {currency}, computed {} (difference {})",
+                    check.date, check.expected, check.computed, difference
+                )?;
+                match last_good {
+                    Some(last_good) => write!(f, ", last agreed on {}", last_good.date),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl Tabular for BalanceEntry {
+    const HEADERS: &'static [&'static str] = &[
+        "Date",
+        "Currency",
+        "Amount",
+        "Balance",
+        "Reported",
+        "Description",
+    ];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.date.to_string(),
+            self.amount.currency().to_string(),
+            self.amount.to_string(),
+            self.balance.to_string(),
+            self.reported
+                .map(|reported| reported.to_string())
+                .unwrap_or_default(),
+            self.description.clone(),
+        ]
+    }
+}
+
+#[cfg(test)]
+#[path = "./tests/test.rs"]
+mod test;
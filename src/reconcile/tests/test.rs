@@ -0,0 +1,67 @@
+use super::*;
+
+use crate::csv_parser::parse_csv;
+use crate::testing::{cad, date};
+
+const TEST_FILE_PATH: &str = "src/csv_parser/tests/test_statement.csv";
+
+#[test]
+fn test_running_balance_and_checkpoints() {
+    let rows = parse_csv(TEST_FILE_PATH).unwrap();
+    let checkpoints = [
+        Checkpoint::parse("2024-06-30=967.50", Currency::CAD).unwrap(),
+        Checkpoint::parse("2024-06-05=3393.56", Currency::CAD).unwrap(),
+    ];
+
+    let reconciliation = reconcile(cad("5000.00"), &rows, &checkpoints).unwrap();
+
+    assert_eq!(9, reconciliation.entries.len());
+    assert_eq!(cad("3625.53"), reconciliation.entries[0].balance);
+    assert_eq!(cad("967.50"), reconciliation.closing());
+    assert_eq!(
+        vec![date("2024-06-05"), date("2024-06-30")],
+        reconciliation
+            .checks
+            .iter()
+            .map(|check| check.date)
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(None, reconciliation.first_divergence());
+    assert_eq!(
+        "closing balance 967.50 CAD, 2 of 2 checks agree",
+        reconciliation.to_string()
+    );
+}
+
+#[test]
+fn test_first_divergence() {
+    let mut rows = parse_csv(TEST_FILE_PATH).unwrap();
+    rows[0].balance = Some(cad("3625.53"));
+    rows[1].balance = Some(cad("3393.56"));
+    // The row filter dropped the e-transfer of 2024-06-10
+    rows.remove(2);
+    rows[2].balance = Some(cad("3834.86"));
+    let checkpoints = [Checkpoint {
+        date: date("2024-06-30"),
+        balance: cad("967.50"),
+    }];
+
+    let reconciliation = reconcile(cad("5000.00"), &rows, &checkpoints).unwrap();
+
+    let (last_good, check) = reconciliation.first_divergence().unwrap();
+    assert_eq!(CheckSource::Row(1), last_good.unwrap().source);
+    assert_eq!(CheckSource::Row(2), check.source);
+    assert_eq!(cad("-80.00"), check.difference().unwrap());
+    assert_eq!(
+        "first divergence on 2024-06-11: expected 3834.86 CAD, computed 3914.86 \
+         (difference -80.00), last agreed on 2024-06-03",
+        reconciliation.to_string()
+    );
+}
+
+#[test]
+fn test_checkpoint_parse_errors() {
+    assert!(Checkpoint::parse("2024-06-30", Currency::CAD).is_err());
+    assert!(Checkpoint::parse("June=1.00", Currency::CAD).is_err());
+    assert!(Checkpoint::parse("2024-06-30=1.001", Currency::CAD).is_err());
+}
@@ -0,0 +1,399 @@
+use std::{cmp::Reverse, collections::BTreeMap};
+
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::category::TransactionCategory;
+use crate::csv_parser::Data;
+use crate::money::{Currency, Money, MoneyError};
+use crate::output::Tabular;
+use crate::report::YearMonth;
+
+/// How often a recurring transaction comes back
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Cadence {
+    Weekly,
+    Biweekly,
+    Monthly,
+    Annual,
+}
+
+impl Cadence {
+    pub const ALL: [Cadence; 4] = [
+        Cadence::Weekly,
+        Cadence::Biweekly,
+        Cadence::Monthly,
+        Cadence::Annual,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Cadence::Weekly => "weekly",
+            Cadence::Biweekly => "biweekly",
+            Cadence::Monthly => "monthly",
+            Cadence::Annual => "annual",
+        }
+    }
+
+    /// Average length of one period in days
+    fn days(&self) -> f64 {
+        match self {
+            Cadence::Weekly => 7.0,
+            Cadence::Biweekly => 14.0,
+            Cadence::Monthly => 30.44,
+            Cadence::Annual => 365.25,
+        }
+    }
+
+    /// How many days an occurrence may be early or late, e.g. when a payment due on a weekend is
+    /// taken on the next business day
+    fn slack(&self) -> f64 {
+        match self {
+            Cadence::Weekly => 1.0,
+            Cadence::Biweekly => 2.0,
+            Cadence::Monthly => 4.0,
+            Cadence::Annual => 10.0,
+        }
+    }
+
+    /// Occurrences needed before a series is reported
+    fn min_occurrences(&self) -> usize {
+        match self {
+            Cadence::Annual => 2,
+            _ => 3,
+        }
+    }
+
+    /// The number of periods `days` spans, `None` when it is not close to a whole number of them
+    fn periods(&self, days: i64) -> Option<i64> {
+        let periods = (days as f64 / self.days()).round();
+        let off = (days as f64 - periods * self.days()).abs();
+        (periods >= 1.0 && off <= self.slack()).then_some(periods as i64)
+    }
+
+    /// The date one period after `date`, keeping the day of the month where possible
+    pub fn next(&self, date: NaiveDate) -> NaiveDate {
+        self.nth(date, 1)
+    }
+
+    /// The date `n` periods after `anchor`
+    ///
+    /// Months and years are counted from the anchor rather than from the previous occurrence, so
+    /// a series due on the 31st falls on the last day of shorter months and on the 31st again
+    /// after them.
+    pub fn nth(&self, anchor: NaiveDate, n: u32) -> NaiveDate {
+        match self {
+            Cadence::Weekly => anchor + Duration::days(7 * i64::from(n)),
+            Cadence::Biweekly => anchor + Duration::days(14 * i64::from(n)),
+            Cadence::Monthly => {
+                let months = anchor.month0() + n;
+                let month = YearMonth {
+                    year: anchor.year() + (months / 12) as i32,
+                    month: months % 12 + 1,
+                };
+                let day = anchor.day().min(month.last_day().day());
+                NaiveDate::from_ymd_opt(month.year, month.month, day).expect("day was clamped")
+            }
+            Cadence::Annual => {
+                let year = anchor.year() + n as i32;
+                NaiveDate::from_ymd_opt(year, anchor.month(), anchor.day())
+                    .or_else(|| NaiveDate::from_ymd_opt(year, 2, 28))
+                    .expect("only February 29 is missing in some years")
+            }
+        }
+    }
+}
+
+/// One transaction of a recurring series
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Occurrence {
+    pub date: NaiveDate,
+    pub amount: Money,
+}
+
+/// An occurrence charged a different amount than the one before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PriceChange {
+    pub date: NaiveDate,
+    pub from: Money,
+    pub to: Money,
+}
+
+/// Transactions with the same description coming back at a regular interval
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RecurringSeries {
+    /// Description of the latest occurrence
+    pub description: String,
+    /// Category of the latest occurrence
+    pub category: TransactionCategory,
+    pub cadence: Cadence,
+    /// Oldest first
+    pub occurrences: Vec<Occurrence>,
+    /// Expected dates in the history without an occurrence
+    pub missed: Vec<NaiveDate>,
+    /// Whether the next occurrence is late as of the detection date
+    pub overdue: bool,
+    /// Oldest first
+    pub price_changes: Vec<PriceChange>,
+    pub next_due: NaiveDate,
+    /// The amount of the latest occurrence
+    pub next_amount: Money,
+}
+
+impl RecurringSeries {
+    pub fn last(&self) -> &Occurrence {
+        self.occurrences.last().expect("a series is never empty")
+    }
+
+    /// The occurrence the due dates are counted from with `Cadence::nth`, so that a series on the
+    /// 31st does not drift to the 28th after February
+    pub fn anchor(&self) -> NaiveDate {
+        let dates: Vec<NaiveDate> = self
+            .occurrences
+            .iter()
+            .map(|occurrence| occurrence.date)
+            .collect();
+        anchor(&dates)
+    }
+
+    /// Number of periods from `anchor` to the latest occurrence
+    pub fn elapsed_periods(&self) -> u32 {
+        elapsed_periods(self.cadence, self.anchor(), self.last().date)
+    }
+
+    /// The cost of the series over a year at its latest amount, negative for expenses
+    pub fn annual_amount(&self) -> Result<Money, MoneyError> {
+        let times = match self.cadence {
+            Cadence::Weekly => 52,
+            Cadence::Biweekly => 26,
+            Cadence::Monthly => 12,
+            Cadence::Annual => 1,
+        };
+        self.next_amount.checked_mul(times)
+    }
+}
+
+/// Finds recurring transactions, like subscriptions, mortgage payments or pay cheques, in a
+/// transaction history
+///
+/// Transactions are grouped by description, ignoring case, spacing and digits, and by sign and
+/// currency. Within a group, a transaction belongs to the same series as the previous one when
+/// their amounts differ by at most `amount_tolerance` percent, so prices that change gradually
+/// stay in one series while a larger change starts a new one. A series is reported when the
+/// intervals between its transactions all match one `Cadence`, allowing for missed occurrences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Detector {
+    /// Percentage of the previous amount, 20 by default
+    pub amount_tolerance: u32,
+}
+
+impl Default for Detector {
+    fn default() -> Detector {
+        Detector {
+            amount_tolerance: 20,
+        }
+    }
+}
+
+/// The part of a description identifying a recurring payee
+pub fn series_key(description: &str) -> String {
+    let mut key = String::new();
+    for word in description.split_whitespace() {
+        let word: String = word
+            .chars()
+            .map(|c| if c.is_ascii_digit() { '#' } else { c })
+            .collect();
+        let mut word = word.to_lowercase();
+        while word.contains("##") {
+            word = word.replace("##", "#");
+        }
+        if !key.is_empty() {
+            key.push(' ');
+        }
+        key.push_str(&word);
+    }
+    key
+}
+
+impl Detector {
+    fn within_tolerance(&self, amount: Money, previous: Money) -> bool {
+        let difference =
+            (i128::from(amount.minor_units()) - i128::from(previous.minor_units())).abs();
+        difference * 100
+            <= i128::from(self.amount_tolerance) * i128::from(previous.minor_units()).abs()
+    }
+
+    /// Every recurring series in `rows`, ordered by next due date. `as_of` is the day the history
+    /// ends on, used to tell whether a series is overdue.
+    pub fn detect(&self, rows: &[Data], as_of: NaiveDate) -> Vec<RecurringSeries> {
+        let mut groups: BTreeMap<(String, bool, Currency), Vec<&Data>> = BTreeMap::new();
+        for data in rows.iter().filter(|data| data.date <= as_of) {
+            let key = (
+                series_key(&data.description),
+                data.amount.is_negative(),
+                data.amount.currency(),
+            );
+            groups.entry(key).or_default().push(data);
+        }
+
+        let mut series = Vec::new();
+        for mut group in groups.into_values() {
+            group.sort_by_key(|data| data.date);
+            let mut clusters: Vec<Vec<&Data>> = Vec::new();
+            for data in group {
+                let cluster = clusters.iter_mut().find(|cluster| {
+                    let last = cluster.last().expect("clusters are never empty");
+                    self.within_tolerance(data.amount, last.amount)
+                });
+                match cluster {
+                    Some(cluster) => cluster.push(data),
+                    None => clusters.push(vec![data]),
+                }
+            }
+            series.extend(
+                clusters
+                    .iter()
+                    .filter_map(|cluster| analyze(cluster, as_of)),
+            );
+        }
+        series.sort_by(|a, b| {
+            a.next_due
+                .cmp(&b.next_due)
+                .then_with(|| a.description.cmp(&b.description))
+        });
+        series
+    }
+}
+
+/// The median number of days between consecutive transactions
+fn median_interval(intervals: &[i64]) -> i64 {
+    let mut sorted = intervals.to_vec();
+    sorted.sort_unstable();
+    sorted[sorted.len() / 2]
+}
+
+/// The first of `dates`, unless it falls on the last day of its month: a series seen first on
+/// November 30 may be due on the 31st, so the month end with the latest day is taken instead
+fn anchor(dates: &[NaiveDate]) -> NaiveDate {
+    let is_month_end = |date: &NaiveDate| YearMonth::of(*date).last_day() == *date;
+    if !is_month_end(&dates[0]) {
+        return dates[0];
+    }
+    dates
+        .iter()
+        .copied()
+        .filter(is_month_end)
+        .max_by_key(|date| (date.day(), Reverse(*date)))
+        .expect("the first date is a month end")
+}
+
+/// The number of whole periods from `first` to `last`, rounded so early and late payments count
+/// towards the period they were due in
+fn elapsed_periods(cadence: Cadence, first: NaiveDate, last: NaiveDate) -> u32 {
+    ((last - first).num_days() as f64 / cadence.days()).round() as u32
+}
+
+/// A series out of transactions sorted by date, when they recur at a regular interval
+///
+/// The cadence comes from the median interval. An interval that isn't close to a whole number of
+/// periods, like the ones around a late payment, is tolerated rather than breaking the series, it
+/// just can't tell which occurrences were missed.
+fn analyze(cluster: &[&Data], as_of: NaiveDate) -> Option<RecurringSeries> {
+    if cluster.len() < 2 {
+        return None;
+    }
+    let intervals: Vec<i64> = cluster
+        .windows(2)
+        .map(|pair| (pair[1].date - pair[0].date).num_days())
+        .collect();
+    let median = median_interval(&intervals);
+    let cadence = Cadence::ALL
+        .into_iter()
+        .find(|cadence| cadence.periods(median) == Some(1))?;
+    if cluster.len() < cadence.min_occurrences() {
+        return None;
+    }
+
+    let mut missed = Vec::new();
+    for (pair, interval) in cluster.windows(2).zip(&intervals) {
+        let Some(periods) = cadence.periods(*interval) else {
+            continue;
+        };
+        for n in 1..periods {
+            missed.push(cadence.nth(pair[0].date, n as u32));
+        }
+    }
+
+    let price_changes = cluster
+        .windows(2)
+        .filter(|pair| pair[0].amount != pair[1].amount)
+        .map(|pair| PriceChange {
+            date: pair[1].date,
+            from: pair[0].amount,
+            to: pair[1].amount,
+        })
+        .collect();
+
+    let last = cluster.last().expect("clusters are never empty");
+    let dates: Vec<NaiveDate> = cluster.iter().map(|data| data.date).collect();
+    let anchor = anchor(&dates);
+    let next_due = cadence.nth(anchor, elapsed_periods(cadence, anchor, last.date) + 1);
+    let overdue = (as_of - next_due).num_days() as f64 > cadence.slack();
+    Some(RecurringSeries {
+        description: last.description.clone(),
+        category: last.category.clone(),
+        cadence,
+        occurrences: cluster
+            .iter()
+            .map(|data| Occurrence {
+                date: data.date,
+                amount: data.amount,
+            })
+            .collect(),
+        missed,
+        overdue,
+        price_changes,
+        next_due,
+        next_amount: last.amount,
+    })
+}
+
+impl Tabular for RecurringSeries {
+    const HEADERS: &'static [&'static str] = &[
+        "Description",
+        "Category",
+        "Cadence",
+        "Count",
+        "Last",
+        "Next Due",
+        "Currency",
+        "Amount",
+        "Missed",
+        "Overdue",
+        "Price Change",
+    ];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.description.clone(),
+            self.category.to_string(),
+            self.cadence.as_str().to_string(),
+            self.occurrences.len().to_string(),
+            self.last().date.to_string(),
+            self.next_due.to_string(),
+            self.next_amount.currency().to_string(),
+            self.next_amount.to_string(),
+            self.missed.len().to_string(),
+            if self.overdue { "yes" } else { "" }.to_string(),
+            self.price_changes
+                .last()
+                .map(|change| format!("{} -> {} on {}", change.from, change.to, change.date))
+                .unwrap_or_default(),
+        ]
+    }
+}
+
+#[cfg(test)]
+#[path = "./tests/test.rs"]
+mod test;
@@ -0,0 +1,172 @@
+use super::*;
+use rstest::rstest;
+
+use crate::csv_parser::parse_csv;
+use crate::testing::{cad, date};
+
+const TEST_FILE_PATH: &str = "src/csv_parser/tests/test_statement.csv";
+
+fn data(day: &str, amount: &str, description: &str) -> Data {
+    Data::new(date(day), cad(amount), description)
+}
+
+#[rstest]
+#[case("[DS]BANK         MTG/HYP   ", "[ds]bank mtg/hyp")]
+#[case("[CW] TF 000123456789", "[cw] tf #")]
+#[case("NETFLIX.COM 866-579-7172", "netflix.com #-#-#")]
+fn test_series_key(#[case] description: &str, #[case] expected: &str) {
+    assert_eq!(expected, series_key(description));
+}
+
+#[rstest]
+#[case(Cadence::Monthly, "2024-01-31", "2024-02-29")]
+#[case(Cadence::Monthly, "2024-12-15", "2025-01-15")]
+#[case(Cadence::Annual, "2024-02-29", "2025-02-28")]
+#[case(Cadence::Biweekly, "2024-06-28", "2024-07-12")]
+fn test_cadence_next(#[case] cadence: Cadence, #[case] from: &str, #[case] expected: &str) {
+    assert_eq!(date(expected), cadence.next(date(from)));
+}
+
+#[rstest]
+#[case(Cadence::Monthly, "2024-01-31", 1, "2024-02-29")]
+#[case(Cadence::Monthly, "2024-01-31", 2, "2024-03-31")]
+#[case(Cadence::Monthly, "2024-10-31", 4, "2025-02-28")]
+#[case(Cadence::Annual, "2024-02-29", 4, "2028-02-29")]
+#[case(Cadence::Weekly, "2024-06-28", 3, "2024-07-19")]
+fn test_cadence_nth(
+    #[case] cadence: Cadence,
+    #[case] anchor: &str,
+    #[case] n: u32,
+    #[case] expected: &str,
+) {
+    assert_eq!(date(expected), cadence.nth(date(anchor), n));
+}
+
+#[test]
+fn test_detects_monthly_series_with_price_change() {
+    let rows = vec![
+        data("2024-03-28", "-27.95", "[SC]PREMIUM PLAN"),
+        data("2024-04-29", "-27.95", "[SC]PREMIUM PLAN"),
+        data("2024-05-28", "-30.95", "[SC]PREMIUM PLAN"),
+        data("2024-06-28", "-30.95", "[SC]PREMIUM  PLAN"),
+        data("2024-06-28", "30.95", "[SC]FULL PLAN FEE REBATE"),
+    ];
+
+    let series = Detector::default().detect(&rows, date("2024-07-14"));
+
+    assert_eq!(1, series.len());
+    let plan = &series[0];
+    assert_eq!(Cadence::Monthly, plan.cadence);
+    assert_eq!(4, plan.occurrences.len());
+    assert_eq!(date("2024-07-28"), plan.next_due);
+    assert_eq!(cad("-30.95"), plan.next_amount);
+    assert!(plan.missed.is_empty());
+    assert!(!plan.overdue);
+    assert_eq!(
+        vec![PriceChange {
+            date: date("2024-05-28"),
+            from: cad("-27.95"),
+            to: cad("-30.95"),
+        }],
+        plan.price_changes
+    );
+    assert_eq!(cad("-371.40"), plan.annual_amount().unwrap());
+}
+
+#[test]
+fn test_flags_missed_and_overdue_occurrences() {
+    let rows = vec![
+        data("2024-01-03", "-1374.47", "[DS]BANK MTG/HYP"),
+        data("2024-02-05", "-1374.47", "[DS]BANK MTG/HYP"),
+        data("2024-04-03", "-1374.47", "[DS]BANK MTG/HYP"),
+        data("2024-05-03", "-1374.47", "[DS]BANK MTG/HYP"),
+    ];
+
+    let series = Detector::default().detect(&rows, date("2024-06-20"));
+
+    assert_eq!(1, series.len());
+    assert_eq!(vec![date("2024-03-05")], series[0].missed);
+    assert_eq!(date("2024-06-03"), series[0].next_due);
+    assert!(series[0].overdue);
+}
+
+#[test]
+fn test_late_payment_keeps_the_series() {
+    let rows = vec![
+        data("2024-01-03", "-1374.47", "[DS]BANK MTG/HYP"),
+        data("2024-02-14", "-1374.47", "[DS]BANK MTG/HYP"),
+        data("2024-03-03", "-1374.47", "[DS]BANK MTG/HYP"),
+        data("2024-04-03", "-1374.47", "[DS]BANK MTG/HYP"),
+        data("2024-05-03", "-1374.47", "[DS]BANK MTG/HYP"),
+    ];
+
+    let series = Detector::default().detect(&rows, date("2024-05-20"));
+
+    assert_eq!(1, series.len());
+    assert_eq!(Cadence::Monthly, series[0].cadence);
+    assert_eq!(5, series[0].occurrences.len());
+    assert!(series[0].missed.is_empty());
+    assert_eq!(date("2024-06-03"), series[0].next_due);
+}
+
+#[test]
+fn test_month_end_series_stays_on_month_end() {
+    let rows = vec![
+        data("2023-11-30", "-55.00", "INTERNET"),
+        data("2023-12-31", "-55.00", "INTERNET"),
+        data("2024-01-31", "-55.00", "INTERNET"),
+        data("2024-02-29", "-55.00", "INTERNET"),
+    ];
+
+    let series = Detector::default().detect(&rows, date("2024-02-29"));
+
+    assert_eq!(1, series.len());
+    assert_eq!(date("2023-12-31"), series[0].anchor());
+    assert_eq!(2, series[0].elapsed_periods());
+    assert_eq!(date("2024-03-31"), series[0].next_due);
+}
+
+#[test]
+fn test_amount_tolerance_splits_series() {
+    // Two transfers to the same payee every two weeks, for unrelated amounts
+    let mut rows = Vec::new();
+    for day in ["2024-05-03", "2024-05-17", "2024-05-31", "2024-06-14"] {
+        rows.push(data(day, "-80.00", "[CW]INTERAC ETRNSFR SENT BROTHER"));
+        rows.push(data(day, "-500.00", "[CW]INTERAC ETRNSFR SENT BROTHER"));
+    }
+
+    let series = Detector::default().detect(&rows, date("2024-06-14"));
+
+    assert_eq!(2, series.len());
+    assert!(series
+        .iter()
+        .all(|series| series.cadence == Cadence::Biweekly && series.occurrences.len() == 4));
+    assert!(Detector {
+        amount_tolerance: 1000
+    }
+    .detect(&rows, date("2024-06-14"))
+    .is_empty());
+}
+
+#[test]
+fn test_irregular_transactions_are_not_recurring() {
+    let rows = vec![
+        data("2024-06-01", "-12.00", "COFFEE"),
+        data("2024-06-04", "-12.00", "COFFEE"),
+        data("2024-06-20", "-12.00", "COFFEE"),
+        data("2024-06-21", "-12.00", "COFFEE"),
+    ];
+
+    assert!(Detector::default()
+        .detect(&rows, date("2024-06-30"))
+        .is_empty());
+}
+
+#[test]
+fn test_single_statement_has_no_series() {
+    let rows = parse_csv(TEST_FILE_PATH).unwrap();
+
+    assert!(Detector::default()
+        .detect(&rows, date("2024-07-14"))
+        .is_empty());
+}
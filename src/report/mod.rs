@@ -0,0 +1,613 @@
+use std::{collections::BTreeMap, fmt};
+
+use chrono::{Datelike, NaiveDate};
+use serde::{Serialize, Serializer};
+
+use crate::category::{CategoryTree, TransactionCategory};
+use crate::csv_parser::{Data, TransactionChannel};
+use crate::money::{Currency, Money, MoneyError};
+use crate::output::Tabular;
+
+/// A calendar month, shown as `2024-06`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct YearMonth {
+    pub year: i32,
+    /// 1 to 12
+    pub month: u32,
+}
+
+impl YearMonth {
+    pub fn of(date: NaiveDate) -> YearMonth {
+        YearMonth {
+            year: date.year(),
+            month: date.month(),
+        }
+    }
+
+    pub fn first_day(&self) -> NaiveDate {
+        NaiveDate::from_ymd_opt(self.year, self.month, 1).expect("months are always valid")
+    }
+
+    pub fn last_day(&self) -> NaiveDate {
+        self.next()
+            .first_day()
+            .pred_opt()
+            .expect("months are always valid")
+    }
+
+    pub fn next(&self) -> YearMonth {
+        if self.month == 12 {
+            YearMonth {
+                year: self.year + 1,
+                month: 1,
+            }
+        } else {
+            YearMonth {
+                year: self.year,
+                month: self.month + 1,
+            }
+        }
+    }
+}
+
+impl fmt::Display for YearMonth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}", self.year, self.month)
+    }
+}
+
+impl Serialize for YearMonth {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Aggregates of a group of transactions, all in the report's currency
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Totals {
+    pub count: usize,
+    /// Sum of the money coming in
+    pub credits: Money,
+    /// Sum of the money going out, negative
+    pub debits: Money,
+    pub net: Money,
+    /// Mean signed amount, rounded to the minor unit. Zero when there are no transactions.
+    pub average: Money,
+    /// The transaction with the largest absolute amount
+    pub largest: Option<Money>,
+}
+
+impl Totals {
+    pub fn empty(currency: Currency) -> Totals {
+        Totals {
+            count: 0,
+            credits: Money::zero(currency),
+            debits: Money::zero(currency),
+            net: Money::zero(currency),
+            average: Money::zero(currency),
+            largest: None,
+        }
+    }
+
+    pub fn add(&mut self, amount: Money) -> Result<(), MoneyError> {
+        if amount.is_negative() {
+            self.debits = self.debits.checked_add(amount)?;
+        } else {
+            self.credits = self.credits.checked_add(amount)?;
+        }
+        self.net = self.net.checked_add(amount)?;
+        self.count += 1;
+        self.average = self.net.checked_div(self.count as i64)?;
+        let larger = match self.largest {
+            Some(largest) => amount.checked_abs()? > largest.checked_abs()?,
+            None => true,
+        };
+        if larger {
+            self.largest = Some(amount);
+        }
+        Ok(())
+    }
+}
+
+/// How a month compares to the one before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Change {
+    pub credits: Money,
+    pub debits: Money,
+    pub net: Money,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CategorySummary {
+    pub category: TransactionCategory,
+    /// Display path in the category tree, e.g. `Housing > Mortgage`
+    pub name: String,
+    /// Transactions filed directly under the category
+    pub totals: Totals,
+    /// Transactions filed under the category or any of its subcategories
+    pub rollup: Totals,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MonthSummary {
+    pub month: YearMonth,
+    pub totals: Totals,
+    /// Difference with the previous calendar month, `None` for the first month of the report
+    pub change: Option<Change>,
+    pub categories: Vec<CategorySummary>,
+}
+
+/// Whether money only moved between two of the user's accounts
+fn is_transfer(data: &Data, tree: &CategoryTree) -> bool {
+    let transfers = TransactionCategory::ACCOUNT_TRANSFERS;
+    data.category == transfers || tree.is_within(&data.category, &transfers)
+}
+
+/// Per-month and per-category summaries of a set of transactions
+///
+/// Month and period totals are income and expenses: transactions filed under
+/// `TransactionCategory::ACCOUNT_TRANSFERS` are left out of them, but still get a category
+/// summary.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MonthlyReport {
+    pub currency: Currency,
+    /// Every month from the first to the last transaction, including months without any
+    pub months: Vec<MonthSummary>,
+    /// Over the whole period, without transfers
+    pub totals: Totals,
+    /// Over the whole period
+    pub categories: Vec<CategorySummary>,
+}
+
+/// Summarizes `rows` by month and by category
+///
+/// Every row must be in `currency`. Category summaries follow `tree`: a parent category's
+/// `rollup` includes its subcategories.
+pub fn monthly_report<'a, I>(
+    rows: I,
+    currency: Currency,
+    tree: &CategoryTree,
+) -> Result<MonthlyReport, MoneyError>
+where
+    I: IntoIterator<Item = &'a Data>,
+{
+    let mut by_month: BTreeMap<YearMonth, Vec<&Data>> = BTreeMap::new();
+    for data in rows {
+        if data.amount.currency() != currency {
+            return Err(MoneyError::CurrencyMismatch {
+                left: currency,
+                right: data.amount.currency(),
+            });
+        }
+        by_month
+            .entry(YearMonth::of(data.date))
+            .or_default()
+            .push(data);
+    }
+
+    let mut months: Vec<MonthSummary> = Vec::new();
+    let mut totals = Totals::empty(currency);
+    if let (Some(first), Some(last)) = (
+        by_month.keys().next().copied(),
+        by_month.keys().next_back().copied(),
+    ) {
+        let mut month = first;
+        while month <= last {
+            let rows = by_month.get(&month).map(Vec::as_slice).unwrap_or_default();
+            let mut month_totals = Totals::empty(currency);
+            for data in rows.iter().filter(|data| !is_transfer(data, tree)) {
+                month_totals.add(data.amount)?;
+                totals.add(data.amount)?;
+            }
+            let change = match months.last() {
+                Some(previous) => Some(Change {
+                    credits: month_totals.credits.checked_sub(previous.totals.credits)?,
+                    debits: month_totals.debits.checked_sub(previous.totals.debits)?,
+                    net: month_totals.net.checked_sub(previous.totals.net)?,
+                }),
+                None => None,
+            };
+            months.push(MonthSummary {
+                month,
+                totals: month_totals,
+                change,
+                categories: category_summaries(rows.iter().copied(), currency, tree)?,
+            });
+            month = month.next();
+        }
+    }
+
+    let categories = category_summaries(by_month.values().flatten().copied(), currency, tree)?;
+    Ok(MonthlyReport {
+        currency,
+        months,
+        totals,
+        categories,
+    })
+}
+
+/// One summary per category with transactions in its subtree, ordered by category id
+pub fn category_summaries<'a, I>(
+    rows: I,
+    currency: Currency,
+    tree: &CategoryTree,
+) -> Result<Vec<CategorySummary>, MoneyError>
+where
+    I: IntoIterator<Item = &'a Data>,
+{
+    let mut own: BTreeMap<TransactionCategory, Totals> = BTreeMap::new();
+    let mut rollup: BTreeMap<TransactionCategory, Totals> = BTreeMap::new();
+    for data in rows {
+        own.entry(data.category.clone())
+            .or_insert_with(|| Totals::empty(currency))
+            .add(data.amount)?;
+        let mut ancestors: Vec<&TransactionCategory> = tree
+            .ancestors(&data.category)
+            .map(|category| &category.id)
+            .collect();
+        if ancestors.is_empty() {
+            ancestors.push(&data.category);
+        }
+        for category in ancestors {
+            rollup
+                .entry(category.clone())
+                .or_insert_with(|| Totals::empty(currency))
+                .add(data.amount)?;
+        }
+    }
+
+    Ok(rollup
+        .into_iter()
+        .map(|(category, rollup)| CategorySummary {
+            name: tree.path(&category),
+            totals: own
+                .get(&category)
+                .copied()
+                .unwrap_or_else(|| Totals::empty(currency)),
+            category,
+            rollup,
+        })
+        .collect())
+}
+
+/// Totals of one account in one currency
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AccountTotals {
+    pub account: String,
+    pub currency: Currency,
+    #[serde(flatten)]
+    pub totals: Totals,
+}
+
+/// Totals per account and currency of `(account, transaction)` pairs, ordered by account.
+/// Transfers are left out, like in `monthly_report`.
+pub fn account_totals<'a, I>(rows: I, tree: &CategoryTree) -> Result<Vec<AccountTotals>, MoneyError>
+where
+    I: IntoIterator<Item = (&'a str, &'a Data)>,
+{
+    let mut totals: BTreeMap<(&str, Currency), Totals> = BTreeMap::new();
+    for (account, data) in rows
+        .into_iter()
+        .filter(|(_, data)| !is_transfer(data, tree))
+    {
+        let currency = data.amount.currency();
+        totals
+            .entry((account, currency))
+            .or_insert_with(|| Totals::empty(currency))
+            .add(data.amount)?;
+    }
+    Ok(totals
+        .into_iter()
+        .map(|((account, currency), totals)| AccountTotals {
+            account: account.to_string(),
+            currency,
+            totals,
+        })
+        .collect())
+}
+
+/// Totals of one payee in one currency
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PayeeTotals {
+    pub payee: String,
+    pub currency: Currency,
+    #[serde(flatten)]
+    pub totals: Totals,
+}
+
+/// Totals per payee and currency of `(payee, transaction)` pairs, ordered by currency, then by
+/// debits, largest first, then by payee. Transfers are left out.
+pub fn payee_totals<'a, I>(rows: I, tree: &CategoryTree) -> Result<Vec<PayeeTotals>, MoneyError>
+where
+    I: IntoIterator<Item = (String, &'a Data)>,
+{
+    let mut totals: BTreeMap<(String, Currency), Totals> = BTreeMap::new();
+    for (payee, data) in rows
+        .into_iter()
+        .filter(|(_, data)| !is_transfer(data, tree))
+    {
+        let currency = data.amount.currency();
+        totals
+            .entry((payee, currency))
+            .or_insert_with(|| Totals::empty(currency))
+            .add(data.amount)?;
+    }
+    let mut rows: Vec<PayeeTotals> = totals
+        .into_iter()
+        .map(|((payee, currency), totals)| PayeeTotals {
+            payee,
+            currency,
+            totals,
+        })
+        .collect();
+    rows.sort_by_key(|row| (row.currency, row.totals.debits.minor_units()));
+    Ok(rows)
+}
+
+/// Totals of one channel in one currency
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChannelTotals {
+    /// `None` for transactions without a known channel
+    pub channel: Option<TransactionChannel>,
+    pub currency: Currency,
+    #[serde(flatten)]
+    pub totals: Totals,
+}
+
+/// Totals per channel and currency, ordered by channel with transactions without one last.
+/// Transfers are left out.
+pub fn channel_totals<'a, I>(rows: I, tree: &CategoryTree) -> Result<Vec<ChannelTotals>, MoneyError>
+where
+    I: IntoIterator<Item = &'a Data>,
+{
+    let mut totals: BTreeMap<(bool, Option<TransactionChannel>, Currency), Totals> =
+        BTreeMap::new();
+    for data in rows.into_iter().filter(|data| !is_transfer(data, tree)) {
+        let currency = data.amount.currency();
+        totals
+            .entry((data.channel.is_none(), data.channel, currency))
+            .or_insert_with(|| Totals::empty(currency))
+            .add(data.amount)?;
+    }
+    Ok(totals
+        .into_iter()
+        .map(|((_, channel, currency), totals)| ChannelTotals {
+            channel,
+            currency,
+            totals,
+        })
+        .collect())
+}
+
+/// Totals of one tag in one currency
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TagTotals {
+    pub tag: String,
+    pub currency: Currency,
+    #[serde(flatten)]
+    pub totals: Totals,
+}
+
+/// Totals per tag and currency, ordered by tag. A transaction with several tags counts towards
+/// each of them, untagged transactions and transfers are left out.
+pub fn tag_totals<'a, I>(rows: I, tree: &CategoryTree) -> Result<Vec<TagTotals>, MoneyError>
+where
+    I: IntoIterator<Item = &'a Data>,
+{
+    let mut totals: BTreeMap<(&str, Currency), Totals> = BTreeMap::new();
+    for data in rows.into_iter().filter(|data| !is_transfer(data, tree)) {
+        let currency = data.amount.currency();
+        for tag in &data.tags {
+            totals
+                .entry((tag, currency))
+                .or_insert_with(|| Totals::empty(currency))
+                .add(data.amount)?;
+        }
+    }
+    Ok(totals
+        .into_iter()
+        .map(|((tag, currency), totals)| TagTotals {
+            tag: tag.to_string(),
+            currency,
+            totals,
+        })
+        .collect())
+}
+
+fn optional(amount: Option<Money>) -> String {
+    amount.map(|amount| amount.to_string()).unwrap_or_default()
+}
+
+/// A `MonthSummary` flattened for tables and CSV
+#[derive(Debug, Clone, Serialize)]
+pub struct MonthRow {
+    pub month: YearMonth,
+    pub currency: Currency,
+    #[serde(flatten)]
+    pub totals: Totals,
+    pub net_change: Option<Money>,
+}
+
+impl MonthlyReport {
+    pub fn month_rows(&self) -> Vec<MonthRow> {
+        self.months
+            .iter()
+            .map(|summary| MonthRow {
+                month: summary.month,
+                currency: self.currency,
+                totals: summary.totals,
+                net_change: summary.change.map(|change| change.net),
+            })
+            .collect()
+    }
+
+    /// Category rows over the whole period
+    pub fn category_rows(&self) -> Vec<CategoryRow> {
+        self.categories
+            .iter()
+            .map(|summary| CategoryRow {
+                month: None,
+                currency: self.currency,
+                summary: summary.clone(),
+            })
+            .collect()
+    }
+
+    /// Category rows for every month
+    pub fn month_category_rows(&self) -> Vec<CategoryRow> {
+        self.months
+            .iter()
+            .flat_map(|month| {
+                month.categories.iter().map(|summary| CategoryRow {
+                    month: Some(month.month),
+                    currency: self.currency,
+                    summary: summary.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+impl Tabular for MonthRow {
+    const HEADERS: &'static [&'static str] = &[
+        "Month",
+        "Currency",
+        "Count",
+        "Credits",
+        "Debits",
+        "Net",
+        "Average",
+        "Largest",
+        "Net Change",
+    ];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.month.to_string(),
+            self.currency.to_string(),
+            self.totals.count.to_string(),
+            self.totals.credits.to_string(),
+            self.totals.debits.to_string(),
+            self.totals.net.to_string(),
+            self.totals.average.to_string(),
+            optional(self.totals.largest),
+            optional(self.net_change),
+        ]
+    }
+}
+
+impl Tabular for AccountTotals {
+    const HEADERS: &'static [&'static str] = &[
+        "Account", "Currency", "Count", "Credits", "Debits", "Net", "Average", "Largest",
+    ];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.account.clone(),
+            self.currency.to_string(),
+            self.totals.count.to_string(),
+            self.totals.credits.to_string(),
+            self.totals.debits.to_string(),
+            self.totals.net.to_string(),
+            self.totals.average.to_string(),
+            optional(self.totals.largest),
+        ]
+    }
+}
+
+impl Tabular for PayeeTotals {
+    const HEADERS: &'static [&'static str] = &[
+        "Payee", "Currency", "Count", "Credits", "Debits", "Net", "Average", "Largest",
+    ];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.payee.clone(),
+            self.currency.to_string(),
+            self.totals.count.to_string(),
+            self.totals.credits.to_string(),
+            self.totals.debits.to_string(),
+            self.totals.net.to_string(),
+            self.totals.average.to_string(),
+            optional(self.totals.largest),
+        ]
+    }
+}
+
+impl Tabular for ChannelTotals {
+    const HEADERS: &'static [&'static str] = &[
+        "Channel", "Currency", "Count", "Credits", "Debits", "Net", "Average", "Largest",
+    ];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.channel
+                .map(|channel| channel.to_string())
+                .unwrap_or_default(),
+            self.currency.to_string(),
+            self.totals.count.to_string(),
+            self.totals.credits.to_string(),
+            self.totals.debits.to_string(),
+            self.totals.net.to_string(),
+            self.totals.average.to_string(),
+            optional(self.totals.largest),
+        ]
+    }
+}
+
+impl Tabular for TagTotals {
+    const HEADERS: &'static [&'static str] = &[
+        "Tag", "Currency", "Count", "Credits", "Debits", "Net", "Average", "Largest",
+    ];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.tag.clone(),
+            self.currency.to_string(),
+            self.totals.count.to_string(),
+            self.totals.credits.to_string(),
+            self.totals.debits.to_string(),
+            self.totals.net.to_string(),
+            self.totals.average.to_string(),
+            optional(self.totals.largest),
+        ]
+    }
+}
+
+/// A `CategorySummary` flattened for tables and CSV. Amounts include subcategories.
+#[derive(Debug, Clone, Serialize)]
+pub struct CategoryRow {
+    pub month: Option<YearMonth>,
+    pub currency: Currency,
+    #[serde(flatten)]
+    pub summary: CategorySummary,
+}
+
+impl Tabular for CategoryRow {
+    const HEADERS: &'static [&'static str] = &[
+        "Month", "Category", "Name", "Currency", "Count", "Credits", "Debits", "Net", "Average",
+        "Largest",
+    ];
+
+    fn cells(&self) -> Vec<String> {
+        let rollup = &self.summary.rollup;
+        vec![
+            self.month
+                .map(|month| month.to_string())
+                .unwrap_or_default(),
+            self.summary.category.to_string(),
+            self.summary.name.clone(),
+            self.currency.to_string(),
+            rollup.count.to_string(),
+            rollup.credits.to_string(),
+            rollup.debits.to_string(),
+            rollup.net.to_string(),
+            rollup.average.to_string(),
+            optional(rollup.largest),
+        ]
+    }
+}
+
+#[cfg(test)]
+#[path = "./tests/test.rs"]
+mod test;
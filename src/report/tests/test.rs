@@ -0,0 +1,330 @@
+use super::*;
+
+use crate::testing::cad;
+
+const CATEGORIES_TOML: &str = r#"
+[[category]]
+id = "housing"
+name = "Housing"
+
+[[category]]
+id = "housing.mortgage"
+name = "Mortgage"
+parent = "housing"
+"#;
+
+fn data(date: &str, amount: &str, category: &str) -> Data {
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+    Data {
+        category: TransactionCategory::new(category),
+        ..Data::new(date, cad(amount), "TEST")
+    }
+}
+
+/// A confirmed transfer, filed by the matcher
+fn transfer() -> Data {
+    Data {
+        channel: Some(TransactionChannel::WebBanking),
+        tags: vec!["house".to_string()],
+        ..data("2024-06-24", "-1500.00", "account_transfers")
+    }
+}
+
+fn rows() -> Vec<Data> {
+    vec![
+        data("2024-04-03", "-1374.47", "housing.mortgage"),
+        data("2024-04-15", "2500.00", "other"),
+        data("2024-04-20", "-100.00", "housing"),
+        data("2024-06-03", "-1374.47", "housing.mortgage"),
+        data("2024-06-28", "-30.95", "bills"),
+    ]
+}
+
+#[test]
+fn test_year_month() {
+    let month = YearMonth::of(NaiveDate::from_ymd_opt(2024, 12, 17).unwrap());
+
+    assert_eq!("2024-12", month.to_string());
+    assert_eq!("2025-01", month.next().to_string());
+    assert_eq!(
+        NaiveDate::from_ymd_opt(2024, 12, 31).unwrap(),
+        month.last_day()
+    );
+    assert_eq!(
+        NaiveDate::from_ymd_opt(2024, 2, 29).unwrap(),
+        YearMonth {
+            year: 2024,
+            month: 2
+        }
+        .last_day()
+    );
+}
+
+#[test]
+fn test_totals() {
+    let mut totals = Totals::empty(Currency::CAD);
+    for amount in ["-10.00", "25.00", "-20.00"] {
+        totals.add(cad(amount)).unwrap();
+    }
+
+    assert_eq!(3, totals.count);
+    assert_eq!(cad("25.00"), totals.credits);
+    assert_eq!(cad("-30.00"), totals.debits);
+    assert_eq!(cad("-5.00"), totals.net);
+    assert_eq!(cad("-1.67"), totals.average);
+    assert_eq!(Some(cad("25.00")), totals.largest);
+}
+
+#[test]
+fn test_monthly_report_fills_gaps_and_compares_months() {
+    let tree = CategoryTree::from_toml(CATEGORIES_TOML).unwrap();
+    let report = monthly_report(&rows(), Currency::CAD, &tree).unwrap();
+
+    let months: Vec<String> = report
+        .months
+        .iter()
+        .map(|summary| summary.month.to_string())
+        .collect();
+    assert_eq!(vec!["2024-04", "2024-05", "2024-06"], months);
+
+    let april = &report.months[0];
+    assert_eq!(cad("1025.53"), april.totals.net);
+    assert_eq!(None, april.change);
+
+    let may = &report.months[1];
+    assert_eq!(0, may.totals.count);
+    assert!(may.categories.is_empty());
+    assert_eq!(
+        Some(Change {
+            credits: cad("-2500.00"),
+            debits: cad("1474.47"),
+            net: cad("-1025.53"),
+        }),
+        may.change
+    );
+
+    let june = &report.months[2];
+    assert_eq!(cad("-1405.42"), june.change.unwrap().net);
+    assert_eq!(5, report.totals.count);
+    assert_eq!(cad("-379.89"), report.totals.net);
+}
+
+#[test]
+fn test_category_summaries_roll_up() {
+    let tree = CategoryTree::from_toml(CATEGORIES_TOML).unwrap();
+    let report = monthly_report(&rows(), Currency::CAD, &tree).unwrap();
+
+    let housing = report
+        .categories
+        .iter()
+        .find(|summary| summary.category.id() == "housing")
+        .unwrap();
+    assert_eq!("Housing", housing.name);
+    assert_eq!(1, housing.totals.count);
+    assert_eq!(cad("-100.00"), housing.totals.net);
+    assert_eq!(3, housing.rollup.count);
+    assert_eq!(cad("-2848.94"), housing.rollup.net);
+    assert_eq!(Some(cad("-1374.47")), housing.rollup.largest);
+
+    let mortgage = report
+        .categories
+        .iter()
+        .find(|summary| summary.category.id() == "housing.mortgage")
+        .unwrap();
+    assert_eq!("Housing > Mortgage", mortgage.name);
+    assert_eq!(mortgage.totals, mortgage.rollup);
+
+    let april: Vec<&str> = report.months[0]
+        .categories
+        .iter()
+        .map(|summary| summary.category.id())
+        .collect();
+    assert_eq!(vec!["housing", "housing.mortgage", "other"], april);
+}
+
+#[test]
+fn test_monthly_report_rejects_other_currencies() {
+    let mut rows = rows();
+    rows[0].amount = Money::new(-100, Currency::USD);
+
+    assert_eq!(
+        Err(MoneyError::CurrencyMismatch {
+            left: Currency::CAD,
+            right: Currency::USD
+        }),
+        monthly_report(&rows, Currency::CAD, &CategoryTree::defaults())
+    );
+}
+
+#[test]
+fn test_month_rows() {
+    let report = monthly_report(&rows(), Currency::CAD, &CategoryTree::defaults()).unwrap();
+    let rows = report.month_rows();
+
+    assert_eq!(
+        vec![
+            "2024-06", "CAD", "2", "0.00", "-1405.42", "-1405.42", "-702.71", "-1374.47",
+            "-1405.42"
+        ],
+        rows[2].cells()
+    );
+    assert_eq!("", rows[0].cells()[8]);
+}
+
+#[test]
+fn test_account_totals() {
+    let rows = rows();
+    let pairs = rows
+        .iter()
+        .enumerate()
+        .map(|(index, data)| (if index < 2 { "visa" } else { "chequing" }, data));
+
+    let totals = account_totals(pairs, &CategoryTree::defaults()).unwrap();
+
+    assert_eq!(2, totals.len());
+    assert_eq!("chequing", totals[0].account);
+    assert_eq!(3, totals[0].totals.count);
+    assert_eq!(cad("-1505.42"), totals[0].totals.net);
+    assert_eq!("visa", totals[1].account);
+    assert_eq!(cad("1125.53"), totals[1].totals.net);
+}
+
+#[test]
+fn test_payee_totals() {
+    let rows = rows();
+    let pairs = rows.iter().map(|data| {
+        let payee = if data.category == TransactionCategory::new("housing.mortgage") {
+            "Mortgage"
+        } else {
+            "Other"
+        };
+        (payee.to_string(), data)
+    });
+
+    let totals = payee_totals(pairs, &CategoryTree::defaults()).unwrap();
+
+    assert_eq!(2, totals.len());
+    assert_eq!("Mortgage", totals[0].payee);
+    assert_eq!(cad("-2748.94"), totals[0].totals.debits);
+    assert_eq!("Other", totals[1].payee);
+    assert_eq!(3, totals[1].totals.count);
+    assert_eq!(cad("2369.05"), totals[1].totals.net);
+}
+
+#[test]
+fn test_channel_totals() {
+    let mut rows = rows();
+    rows[1].channel = Some(TransactionChannel::Deposit);
+    rows[4].channel = Some(TransactionChannel::ServiceCharge);
+
+    let totals = channel_totals(&rows, &CategoryTree::defaults()).unwrap();
+
+    let channels: Vec<(Option<TransactionChannel>, usize)> = totals
+        .iter()
+        .map(|row| (row.channel, row.totals.count))
+        .collect();
+    assert_eq!(
+        vec![
+            (Some(TransactionChannel::Deposit), 1),
+            (Some(TransactionChannel::ServiceCharge), 1),
+            (None, 3)
+        ],
+        channels
+    );
+    assert_eq!(cad("-30.95"), totals[1].totals.net);
+}
+
+#[test]
+fn test_tag_totals() {
+    let mut rows = rows();
+    rows[0].tags = vec!["house".to_string()];
+    rows[2].tags = vec!["house".to_string(), "renovation".to_string()];
+
+    let totals = tag_totals(&rows, &CategoryTree::defaults()).unwrap();
+
+    let tags: Vec<(&str, usize, Money)> = totals
+        .iter()
+        .map(|row| (row.tag.as_str(), row.totals.count, row.totals.net))
+        .collect();
+    assert_eq!(
+        vec![
+            ("house", 2, cad("-1474.47")),
+            ("renovation", 1, cad("-100.00"))
+        ],
+        tags
+    );
+}
+
+#[test]
+fn test_transfers_are_left_out_of_totals() {
+    let mut rows = rows();
+    rows.push(data("2024-06-24", "-1500.00", "account_transfers"));
+    let report = monthly_report(&rows, Currency::CAD, &CategoryTree::defaults()).unwrap();
+
+    let june = &report.months[2];
+    assert_eq!(2, june.totals.count);
+    assert_eq!(cad("-1405.42"), june.totals.net);
+    assert_eq!(cad("-379.89"), report.totals.net);
+    let transfers = report
+        .categories
+        .iter()
+        .find(|summary| summary.category == TransactionCategory::ACCOUNT_TRANSFERS)
+        .unwrap();
+    assert_eq!(cad("-1500.00"), transfers.rollup.net);
+}
+
+#[test]
+fn test_transfers_are_left_out_of_account_totals() {
+    let rows = [
+        transfer(),
+        data("2024-06-03", "-1374.47", "housing.mortgage"),
+    ];
+
+    let totals = account_totals(
+        rows.iter().map(|data| ("chequing", data)),
+        &CategoryTree::defaults(),
+    )
+    .unwrap();
+
+    assert_eq!(1, totals[0].totals.count);
+    assert_eq!(cad("-1374.47"), totals[0].totals.net);
+}
+
+#[test]
+fn test_transfers_are_left_out_of_payee_totals() {
+    let rows = [transfer()];
+
+    let totals = payee_totals(
+        rows.iter().map(|data| (String::from("Savings"), data)),
+        &CategoryTree::defaults(),
+    )
+    .unwrap();
+
+    assert!(totals.is_empty());
+}
+
+#[test]
+fn test_transfers_are_left_out_of_channel_totals() {
+    let mut rows = rows();
+    rows[4].channel = Some(TransactionChannel::WebBanking);
+    rows.push(transfer());
+
+    let totals = channel_totals(&rows, &CategoryTree::defaults()).unwrap();
+
+    assert_eq!(Some(TransactionChannel::WebBanking), totals[0].channel);
+    assert_eq!(1, totals[0].totals.count);
+    assert_eq!(cad("-30.95"), totals[0].totals.net);
+}
+
+#[test]
+fn test_transfers_are_left_out_of_tag_totals() {
+    let mut rows = rows();
+    rows[0].tags = vec!["house".to_string()];
+    rows.push(transfer());
+
+    let totals = tag_totals(&rows, &CategoryTree::defaults()).unwrap();
+
+    assert_eq!(1, totals.len());
+    assert_eq!(1, totals[0].totals.count);
+    assert_eq!(cad("-1374.47"), totals[0].totals.net);
+}
@@ -0,0 +1,1205 @@
+use std::{collections::HashMap, error::Error, fmt, path::Path};
+
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use crate::account::{Account, AccountType};
+use crate::category::{CategoryTree, TransactionCategory};
+use crate::channel::ChannelTable;
+use crate::csv_parser::{Data, Split, StatementMetadata, TransactionChannel, TransactionType};
+use crate::money::{Currency, Money};
+use crate::transfer::{TransferMatch, TransferStatus, TRANSFER_RULE};
+
+/// Schema changes, applied in order. `PRAGMA user_version` records how many have run.
+const MIGRATIONS: &[&str] = &[
+    r#"
+    CREATE TABLE import_batch (
+        id INTEGER PRIMARY KEY,
+        source_file TEXT NOT NULL,
+        account TEXT NOT NULL,
+        imported_at TEXT NOT NULL
+    );
+
+    CREATE TABLE transactions (
+        id INTEGER PRIMARY KEY,
+        batch_id INTEGER NOT NULL REFERENCES import_batch(id),
+        account TEXT NOT NULL,
+        fingerprint TEXT NOT NULL UNIQUE,
+        transaction_type TEXT NOT NULL,
+        date TEXT NOT NULL,
+        amount INTEGER NOT NULL,
+        currency TEXT NOT NULL,
+        description TEXT NOT NULL,
+        category TEXT NOT NULL,
+        categorized_by TEXT
+    );
+
+    CREATE INDEX transactions_date ON transactions(date);
+"#,
+    r#"
+    CREATE TABLE accounts (
+        id TEXT PRIMARY KEY,
+        masked_number TEXT,
+        institution TEXT,
+        account_type TEXT NOT NULL,
+        currency TEXT NOT NULL,
+        opening_balance INTEGER NOT NULL
+    );
+
+    INSERT INTO accounts (id, account_type, currency, opening_balance)
+        SELECT account, 'other', MIN(currency), 0 FROM transactions GROUP BY account;
+    INSERT OR IGNORE INTO accounts (id, account_type, currency, opening_balance)
+        SELECT DISTINCT account, 'other', 'CAD', 0 FROM import_batch;
+
+    ALTER TABLE transactions ADD COLUMN account_number TEXT;
+"#,
+    r#"
+    CREATE TABLE transfers (
+        id INTEGER PRIMARY KEY,
+        debit_id INTEGER NOT NULL UNIQUE REFERENCES transactions(id),
+        credit_id INTEGER NOT NULL UNIQUE REFERENCES transactions(id),
+        confidence REAL NOT NULL,
+        status TEXT NOT NULL
+    );
+"#,
+    r#"
+    ALTER TABLE transactions ADD COLUMN balance INTEGER;
+"#,
+    r#"
+    ALTER TABLE import_batch ADD COLUMN valid_as_of TEXT;
+    ALTER TABLE import_batch ADD COLUMN first_date TEXT;
+    ALTER TABLE import_batch ADD COLUMN last_date TEXT;
+"#,
+    r#"
+    ALTER TABLE transactions ADD COLUMN transaction_id TEXT;
+    ALTER TABLE transactions ADD COLUMN user_date TEXT;
+"#,
+    r#"
+    ALTER TABLE transactions ADD COLUMN channel TEXT;
+"#,
+    r#"
+    CREATE TABLE splits (
+        id INTEGER PRIMARY KEY,
+        transaction_id INTEGER NOT NULL REFERENCES transactions(id),
+        category TEXT NOT NULL,
+        amount INTEGER NOT NULL,
+        memo TEXT
+    );
+
+    CREATE INDEX splits_transaction ON splits(transaction_id);
+"#,
+    r#"
+    ALTER TABLE transactions ADD COLUMN note TEXT;
+
+    CREATE TABLE tags (
+        transaction_id INTEGER NOT NULL REFERENCES transactions(id),
+        tag TEXT NOT NULL,
+        PRIMARY KEY (transaction_id, tag)
+    );
+
+    CREATE INDEX tags_tag ON tags(tag);
+
+    CREATE TABLE attachments (
+        id INTEGER PRIMARY KEY,
+        transaction_id INTEGER NOT NULL REFERENCES transactions(id),
+        path TEXT NOT NULL,
+        UNIQUE (transaction_id, path)
+    );
+"#,
+    r#"
+    ALTER TABLE transfers ADD COLUMN debit_category TEXT;
+    ALTER TABLE transfers ADD COLUMN debit_categorized_by TEXT;
+    ALTER TABLE transfers ADD COLUMN credit_category TEXT;
+    ALTER TABLE transfers ADD COLUMN credit_categorized_by TEXT;
+"#,
+    r#"
+    CREATE TABLE transfers_v11 (
+        id INTEGER PRIMARY KEY,
+        debit_id INTEGER NOT NULL REFERENCES transactions(id),
+        credit_id INTEGER NOT NULL REFERENCES transactions(id),
+        confidence REAL NOT NULL,
+        status TEXT NOT NULL,
+        debit_category TEXT,
+        debit_categorized_by TEXT,
+        credit_category TEXT,
+        credit_categorized_by TEXT
+    );
+    INSERT INTO transfers_v11 SELECT id, debit_id, credit_id, confidence, status, debit_category,
+        debit_categorized_by, credit_category, credit_categorized_by FROM transfers;
+    DROP TABLE transfers;
+    ALTER TABLE transfers_v11 RENAME TO transfers;
+
+    -- A rejected pair doesn't stop either side from being paired with its real counterpart
+    CREATE UNIQUE INDEX transfers_debit ON transfers(debit_id) WHERE status != 'rejected';
+    CREATE UNIQUE INDEX transfers_credit ON transfers(credit_id) WHERE status != 'rejected';
+"#,
+];
+
+/// The `user_version` that adds `transactions.channel`, after which stored rows are decoded with
+/// `ChannelTable::defaults`
+const CHANNEL_VERSION: usize = 7;
+
+/// Date format of the `date` columns, sorts chronologically as text
+const DATE_FORMAT: &str = "%Y-%m-%d";
+/// Format of `import_batch.valid_as_of`, sorts chronologically as text
+const DATE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// One call to `Store::import`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportBatch {
+    pub id: i64,
+    pub source_file: String,
+    pub account: String,
+    /// RFC 3339 timestamp
+    pub imported_at: String,
+    /// When the bank exported the statement, if it said so
+    pub valid_as_of: Option<NaiveDateTime>,
+    /// Dates of the oldest and newest rows in the batch
+    pub first_date: Option<NaiveDate>,
+    pub last_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub batch_id: i64,
+    /// Rows stored for the first time
+    pub inserted: usize,
+    /// Rows already in the store from an earlier import
+    pub duplicates: usize,
+}
+
+/// A transaction as kept in the store
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredTransaction {
+    pub id: i64,
+    pub batch_id: i64,
+    pub account: String,
+    pub fingerprint: String,
+    pub data: Data,
+}
+
+/// A pair of transactions matched by `transfer::Matcher`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StoredTransfer {
+    pub id: i64,
+    pub transfer: TransferMatch,
+}
+
+/// The ledger of every imported transaction, kept in a SQLite database
+///
+/// Statement exports usually overlap, so every row gets a fingerprint made of its account, date,
+/// amount, normalized description and occurrence index. The occurrence index tells apart identical
+/// transactions within one import, e.g. two coffees bought on the same day, while re-importing
+/// the same rows maps them to the same fingerprints and skips them. Rows carrying a bank assigned
+/// transaction id, e.g. from OFX statements, are fingerprinted by that id instead.
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    /// Opens the database at `path`, creating it if needed
+    pub fn open(path: impl AsRef<Path>) -> Result<Store, StoreError> {
+        Store::init(Connection::open(path)?)
+    }
+
+    /// A store that only lives as long as the value, for tests and dry runs
+    pub fn open_in_memory() -> Result<Store, StoreError> {
+        Store::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Store, StoreError> {
+        conn.pragma_update(None, "foreign_keys", true)?;
+        let mut store = Store { conn };
+        store.migrate()?;
+        Ok(store)
+    }
+
+    fn migrate(&mut self) -> Result<(), StoreError> {
+        let version: i64 = self
+            .conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))?;
+        let version = usize::try_from(version).unwrap_or(usize::MAX);
+        if version > MIGRATIONS.len() {
+            return Err(StoreError::UnknownVersion(version));
+        }
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let tx = self.conn.transaction()?;
+            tx.execute_batch(migration)?;
+            if index + 1 == CHANNEL_VERSION {
+                Store::fill_channels_with(&tx, &ChannelTable::defaults())?;
+            }
+            tx.pragma_update(None, "user_version", index as i64 + 1)?;
+            tx.commit()?;
+        }
+        Ok(())
+    }
+
+    /// Decodes the channel of stored transactions that have none with `table`, returning how many
+    /// got one. Channels already set are kept.
+    pub fn fill_channels(&mut self, table: &ChannelTable) -> Result<usize, StoreError> {
+        let tx = self.conn.transaction()?;
+        let filled = Store::fill_channels_with(&tx, table)?;
+        tx.commit()?;
+        Ok(filled)
+    }
+
+    fn fill_channels_with(conn: &Connection, table: &ChannelTable) -> Result<usize, StoreError> {
+        let mut statement =
+            conn.prepare("SELECT id, description FROM transactions WHERE channel IS NULL")?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut filled = 0;
+        for (id, description) in rows {
+            if let Some(channel) = table.channel_of(&description) {
+                conn.execute(
+                    "UPDATE transactions SET channel = ?1 WHERE id = ?2",
+                    params![channel.as_str(), id],
+                )?;
+                filled += 1;
+            }
+        }
+        Ok(filled)
+    }
+
+    /// Adds an account, failing with `StoreError::AccountExists` when its id is taken and with
+    /// `StoreError::NumberTaken` when another account's number has the same visible digits
+    pub fn add_account(&mut self, account: &Account) -> Result<(), StoreError> {
+        if self.account(&account.id)?.is_some() {
+            return Err(StoreError::AccountExists(account.id.clone()));
+        }
+        if let Some(number) = &account.masked_number {
+            if let Some(other) = self
+                .accounts()?
+                .into_iter()
+                .find(|other| other.matches_number(number))
+            {
+                return Err(StoreError::NumberTaken {
+                    number: number.clone(),
+                    account: other.id,
+                });
+            }
+        }
+        self.conn.execute(
+            "INSERT INTO accounts (id, masked_number, institution, account_type, currency, \
+             opening_balance) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                account.id,
+                account.masked_number,
+                account.institution,
+                account.account_type.as_str(),
+                account.currency.code(),
+                account.opening_balance.minor_units(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Every account, ordered by id
+    pub fn accounts(&self) -> Result<Vec<Account>, StoreError> {
+        let mut statement = self.conn.prepare(
+            "SELECT id, masked_number, institution, account_type, currency, opening_balance \
+             FROM accounts ORDER BY id",
+        )?;
+        let mut rows = statement.query([])?;
+        let mut accounts = Vec::new();
+        while let Some(row) = rows.next()? {
+            accounts.push(Store::read_account(row)?);
+        }
+        Ok(accounts)
+    }
+
+    pub fn account(&self, id: &str) -> Result<Option<Account>, StoreError> {
+        Ok(self
+            .accounts()?
+            .into_iter()
+            .find(|account| account.id == id))
+    }
+
+    /// The account a statement row with this card or account number belongs to, failing with
+    /// `StoreError::AmbiguousNumber` when the visible digits match more than one account
+    pub fn account_by_number(&self, number: &str) -> Result<Option<Account>, StoreError> {
+        let mut matching: Vec<Account> = self
+            .accounts()?
+            .into_iter()
+            .filter(|account| account.matches_number(number))
+            .collect();
+        if matching.len() > 1 {
+            return Err(StoreError::AmbiguousNumber {
+                number: number.to_string(),
+                accounts: matching.into_iter().map(|account| account.id).collect(),
+            });
+        }
+        Ok(matching.pop())
+    }
+
+    fn read_account(row: &Row) -> Result<Account, StoreError> {
+        let id: String = row.get(0)?;
+        let corrupt = |column: &str, value: String| StoreError::CorruptAccount {
+            id: id.clone(),
+            column: column.to_string(),
+            value,
+        };
+        let account_type: String = row.get(3)?;
+        let currency: String = row.get(4)?;
+        let currency =
+            Currency::new(&currency).map_err(|_| corrupt("currency", currency.clone()))?;
+        Ok(Account {
+            masked_number: row.get(1)?,
+            institution: row.get(2)?,
+            account_type: AccountType::parse(&account_type)
+                .ok_or_else(|| corrupt("account_type", account_type.clone()))?,
+            currency,
+            opening_balance: Money::new(row.get(5)?, currency),
+            id,
+        })
+    }
+
+    /// Stores `rows` read from `source_file` for `account`, skipping rows already in the store
+    ///
+    /// The account must have been added with `add_account` first.
+    pub fn import(
+        &mut self,
+        source_file: &str,
+        account: &str,
+        rows: &[Data],
+    ) -> Result<ImportSummary, StoreError> {
+        self.import_with(source_file, account, rows, &StatementMetadata::default())
+    }
+
+    /// Like `import`, also recording when the statement was exported
+    pub fn import_with(
+        &mut self,
+        source_file: &str,
+        account: &str,
+        rows: &[Data],
+        metadata: &StatementMetadata,
+    ) -> Result<ImportSummary, StoreError> {
+        if self.account(account)?.is_none() {
+            return Err(StoreError::UnknownAccount(account.to_string()));
+        }
+        for data in rows {
+            check_splits(data)?;
+            for tag in &data.tags {
+                normalize_tag(tag)?;
+            }
+        }
+        let first_date = rows.iter().map(|data| data.date).min();
+        let last_date = rows.iter().map(|data| data.date).max();
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO import_batch (source_file, account, imported_at, valid_as_of, \
+             first_date, last_date) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                source_file,
+                account,
+                Utc::now().to_rfc3339(),
+                metadata
+                    .valid_as_of
+                    .map(|valid_as_of| valid_as_of.format(DATE_TIME_FORMAT).to_string()),
+                first_date.map(|date| date.format(DATE_FORMAT).to_string()),
+                last_date.map(|date| date.format(DATE_FORMAT).to_string()),
+            ],
+        )?;
+        let batch_id = tx.last_insert_rowid();
+
+        let mut summary = ImportSummary {
+            batch_id,
+            inserted: 0,
+            duplicates: 0,
+        };
+        {
+            let mut insert = tx.prepare(
+                "INSERT OR IGNORE INTO transactions (batch_id, account, fingerprint, \
+                 transaction_type, date, amount, currency, description, category, categorized_by, \
+                 account_number, balance, transaction_id, user_date, channel, note) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+            )?;
+            for (data, fingerprint) in rows.iter().zip(fingerprints(account, rows)) {
+                let inserted = insert.execute(params![
+                    batch_id,
+                    account,
+                    fingerprint,
+                    data.transaction_type.as_str(),
+                    data.date.format(DATE_FORMAT).to_string(),
+                    data.amount.minor_units(),
+                    data.amount.currency().code(),
+                    data.description,
+                    data.category.id(),
+                    data.categorized_by,
+                    data.account_number,
+                    data.balance.map(|balance| balance.minor_units()),
+                    data.transaction_id,
+                    data.user_date
+                        .map(|date| date.format(DATE_FORMAT).to_string()),
+                    data.channel.map(|channel| channel.as_str()),
+                    data.note,
+                ])?;
+                if inserted == 0 {
+                    summary.duplicates += 1;
+                } else {
+                    summary.inserted += 1;
+                    let id = tx.last_insert_rowid();
+                    Store::insert_splits(&tx, id, &data.splits)?;
+                    Store::insert_tags(&tx, id, &data.tags)?;
+                    for path in &data.attachments {
+                        tx.execute(
+                            "INSERT OR IGNORE INTO attachments (transaction_id, path) \
+                             VALUES (?1, ?2)",
+                            params![id, path],
+                        )?;
+                    }
+                }
+            }
+        }
+        tx.commit()?;
+
+        Ok(summary)
+    }
+
+    /// Every import, oldest first
+    pub fn batches(&self) -> Result<Vec<ImportBatch>, StoreError> {
+        let mut statement = self.conn.prepare(
+            "SELECT id, source_file, account, imported_at, valid_as_of, first_date, last_date \
+             FROM import_batch ORDER BY id",
+        )?;
+        let mut rows = statement.query([])?;
+        let mut batches = Vec::new();
+        while let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            let corrupt = |column: &str, value: String| StoreError::CorruptBatch {
+                id,
+                column: column.to_string(),
+                value,
+            };
+            let valid_as_of = row
+                .get::<_, Option<String>>(4)?
+                .map(|text| {
+                    NaiveDateTime::parse_from_str(&text, DATE_TIME_FORMAT)
+                        .map_err(|_| corrupt("valid_as_of", text))
+                })
+                .transpose()?;
+            let date = |index: usize, column: &str| -> Result<Option<NaiveDate>, StoreError> {
+                row.get::<_, Option<String>>(index)?
+                    .map(|text| {
+                        NaiveDate::parse_from_str(&text, DATE_FORMAT)
+                            .map_err(|_| corrupt(column, text))
+                    })
+                    .transpose()
+            };
+            batches.push(ImportBatch {
+                id,
+                source_file: row.get(1)?,
+                account: row.get(2)?,
+                imported_at: row.get(3)?,
+                valid_as_of,
+                first_date: date(5, "first_date")?,
+                last_date: date(6, "last_date")?,
+            });
+        }
+        Ok(batches)
+    }
+
+    /// The export time of the newest statement imported for `account`, `None` if no statement
+    /// said when it was exported
+    pub fn valid_as_of(&self, account: &str) -> Result<Option<NaiveDateTime>, StoreError> {
+        let newest: Option<(i64, String)> = self
+            .conn
+            .query_row(
+                "SELECT id, valid_as_of FROM import_batch \
+                 WHERE account = ?1 AND valid_as_of IS NOT NULL \
+                 ORDER BY valid_as_of DESC LIMIT 1",
+                params![account],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        newest
+            .map(|(id, text)| {
+                NaiveDateTime::parse_from_str(&text, DATE_TIME_FORMAT).map_err(|_| {
+                    StoreError::CorruptBatch {
+                        id,
+                        column: "valid_as_of".to_string(),
+                        value: text,
+                    }
+                })
+            })
+            .transpose()
+    }
+
+    /// Every stored transaction, in date order
+    pub fn transactions(&self) -> Result<Vec<StoredTransaction>, StoreError> {
+        let mut statement = self.conn.prepare(
+            "SELECT id, batch_id, account, fingerprint, transaction_type, date, amount, currency, \
+             description, category, categorized_by, account_number, balance, transaction_id, \
+             user_date, channel, note FROM transactions \
+             ORDER BY date, id",
+        )?;
+        let mut rows = statement.query([])?;
+        let mut transactions = Vec::new();
+        while let Some(row) = rows.next()? {
+            transactions.push(Store::read_transaction(row)?);
+        }
+        self.read_details(&mut transactions, None)?;
+        Ok(transactions)
+    }
+
+    pub fn transaction(&self, id: i64) -> Result<Option<StoredTransaction>, StoreError> {
+        let mut statement = self.conn.prepare(
+            "SELECT id, batch_id, account, fingerprint, transaction_type, date, amount, currency, \
+             description, category, categorized_by, account_number, balance, transaction_id, \
+             user_date, channel, note FROM transactions \
+             WHERE id = ?1",
+        )?;
+        let transaction = statement
+            .query_row([id], |row| Ok(Store::read_transaction(row)))
+            .optional()?
+            .transpose()?;
+        let mut transactions: Vec<StoredTransaction> = transaction.into_iter().collect();
+        self.read_details(&mut transactions, Some(id))?;
+        Ok(transactions.pop())
+    }
+
+    /// Fills in the splits, tags and attachments of `transactions`, which are every transaction
+    /// or only the transaction `id`
+    fn read_details(
+        &self,
+        transactions: &mut [StoredTransaction],
+        id: Option<i64>,
+    ) -> Result<(), StoreError> {
+        let mut splits = self.splits(id)?;
+        let mut tags = self.strings("SELECT transaction_id, tag FROM tags", id)?;
+        let mut attachments = self.strings("SELECT transaction_id, path FROM attachments", id)?;
+        for transaction in transactions {
+            let data = &mut transaction.data;
+            data.splits = splits.remove(&transaction.id).unwrap_or_default();
+            data.tags = tags.remove(&transaction.id).unwrap_or_default();
+            data.tags.sort();
+            data.attachments = attachments.remove(&transaction.id).unwrap_or_default();
+        }
+        Ok(())
+    }
+
+    /// The second column of `select` by transaction id, the first column, in insertion order
+    fn strings(
+        &self,
+        select: &str,
+        id: Option<i64>,
+    ) -> Result<HashMap<i64, Vec<String>>, StoreError> {
+        let mut statement = self.conn.prepare(&format!(
+            "{select} WHERE ?1 IS NULL OR transaction_id = ?1 ORDER BY rowid"
+        ))?;
+        let mut rows = statement.query([id])?;
+        let mut strings: HashMap<i64, Vec<String>> = HashMap::new();
+        while let Some(row) = rows.next()? {
+            strings.entry(row.get(0)?).or_default().push(row.get(1)?);
+        }
+        Ok(strings)
+    }
+
+    /// The splits of every transaction, or of the transaction `id`, by transaction id. Amounts
+    /// are in the currency of their transaction.
+    fn splits(&self, id: Option<i64>) -> Result<HashMap<i64, Vec<Split>>, StoreError> {
+        let mut statement = self.conn.prepare(
+            "SELECT splits.transaction_id, splits.category, splits.amount, splits.memo, \
+             transactions.currency FROM splits \
+             JOIN transactions ON transactions.id = splits.transaction_id \
+             WHERE ?1 IS NULL OR splits.transaction_id = ?1 ORDER BY splits.id",
+        )?;
+        let mut rows = statement.query([id])?;
+        let mut splits: HashMap<i64, Vec<Split>> = HashMap::new();
+        while let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            let category: String = row.get(1)?;
+            let currency: String = row.get(4)?;
+            let currency = Currency::new(&currency).map_err(|_| StoreError::Corrupt {
+                id,
+                column: "currency".to_string(),
+                value: currency.clone(),
+            })?;
+            splits.entry(id).or_default().push(Split {
+                category: TransactionCategory::new(&category),
+                amount: Money::new(row.get(2)?, currency),
+                memo: row.get(3)?,
+            });
+        }
+        Ok(splits)
+    }
+
+    /// Divides a transaction between categories, replacing its splits. The amounts must add up
+    /// to the transaction's amount, no splits undoes the split.
+    pub fn set_splits(&mut self, id: i64, splits: &[Split]) -> Result<(), StoreError> {
+        let mut transaction = self.transaction(id)?.ok_or(StoreError::NotFound(id))?;
+        transaction.data.splits = splits.to_vec();
+        check_splits(&transaction.data)?;
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM splits WHERE transaction_id = ?1", [id])?;
+        Store::insert_splits(&tx, id, splits)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn insert_splits(conn: &Connection, id: i64, splits: &[Split]) -> Result<(), StoreError> {
+        for split in splits {
+            conn.execute(
+                "INSERT INTO splits (transaction_id, category, amount, memo) \
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    id,
+                    split.category.id(),
+                    split.amount.minor_units(),
+                    split.memo
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Stored transactions `filter` keeps, in date order
+    pub fn find(
+        &self,
+        filter: &TransactionFilter,
+        tree: &CategoryTree,
+    ) -> Result<Vec<StoredTransaction>, StoreError> {
+        let mut transactions = self.transactions()?;
+        transactions.retain(|transaction| filter.matches(transaction, tree));
+        Ok(transactions)
+    }
+
+    /// Adds tags to a transaction, see `normalize_tag`. Tags it already has are ignored.
+    pub fn add_tags(&mut self, id: i64, tags: &[String]) -> Result<(), StoreError> {
+        self.transaction(id)?.ok_or(StoreError::NotFound(id))?;
+        let tx = self.conn.transaction()?;
+        Store::insert_tags(&tx, id, tags)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn insert_tags(conn: &Connection, id: i64, tags: &[String]) -> Result<(), StoreError> {
+        for tag in tags {
+            conn.execute(
+                "INSERT OR IGNORE INTO tags (transaction_id, tag) VALUES (?1, ?2)",
+                params![id, normalize_tag(tag)?],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Removes tags from a transaction. Tags it doesn't have are ignored.
+    pub fn remove_tags(&mut self, id: i64, tags: &[String]) -> Result<(), StoreError> {
+        self.transaction(id)?.ok_or(StoreError::NotFound(id))?;
+        for tag in tags {
+            self.conn.execute(
+                "DELETE FROM tags WHERE transaction_id = ?1 AND tag = ?2",
+                params![id, normalize_tag(tag)?],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Every tag in use with the number of transactions carrying it, ordered by tag
+    pub fn tags(&self) -> Result<Vec<(String, usize)>, StoreError> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT tag, COUNT(*) FROM tags GROUP BY tag ORDER BY tag")?;
+        let tags = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize)))?
+            .collect::<Result<_, _>>()?;
+        Ok(tags)
+    }
+
+    /// Replaces the note of a transaction, `None` removes it
+    pub fn set_note(&mut self, id: i64, note: Option<&str>) -> Result<(), StoreError> {
+        let updated = self.conn.execute(
+            "UPDATE transactions SET note = ?1 WHERE id = ?2",
+            params![note, id],
+        )?;
+        if updated == 0 {
+            return Err(StoreError::NotFound(id));
+        }
+        Ok(())
+    }
+
+    /// Links a file to a transaction. The path is stored as given, the file isn't copied.
+    pub fn attach(&mut self, id: i64, path: &str) -> Result<(), StoreError> {
+        self.transaction(id)?.ok_or(StoreError::NotFound(id))?;
+        self.conn.execute(
+            "INSERT OR IGNORE INTO attachments (transaction_id, path) VALUES (?1, ?2)",
+            params![id, path],
+        )?;
+        Ok(())
+    }
+
+    /// Unlinks a file from a transaction, returning whether it was attached
+    pub fn detach(&mut self, id: i64, path: &str) -> Result<bool, StoreError> {
+        self.transaction(id)?.ok_or(StoreError::NotFound(id))?;
+        let removed = self.conn.execute(
+            "DELETE FROM attachments WHERE transaction_id = ?1 AND path = ?2",
+            params![id, path],
+        )?;
+        Ok(removed > 0)
+    }
+
+    /// Records the category of a transaction and the rule that assigned it, if any
+    pub fn set_category(
+        &mut self,
+        id: i64,
+        category: &TransactionCategory,
+        categorized_by: Option<&str>,
+    ) -> Result<(), StoreError> {
+        let updated = self.conn.execute(
+            "UPDATE transactions SET category = ?1, categorized_by = ?2 WHERE id = ?3",
+            params![category.id(), categorized_by, id],
+        )?;
+        if updated == 0 {
+            return Err(StoreError::NotFound(id));
+        }
+        Ok(())
+    }
+
+    /// Records matched transfers, filing both sides of confirmed ones under
+    /// `TransactionCategory::ACCOUNT_TRANSFERS`
+    ///
+    /// The categories both sides had are kept with the match, rejecting it restores them.
+    pub fn record_transfers(&mut self, transfers: &[TransferMatch]) -> Result<(), StoreError> {
+        let tx = self.conn.transaction()?;
+        for transfer in transfers {
+            let inserted = tx.execute(
+                "INSERT INTO transfers (debit_id, credit_id, confidence, status, \
+                 debit_category, debit_categorized_by, credit_category, credit_categorized_by) \
+                 SELECT ?1, ?2, ?3, ?4, debit.category, debit.categorized_by, \
+                 credit.category, credit.categorized_by \
+                 FROM transactions debit, transactions credit \
+                 WHERE debit.id = ?1 AND credit.id = ?2",
+                params![
+                    transfer.debit,
+                    transfer.credit,
+                    transfer.confidence,
+                    transfer.status.as_str()
+                ],
+            )?;
+            if inserted != 1 {
+                let debit_exists: bool = tx.query_row(
+                    "SELECT EXISTS (SELECT 1 FROM transactions WHERE id = ?1)",
+                    [transfer.debit],
+                    |row| row.get(0),
+                )?;
+                return Err(StoreError::NotFound(if debit_exists {
+                    transfer.credit
+                } else {
+                    transfer.debit
+                }));
+            }
+            if transfer.status == TransferStatus::Confirmed {
+                Store::file_as_transfer(&tx, tx.last_insert_rowid(), transfer, true)?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Files both sides of the transfer `id` under `TransactionCategory::ACCOUNT_TRANSFERS` or,
+    /// when `is_transfer` is false, gives back the categories they had before the match
+    ///
+    /// A side the user categorized by hand keeps its category either way.
+    fn file_as_transfer(
+        conn: &Connection,
+        id: i64,
+        transfer: &TransferMatch,
+        is_transfer: bool,
+    ) -> Result<(), StoreError> {
+        if is_transfer {
+            conn.execute(
+                "UPDATE transactions SET category = ?1, categorized_by = ?2 \
+                 WHERE id IN (?3, ?4) AND (categorized_by IS NOT NULL OR category = ?5)",
+                params![
+                    TransactionCategory::ACCOUNT_TRANSFERS.id(),
+                    TRANSFER_RULE,
+                    transfer.debit,
+                    transfer.credit,
+                    TransactionCategory::OTHER.id(),
+                ],
+            )?;
+        } else {
+            // Matches recorded before the previous categories were kept fall back to `OTHER`
+            conn.execute(
+                "UPDATE transactions SET \
+                 category = coalesce((SELECT CASE WHEN transactions.id = debit_id \
+                 THEN debit_category ELSE credit_category END \
+                 FROM transfers WHERE id = ?1), ?2), \
+                 categorized_by = (SELECT CASE WHEN transactions.id = debit_id \
+                 THEN debit_categorized_by ELSE credit_categorized_by END \
+                 FROM transfers WHERE id = ?1) \
+                 WHERE id IN (?3, ?4) AND categorized_by = ?5",
+                params![
+                    id,
+                    TransactionCategory::OTHER.id(),
+                    transfer.debit,
+                    transfer.credit,
+                    TRANSFER_RULE,
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Every matched transfer, in the order they were found
+    pub fn transfers(&self) -> Result<Vec<StoredTransfer>, StoreError> {
+        let mut statement = self.conn.prepare(
+            "SELECT id, debit_id, credit_id, confidence, status FROM transfers ORDER BY id",
+        )?;
+        let mut rows = statement.query([])?;
+        let mut transfers = Vec::new();
+        while let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            let status: String = row.get(4)?;
+            transfers.push(StoredTransfer {
+                id,
+                transfer: TransferMatch {
+                    debit: row.get(1)?,
+                    credit: row.get(2)?,
+                    confidence: row.get(3)?,
+                    status: TransferStatus::parse(&status).ok_or_else(|| StoreError::Corrupt {
+                        id,
+                        column: String::from("status"),
+                        value: status.clone(),
+                    })?,
+                },
+            });
+        }
+        Ok(transfers)
+    }
+
+    /// Confirms or rejects a matched transfer, updating the category of both sides
+    pub fn set_transfer_status(
+        &mut self,
+        id: i64,
+        status: TransferStatus,
+    ) -> Result<(), StoreError> {
+        let transfer = self
+            .transfers()?
+            .into_iter()
+            .find(|transfer| transfer.id == id)
+            .ok_or(StoreError::TransferNotFound(id))?;
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "UPDATE transfers SET status = ?1 WHERE id = ?2",
+            params![status.as_str(), id],
+        )?;
+        Store::file_as_transfer(
+            &tx,
+            id,
+            &transfer.transfer,
+            status == TransferStatus::Confirmed,
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn read_transaction(row: &Row) -> Result<StoredTransaction, StoreError> {
+        let id: i64 = row.get(0)?;
+        let corrupt = |column: &str, value: String| StoreError::Corrupt {
+            id,
+            column: column.to_string(),
+            value,
+        };
+
+        let transaction_type: String = row.get(4)?;
+        let date: String = row.get(5)?;
+        let currency: String = row.get(7)?;
+        let category: String = row.get(9)?;
+        let currency =
+            Currency::new(&currency).map_err(|_| corrupt("currency", currency.clone()))?;
+        let balance: Option<i64> = row.get(12)?;
+        let user_date = row
+            .get::<_, Option<String>>(14)?
+            .map(|text| {
+                NaiveDate::parse_from_str(&text, DATE_FORMAT)
+                    .map_err(|_| corrupt("user_date", text))
+            })
+            .transpose()?;
+        let channel = row
+            .get::<_, Option<String>>(15)?
+            .map(|text| TransactionChannel::parse(&text).ok_or_else(|| corrupt("channel", text)))
+            .transpose()?;
+        let data = Data {
+            account_number: row.get(11)?,
+            transaction_type: TransactionType::parse(&transaction_type)
+                .ok_or_else(|| corrupt("transaction_type", transaction_type.clone()))?,
+            date: NaiveDate::parse_from_str(&date, DATE_FORMAT)
+                .map_err(|_| corrupt("date", date.clone()))?,
+            amount: Money::new(row.get(6)?, currency),
+            description: row.get(8)?,
+            category: TransactionCategory::new(&category),
+            splits: Vec::new(),
+            balance: balance.map(|balance| Money::new(balance, currency)),
+            transaction_id: row.get(13)?,
+            user_date,
+            channel,
+            categorized_by: row.get(10)?,
+            tags: Vec::new(),
+            note: row.get(16)?,
+            attachments: Vec::new(),
+        };
+
+        Ok(StoredTransaction {
+            id,
+            batch_id: row.get(1)?,
+            account: row.get(2)?,
+            fingerprint: row.get(3)?,
+            data,
+        })
+    }
+}
+
+/// Criteria for `Store::find`, unset fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct TransactionFilter {
+    pub account: Option<String>,
+    /// Inclusive
+    pub from: Option<NaiveDate>,
+    /// Inclusive
+    pub to: Option<NaiveDate>,
+    /// Also matches the category's descendants
+    pub category: Option<TransactionCategory>,
+    pub transaction_type: Option<TransactionType>,
+    /// Inclusive bound on the signed amount
+    pub min_amount: Option<Money>,
+    /// Inclusive bound on the signed amount
+    pub max_amount: Option<Money>,
+    /// Case insensitive substring of the description or the note
+    pub search: Option<String>,
+    /// A tag the transaction carries, see `normalize_tag`
+    pub tag: Option<String>,
+    pub channel: Option<TransactionChannel>,
+}
+
+impl TransactionFilter {
+    /// Whether `category` is the filter's category or one of its descendants, always true
+    /// without a category
+    pub fn matches_category(&self, category: &TransactionCategory, tree: &CategoryTree) -> bool {
+        self.category
+            .as_ref()
+            .is_none_or(|filter| tree.is_within(category, filter) || category == filter)
+    }
+
+    pub fn matches(&self, transaction: &StoredTransaction, tree: &CategoryTree) -> bool {
+        let data = &transaction.data;
+        self.account
+            .as_ref()
+            .is_none_or(|account| *account == transaction.account)
+            && self.from.is_none_or(|from| data.date >= from)
+            && self.to.is_none_or(|to| data.date <= to)
+            && ((data.splits.is_empty() && self.matches_category(&data.category, tree))
+                || data
+                    .splits
+                    .iter()
+                    .any(|split| self.matches_category(&split.category, tree)))
+            && self
+                .transaction_type
+                .is_none_or(|transaction_type| transaction_type == data.transaction_type)
+            && self.min_amount.is_none_or(|min| data.amount >= min)
+            && self.max_amount.is_none_or(|max| data.amount <= max)
+            && self.search.as_ref().is_none_or(|search| {
+                let search = search.to_lowercase();
+                data.description.to_lowercase().contains(&search)
+                    || data
+                        .note
+                        .as_ref()
+                        .is_some_and(|note| note.to_lowercase().contains(&search))
+            })
+            && self.tag.as_ref().is_none_or(|tag| {
+                let tag = tag.trim().to_lowercase();
+                data.tags.contains(&tag)
+            })
+            && self
+                .channel
+                .is_none_or(|channel| data.channel == Some(channel))
+    }
+}
+
+/// The stored form of a tag: trimmed and lower case. Tags are made of letters, digits, `-`, `_`,
+/// `.` and `/`, e.g. `vacation-2024`, and fail with `StoreError::InvalidTag` otherwise.
+pub fn normalize_tag(tag: &str) -> Result<String, StoreError> {
+    let tag = tag.trim().to_lowercase();
+    let valid = !tag.is_empty()
+        && tag
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | '/'));
+    if valid {
+        Ok(tag)
+    } else {
+        Err(StoreError::InvalidTag(tag))
+    }
+}
+
+/// Fails with `StoreError::UnbalancedSplits` unless the splits of `data` add up to its amount
+fn check_splits(data: &Data) -> Result<(), StoreError> {
+    if data.splits_balance() {
+        return Ok(());
+    }
+    Err(StoreError::UnbalancedSplits {
+        amount: data.amount,
+        splits: Money::checked_sum(
+            data.splits.iter().map(|split| split.amount),
+            data.amount.currency(),
+        )
+        .ok(),
+    })
+}
+
+/// Lowercases and collapses runs of whitespace so cosmetic changes between exports don't matter
+fn normalize_description(description: &str) -> String {
+    description
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// The fingerprint of each row, see `Store`. Rows with a `transaction_id` are identified by it
+/// alone.
+pub fn fingerprints(account: &str, rows: &[Data]) -> Vec<String> {
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+    rows.iter()
+        .map(|data| {
+            if let Some(transaction_id) = &data.transaction_id {
+                return format!("{account}|id|{transaction_id}");
+            }
+            let key = format!(
+                "{account}|{}|{}|{}|{}",
+                data.date.format(DATE_FORMAT),
+                data.amount.minor_units(),
+                data.amount.currency(),
+                normalize_description(&data.description)
+            );
+            let occurrence = occurrences.entry(key.clone()).or_default();
+            let fingerprint = format!("{key}|{occurrence}");
+            *occurrence += 1;
+            fingerprint
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum StoreError {
+    Sqlite(rusqlite::Error),
+    /// The database was written by a newer version of this crate
+    UnknownVersion(usize),
+    /// No transaction has this id
+    NotFound(i64),
+    /// No transfer has this id
+    TransferNotFound(i64),
+    /// No account has this id
+    UnknownAccount(String),
+    AccountExists(String),
+    /// An account added with a number whose visible digits are those of `account`
+    NumberTaken {
+        number: String,
+        account: String,
+    },
+    /// A statement number whose visible digits match several accounts
+    AmbiguousNumber {
+        number: String,
+        accounts: Vec<String>,
+    },
+    /// A stored value can't be read back
+    Corrupt {
+        id: i64,
+        column: String,
+        value: String,
+    },
+    CorruptAccount {
+        id: String,
+        column: String,
+        value: String,
+    },
+    /// An import batch column holds a value that cannot be read back
+    CorruptBatch {
+        id: i64,
+        column: String,
+        value: String,
+    },
+    /// The splits of a transaction don't add up to its amount. `splits` is `None` when they are
+    /// in another currency.
+    UnbalancedSplits {
+        amount: Money,
+        splits: Option<Money>,
+    },
+    /// A tag is empty or contains characters other than those `normalize_tag` allows
+    InvalidTag(String),
+}
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(e: rusqlite::Error) -> StoreError {
+        StoreError::Sqlite(e)
+    }
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Sqlite(source) => write!(f, "database error: {source}"),
+            StoreError::UnknownVersion(version) => {
+                write!(
+                    f,
+                    "database schema version {version} is newer than supported"
+                )
+            }
+            StoreError::NotFound(id) => write!(f, "no transaction with id {id}"),
+            StoreError::TransferNotFound(id) => write!(f, "no transfer with id {id}"),
+            StoreError::UnknownAccount(id) => write!(f, "no account with id {id:?}"),
+            StoreError::AccountExists(id) => write!(f, "account {id:?} already exists"),
+            StoreError::NumberTaken { number, account } => write!(
+                f,
+                "number {number} can't be told apart from the number of account {account:?}"
+            ),
+            StoreError::AmbiguousNumber { number, accounts } => write!(
+                f,
+                "number {number} matches accounts {}, pass --account",
+                accounts.join(", ")
+            ),
+            StoreError::Corrupt { id, column, value } => {
+                write!(f, "transaction {id} has an invalid {column}: {value:?}")
+            }
+            StoreError::CorruptAccount { id, column, value } => {
+                write!(f, "account {id:?} has an invalid {column}: {value:?}")
+            }
+            StoreError::CorruptBatch { id, column, value } => {
+                write!(f, "import batch {id} has an invalid {column}: {value:?}")
+            }
+            StoreError::UnbalancedSplits {
+                amount,
+                splits: Some(splits),
+            } => write!(
+                f,
+                "splits add up to {splits}, the transaction amount is {amount}"
+            ),
+            StoreError::UnbalancedSplits {
+                amount,
+                splits: None,
+            } => write!(
+                f,
+                "splits are not in the currency of the transaction amount {amount}"
+            ),
+            StoreError::InvalidTag(tag) => write!(
+                f,
+                "invalid tag {tag:?}, use letters, digits, '-', '_', '.' and '/'"
+            ),
+        }
+    }
+}
+
+impl Error for StoreError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StoreError::Sqlite(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./tests/test.rs"]
+mod test;
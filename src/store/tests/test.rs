@@ -0,0 +1,561 @@
+use super::*;
+
+use crate::account::mask_number;
+use crate::csv_parser::{parse_csv, parse_csv_with, ParseMode, StatementFormat};
+use crate::transfer::Matcher;
+
+const TEST_FILE_PATH: &str = "src/csv_parser/tests/test_statement.csv";
+const ACCOUNT: &str = "chequing";
+
+/// An empty store with a chequing and a savings account
+fn store() -> Store {
+    let mut store = Store::open_in_memory().unwrap();
+    let chequing = Account {
+        masked_number: mask_number("6007620712733055"),
+        account_type: AccountType::Chequing,
+        ..Account::new(ACCOUNT, Currency::CAD)
+    };
+    store.add_account(&chequing).unwrap();
+    store
+        .add_account(&Account::new("savings", Currency::CAD))
+        .unwrap();
+    store
+}
+
+#[test]
+fn test_import_and_read_back() {
+    let mut store = store();
+    let rows = parse_csv(TEST_FILE_PATH).unwrap();
+
+    let summary = store.import(TEST_FILE_PATH, ACCOUNT, &rows).unwrap();
+
+    assert_eq!(rows.len(), summary.inserted);
+    assert_eq!(0, summary.duplicates);
+    let stored = store.transactions().unwrap();
+    assert_eq!(
+        rows,
+        stored
+            .iter()
+            .map(|transaction| transaction.data.clone())
+            .collect::<Vec<Data>>()
+    );
+    assert!(stored
+        .iter()
+        .all(|transaction| transaction.account == ACCOUNT && transaction.batch_id == 1));
+    assert_eq!(
+        Some(&stored[0]),
+        store.transaction(stored[0].id).unwrap().as_ref()
+    );
+}
+
+#[test]
+fn test_reimport_skips_duplicates() {
+    let mut store = store();
+    let rows = parse_csv(TEST_FILE_PATH).unwrap();
+    store.import(TEST_FILE_PATH, ACCOUNT, &rows[..5]).unwrap();
+
+    // The next export overlaps with the first one
+    let summary = store.import(TEST_FILE_PATH, ACCOUNT, &rows[3..]).unwrap();
+
+    assert_eq!(rows.len() - 5, summary.inserted);
+    assert_eq!(2, summary.duplicates);
+    assert_eq!(rows.len(), store.transactions().unwrap().len());
+    assert_eq!(2, store.batches().unwrap().len());
+}
+
+#[test]
+fn test_transaction_ids_identify_rows() {
+    let mut store = store();
+    let mut rows = parse_csv(TEST_FILE_PATH).unwrap();
+    for (index, data) in rows.iter_mut().enumerate() {
+        data.transaction_id = Some(format!("FIT{index}"));
+    }
+    rows[1].user_date = NaiveDate::from_ymd_opt(2024, 6, 1);
+    store.import(TEST_FILE_PATH, ACCOUNT, &rows).unwrap();
+
+    // The bank reworded a description, the id still matches
+    rows[0].description = "MORTGAGE PAYMENT".to_string();
+    let summary = store.import(TEST_FILE_PATH, ACCOUNT, &rows).unwrap();
+
+    assert_eq!(0, summary.inserted);
+    assert_eq!(rows.len(), summary.duplicates);
+    let stored = store.transactions().unwrap();
+    assert_eq!("chequing|id|FIT0", stored[0].fingerprint);
+    assert_eq!(rows[1].user_date, stored[1].data.user_date);
+    assert_eq!(Some("FIT1"), stored[1].data.transaction_id.as_deref());
+}
+
+#[test]
+fn test_batches_record_statement_metadata() {
+    let mut store = store();
+    let output = parse_csv_with(
+        TEST_FILE_PATH,
+        &StatementFormat::first_bank(),
+        ParseMode::Strict,
+    )
+    .unwrap();
+    assert_eq!(None, store.valid_as_of(ACCOUNT).unwrap());
+
+    store
+        .import_with(TEST_FILE_PATH, ACCOUNT, &output.rows[..3], &output.metadata)
+        .unwrap();
+    store
+        .import(TEST_FILE_PATH, ACCOUNT, &output.rows[3..])
+        .unwrap();
+
+    let batches = store.batches().unwrap();
+    assert_eq!(output.metadata.valid_as_of, batches[0].valid_as_of);
+    assert_eq!(
+        (
+            NaiveDate::from_ymd_opt(2024, 6, 3),
+            NaiveDate::from_ymd_opt(2024, 6, 10)
+        ),
+        (batches[0].first_date, batches[0].last_date)
+    );
+    assert_eq!(None, batches[1].valid_as_of);
+    assert_eq!(
+        output.metadata.valid_as_of,
+        store.valid_as_of(ACCOUNT).unwrap()
+    );
+    assert_eq!(None, store.valid_as_of("savings").unwrap());
+}
+
+#[test]
+fn test_same_rows_in_other_account_are_kept() {
+    let mut store = store();
+    let rows = parse_csv(TEST_FILE_PATH).unwrap();
+    store.import(TEST_FILE_PATH, ACCOUNT, &rows).unwrap();
+
+    let summary = store.import(TEST_FILE_PATH, "savings", &rows).unwrap();
+
+    assert_eq!(rows.len(), summary.inserted);
+}
+
+#[test]
+fn test_identical_rows_get_occurrence_index() {
+    let rows = parse_csv(TEST_FILE_PATH).unwrap();
+    let mut coffee = rows[0].clone();
+    coffee.description = String::from("[DS]BANK MTG/HYP");
+    let twice = vec![rows[0].clone(), coffee];
+
+    let fingerprints = fingerprints(ACCOUNT, &twice);
+
+    assert_eq!(
+        vec![
+            "chequing|2024-06-03|-137447|CAD|[ds]bank mtg/hyp|0",
+            "chequing|2024-06-03|-137447|CAD|[ds]bank mtg/hyp|1",
+        ],
+        fingerprints
+    );
+
+    let mut store = store();
+    assert_eq!(
+        2,
+        store
+            .import(TEST_FILE_PATH, ACCOUNT, &twice)
+            .unwrap()
+            .inserted
+    );
+    assert_eq!(
+        0,
+        store
+            .import(TEST_FILE_PATH, ACCOUNT, &twice)
+            .unwrap()
+            .inserted
+    );
+}
+
+#[test]
+fn test_find_and_set_category() {
+    let mut store = store();
+    store
+        .import(TEST_FILE_PATH, ACCOUNT, &parse_csv(TEST_FILE_PATH).unwrap())
+        .unwrap();
+    let tree = CategoryTree::defaults();
+
+    let filter = TransactionFilter {
+        from: NaiveDate::from_ymd_opt(2024, 6, 10),
+        transaction_type: Some(TransactionType::DEBIT),
+        max_amount: Some(Money::new(-10000, Currency::CAD)),
+        search: Some(String::from("tf")),
+        ..TransactionFilter::default()
+    };
+    let found = store.find(&filter, &tree).unwrap();
+    assert_eq!(1, found.len());
+    assert_eq!("[CW] TF 000123456789", found[0].data.description);
+
+    store
+        .set_category(
+            found[0].id,
+            &TransactionCategory::ACCOUNT_TRANSFERS,
+            Some("transfers"),
+        )
+        .unwrap();
+    let by_category = TransactionFilter {
+        category: Some(TransactionCategory::ACCOUNT_TRANSFERS),
+        ..TransactionFilter::default()
+    };
+    let found = store.find(&by_category, &tree).unwrap();
+    assert_eq!(1, found.len());
+    assert_eq!(Some("transfers"), found[0].data.categorized_by.as_deref());
+    assert!(matches!(
+        store.set_category(999, &TransactionCategory::FOOD, None),
+        Err(StoreError::NotFound(999))
+    ));
+}
+
+#[test]
+fn test_accounts() {
+    let mut store = store();
+
+    let ids: Vec<String> = store
+        .accounts()
+        .unwrap()
+        .into_iter()
+        .map(|account| account.id)
+        .collect();
+    assert_eq!(vec!["chequing", "savings"], ids);
+    assert_eq!(
+        Some(String::from(ACCOUNT)),
+        store
+            .account_by_number("************3055")
+            .unwrap()
+            .map(|account| account.id)
+    );
+    assert_eq!(None, store.account_by_number("************1234").unwrap());
+    let visa = Account {
+        masked_number: mask_number("4520123456783055"),
+        ..Account::new("visa", Currency::CAD)
+    };
+    assert!(matches!(
+        store.add_account(&visa),
+        Err(StoreError::NumberTaken { account, .. }) if account == ACCOUNT
+    ));
+    assert!(matches!(
+        store.add_account(&Account::new("savings", Currency::USD)),
+        Err(StoreError::AccountExists(_))
+    ));
+    assert!(matches!(
+        store.import(TEST_FILE_PATH, "visa", &parse_csv(TEST_FILE_PATH).unwrap()),
+        Err(StoreError::UnknownAccount(_))
+    ));
+}
+
+#[test]
+fn test_ambiguous_account_number() {
+    let store = Store::open_in_memory().unwrap();
+    // Accounts added before `add_account` compared numbers can share their visible digits
+    for (id, number) in [
+        ("chequing", "6007620712733055"),
+        ("visa", "4520123456783055"),
+    ] {
+        store
+            .conn
+            .execute(
+                "INSERT INTO accounts (id, masked_number, account_type, currency, \
+                 opening_balance) VALUES (?1, ?2, 'other', 'CAD', 0)",
+                params![id, mask_number(number)],
+            )
+            .unwrap();
+    }
+
+    assert_eq!(
+        "number 3055 matches accounts chequing, visa, pass --account",
+        store.account_by_number("3055").unwrap_err().to_string()
+    );
+}
+
+#[test]
+fn test_migration_creates_accounts_of_existing_transactions() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(MIGRATIONS[0]).unwrap();
+    conn.execute_batch(
+        "PRAGMA user_version = 1;
+         INSERT INTO import_batch VALUES (1, 'june.csv', 'visa', '2024-07-14T16:48:14Z');
+         INSERT INTO transactions VALUES (1, 1, 'visa', 'visa|x', 'DEBIT', '2024-06-03', -100, \
+             'USD', 'COFFEE', 'other', NULL);",
+    )
+    .unwrap();
+
+    let store = Store::init(conn).unwrap();
+
+    assert_eq!(
+        vec![Account::new("visa", Currency::USD)],
+        store.accounts().unwrap()
+    );
+    assert_eq!(
+        None,
+        store.transaction(1).unwrap().unwrap().data.account_number
+    );
+}
+
+#[test]
+fn test_channels_are_stored_and_backfilled() {
+    let conn = Connection::open_in_memory().unwrap();
+    for migration in &MIGRATIONS[..6] {
+        conn.execute_batch(migration).unwrap();
+    }
+    conn.execute_batch(
+        "PRAGMA user_version = 6;
+         INSERT INTO accounts VALUES ('visa', NULL, NULL, 'other', 'CAD', 0);
+         INSERT INTO import_batch (id, source_file, account, imported_at) \
+             VALUES (1, 'june.csv', 'visa', '2024-07-14T16:48:14Z');
+         INSERT INTO transactions (id, batch_id, account, fingerprint, transaction_type, date, \
+             amount, currency, description, category) VALUES \
+             (1, 1, 'visa', 'visa|a', 'DEBIT', '2024-06-28', -3095, 'CAD', ' [sc]PREMIUM PLAN', \
+             'other'), \
+             (2, 1, 'visa', 'visa|b', 'DEBIT', '2024-06-03', -100, 'CAD', '[XX]COFFEE', 'other'), \
+             (3, 1, 'visa', 'visa|c', 'DEBIT', '2024-06-03', -100, 'CAD', 'COFFEE', 'other');",
+    )
+    .unwrap();
+    let mut store = Store::init(conn).unwrap();
+    let mut rows = parse_csv(TEST_FILE_PATH).unwrap();
+    rows[0].channel = Some(TransactionChannel::Other);
+    store.import(TEST_FILE_PATH, "visa", &rows[..1]).unwrap();
+
+    let channels: Vec<Option<TransactionChannel>> = store
+        .transactions()
+        .unwrap()
+        .iter()
+        .map(|transaction| transaction.data.channel)
+        .collect();
+
+    assert_eq!(
+        vec![
+            None,
+            None,
+            Some(TransactionChannel::Other),
+            Some(TransactionChannel::ServiceCharge)
+        ],
+        channels
+    );
+}
+
+#[test]
+fn test_splits() {
+    let mut store = store();
+    let mut rows = parse_csv(TEST_FILE_PATH).unwrap();
+    let split = |category: &str, amount: i64| Split {
+        category: TransactionCategory::new(category),
+        amount: Money::new(amount, Currency::CAD),
+        memo: None,
+    };
+    rows[1].splits = vec![split("bills", -20000), split("other", -3197)];
+    store.import(TEST_FILE_PATH, ACCOUNT, &rows).unwrap();
+    let tree = CategoryTree::defaults();
+
+    let stored = store.transactions().unwrap();
+    assert_eq!(rows[1].splits, stored[1].data.splits);
+    let filter = TransactionFilter {
+        category: Some(TransactionCategory::BILLS),
+        ..TransactionFilter::default()
+    };
+    assert_eq!(1, store.find(&filter, &tree).unwrap().len());
+
+    store
+        .set_splits(
+            stored[0].id,
+            &[split("bills", -100000), split("food", -37447)],
+        )
+        .unwrap();
+    assert_eq!(2, store.find(&filter, &tree).unwrap().len());
+    assert!(matches!(
+        store.set_splits(stored[0].id, &[split("bills", -100000)]),
+        Err(StoreError::UnbalancedSplits { .. })
+    ));
+    store.set_splits(stored[1].id, &[]).unwrap();
+    assert!(store
+        .transaction(stored[1].id)
+        .unwrap()
+        .unwrap()
+        .data
+        .splits
+        .is_empty());
+
+    rows[2].splits = vec![split("food", -100)];
+    assert!(matches!(
+        store.import("other.csv", ACCOUNT, &rows[2..3]),
+        Err(StoreError::UnbalancedSplits { .. })
+    ));
+}
+
+#[test]
+fn test_tags_notes_and_attachments() {
+    let mut store = store();
+    let mut rows = parse_csv(TEST_FILE_PATH).unwrap();
+    rows[0].tags = vec!["House".to_string()];
+    rows[0].note = Some("fixed rate until 2026".to_string());
+    store.import(TEST_FILE_PATH, ACCOUNT, &rows).unwrap();
+    let tree = CategoryTree::defaults();
+    let stored = store.transactions().unwrap();
+    let tags = |tags: &[&str]| tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>();
+
+    store
+        .add_tags(stored[2].id, &tags(&["june-trip", "house", "june-trip"]))
+        .unwrap();
+    store
+        .add_tags(stored[3].id, &tags(&[" June-Trip "]))
+        .unwrap();
+    store.remove_tags(stored[2].id, &tags(&["house"])).unwrap();
+    store
+        .set_note(stored[3].id, Some("dinner in Banff"))
+        .unwrap();
+    store.attach(stored[3].id, "/receipts/banff.pdf").unwrap();
+    store.attach(stored[3].id, "/receipts/banff.pdf").unwrap();
+
+    let first = store.transaction(stored[0].id).unwrap().unwrap().data;
+    assert_eq!(tags(&["house"]), first.tags);
+    assert_eq!(Some("fixed rate until 2026"), first.note.as_deref());
+    let fourth = store.transaction(stored[3].id).unwrap().unwrap().data;
+    assert_eq!(tags(&["june-trip"]), fourth.tags);
+    assert_eq!(tags(&["/receipts/banff.pdf"]), fourth.attachments);
+    assert_eq!(
+        vec![("house".to_string(), 1), ("june-trip".to_string(), 2)],
+        store.tags().unwrap()
+    );
+
+    let filter = TransactionFilter {
+        tag: Some("JUNE-TRIP".to_string()),
+        ..TransactionFilter::default()
+    };
+    assert_eq!(2, store.find(&filter, &tree).unwrap().len());
+    let filter = TransactionFilter {
+        search: Some("banff".to_string()),
+        ..TransactionFilter::default()
+    };
+    assert_eq!(1, store.find(&filter, &tree).unwrap().len());
+
+    assert!(store.detach(stored[3].id, "/receipts/banff.pdf").unwrap());
+    assert!(!store.detach(stored[3].id, "/receipts/banff.pdf").unwrap());
+    store.set_note(stored[3].id, None).unwrap();
+    assert_eq!(
+        None,
+        store.transaction(stored[3].id).unwrap().unwrap().data.note
+    );
+    assert!(matches!(
+        store.add_tags(stored[0].id, &tags(&["june trip"])),
+        Err(StoreError::InvalidTag(_))
+    ));
+    assert!(matches!(
+        store.add_tags(-1, &tags(&["house"])),
+        Err(StoreError::NotFound(-1))
+    ));
+}
+
+#[test]
+fn test_transfer_matches_keep_previous_categories() {
+    let mut store = store();
+    store
+        .import(TEST_FILE_PATH, ACCOUNT, &parse_csv(TEST_FILE_PATH).unwrap())
+        .unwrap();
+    let transfer = Data::new(
+        NaiveDate::from_ymd_opt(2024, 6, 24).unwrap(),
+        Money::new(150000, Currency::CAD),
+        "TF FROM CHEQUING",
+    );
+    store.import("savings.csv", "savings", &[transfer]).unwrap();
+    let mut matches = Matcher::default().find(&store.transactions().unwrap());
+    matches[0].status = TransferStatus::Review;
+    let (debit, credit) = (matches[0].debit, matches[0].credit);
+    store
+        .set_category(debit, &TransactionCategory::BILLS, Some("rent"))
+        .unwrap();
+    store
+        .set_category(credit, &TransactionCategory::EDUCATION, None)
+        .unwrap();
+    store.record_transfers(&matches).unwrap();
+    let id = store.transfers().unwrap()[0].id;
+    let category = |store: &Store, id: i64| {
+        let data = store.transaction(id).unwrap().unwrap().data;
+        (data.category, data.categorized_by)
+    };
+
+    store
+        .set_transfer_status(id, TransferStatus::Confirmed)
+        .unwrap();
+    assert_eq!(
+        (
+            TransactionCategory::ACCOUNT_TRANSFERS,
+            Some(TRANSFER_RULE.to_string())
+        ),
+        category(&store, debit)
+    );
+    // Categorized by hand, so the match leaves it alone
+    assert_eq!(
+        (TransactionCategory::EDUCATION, None),
+        category(&store, credit)
+    );
+
+    store
+        .set_transfer_status(id, TransferStatus::Rejected)
+        .unwrap();
+    assert_eq!(
+        (TransactionCategory::BILLS, Some("rent".to_string())),
+        category(&store, debit)
+    );
+    assert_eq!(
+        (TransactionCategory::EDUCATION, None),
+        category(&store, credit)
+    );
+}
+
+#[test]
+fn test_transfers() {
+    let mut store = store();
+    store
+        .import(TEST_FILE_PATH, ACCOUNT, &parse_csv(TEST_FILE_PATH).unwrap())
+        .unwrap();
+    let transfer = Data::new(
+        NaiveDate::from_ymd_opt(2024, 6, 24).unwrap(),
+        Money::new(150000, Currency::CAD),
+        "TF FROM CHEQUING",
+    );
+    store.import("savings.csv", "savings", &[transfer]).unwrap();
+
+    let matches = Matcher::default().find(&store.transactions().unwrap());
+    assert_eq!(1, matches.len());
+    store.record_transfers(&matches).unwrap();
+
+    let stored = store.transfers().unwrap();
+    assert_eq!(1, stored.len());
+    assert_eq!(TransferStatus::Confirmed, stored[0].transfer.status);
+    let debit = store.transaction(matches[0].debit).unwrap().unwrap();
+    assert_eq!(TransactionCategory::ACCOUNT_TRANSFERS, debit.data.category);
+    assert_eq!(Some(TRANSFER_RULE), debit.data.categorized_by.as_deref());
+
+    store
+        .set_transfer_status(stored[0].id, TransferStatus::Rejected)
+        .unwrap();
+    let credit = store.transaction(matches[0].credit).unwrap().unwrap();
+    assert_eq!(TransactionCategory::OTHER, credit.data.category);
+    assert_eq!(None, credit.data.categorized_by);
+    assert!(matches!(
+        store.set_transfer_status(99, TransferStatus::Confirmed),
+        Err(StoreError::TransferNotFound(99))
+    ));
+
+    // Both sides of the rejected pair can still be matched with something else
+    let deposit = Data::new(
+        NaiveDate::from_ymd_opt(2024, 6, 25).unwrap(),
+        Money::new(150000, Currency::CAD),
+        "TF FROM CHEQUING",
+    );
+    store
+        .import("savings-2.csv", "savings", &[deposit])
+        .unwrap();
+    let other = store.transactions().unwrap().last().unwrap().id;
+    store
+        .record_transfers(&[TransferMatch {
+            credit: other,
+            ..matches[0]
+        }])
+        .unwrap();
+    assert_eq!(2, store.transfers().unwrap().len());
+    assert!(matches!(
+        store.record_transfers(&[TransferMatch {
+            credit: 99,
+            ..matches[0]
+        }]),
+        Err(StoreError::NotFound(99))
+    ));
+}
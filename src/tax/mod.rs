@@ -0,0 +1,401 @@
+use std::{collections::BTreeMap, error::Error, fmt, fs, io, path::Path};
+
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::category::{CategoryTree, TransactionCategory};
+use crate::csv_parser::Data;
+use crate::money::{Currency, Money, MoneyError};
+use crate::output::{escape_html, html_table, Tabular};
+use crate::payee::{same_payee, Description};
+
+/// A schedule as written in the schedules file, see `Schedules`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScheduleConfig {
+    line: String,
+    name: String,
+    #[serde(default)]
+    categories: Vec<TransactionCategory>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    payees: Vec<String>,
+    percent: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SchedulesFile {
+    #[serde(default)]
+    schedule: Vec<ScheduleConfig>,
+}
+
+/// A line of a tax form and the transactions that can be claimed on it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    /// The line on the form, e.g. `33099`
+    pub line: String,
+    pub name: String,
+    /// Categories whose transactions qualify, subcategories included
+    pub categories: Vec<TransactionCategory>,
+    /// Tags any of which makes a transaction qualify, in lower case
+    pub tags: Vec<String>,
+    /// Cleaned payees, matched ignoring case and spacing
+    pub payees: Vec<String>,
+    /// Share of a qualifying amount that is deductible, e.g. the part of the house used as an
+    /// office
+    pub percent: u32,
+}
+
+impl Schedule {
+    /// Whether `data` qualifies for the schedule through its category, one of its tags or its
+    /// payee
+    pub fn matches(&self, data: &Data, tree: &CategoryTree) -> bool {
+        self.categories
+            .iter()
+            .any(|category| &data.category == category || tree.is_within(&data.category, category))
+            || data.tags.iter().any(|tag| self.tags.contains(tag))
+            || (!self.payees.is_empty() && {
+                let description = Description::parse(&data.description);
+                self.payees
+                    .iter()
+                    .any(|payee| same_payee(payee, &description.payee))
+            })
+    }
+
+    /// The deductible part of a transaction amount, positive for expenses and negative for
+    /// refunds
+    pub fn deductible(&self, amount: Money) -> Result<Money, MoneyError> {
+        amount
+            .checked_neg()?
+            .checked_mul(i64::from(self.percent))?
+            .checked_div(100)
+    }
+}
+
+/// A set of tax schedules, read from a TOML file:
+///
+/// ```toml
+/// [[schedule]]
+/// line = "33099"
+/// name = "Medical expenses"
+/// categories = ["healthcare"]
+/// tags = ["medical"]
+///
+/// [[schedule]]
+/// line = "9945"
+/// name = "Business-use-of-home expenses"
+/// categories = ["utilities"]
+/// payees = ["CITY TAX"]
+/// percent = 15
+/// ```
+///
+/// A schedule needs at least one category, tag or payee. `percent` defaults to 100. A transaction
+/// is claimed on the first schedule it qualifies for, so it is never counted twice.
+#[derive(Debug, Clone, Default)]
+pub struct Schedules {
+    schedules: Vec<Schedule>,
+}
+
+impl Schedules {
+    pub fn new(schedules: Vec<Schedule>) -> Schedules {
+        Schedules { schedules }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Schedules, TaxError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|source| TaxError::Io {
+            file: path.display().to_string(),
+            source,
+        })?;
+        Schedules::from_toml(&text)
+    }
+
+    pub fn from_toml(text: &str) -> Result<Schedules, TaxError> {
+        let file: SchedulesFile = toml::from_str(text).map_err(TaxError::Toml)?;
+        let mut schedules: Vec<Schedule> = Vec::new();
+        for config in file.schedule {
+            let line = config.line.trim().to_string();
+            if config.categories.is_empty() && config.tags.is_empty() && config.payees.is_empty() {
+                return Err(TaxError::NothingQualifies(line));
+            }
+            let percent = config.percent.unwrap_or(100);
+            if !(1..=100).contains(&percent) {
+                return Err(TaxError::InvalidPercent { line, percent });
+            }
+            if schedules.iter().any(|schedule| schedule.line == line) {
+                return Err(TaxError::Duplicate(line));
+            }
+            schedules.push(Schedule {
+                line,
+                name: config.name.trim().to_string(),
+                categories: config.categories,
+                tags: config
+                    .tags
+                    .iter()
+                    .map(|tag| tag.trim().to_lowercase())
+                    .collect(),
+                payees: config.payees,
+                percent,
+            });
+        }
+        Ok(Schedules::new(schedules))
+    }
+
+    pub fn schedules(&self) -> &[Schedule] {
+        &self.schedules
+    }
+
+    /// Checks that every schedule refers to categories of `tree`
+    pub fn validate(&self, tree: &CategoryTree) -> Result<(), TaxError> {
+        for schedule in &self.schedules {
+            if let Some(category) = schedule
+                .categories
+                .iter()
+                .find(|category| !tree.contains(category))
+            {
+                return Err(TaxError::UnknownCategory {
+                    line: schedule.line.clone(),
+                    category: category.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// The qualifying transactions of `year` in `rows` and their totals per schedule line
+    ///
+    /// Rows are expected to be allocations, so that only the qualifying parts of a split
+    /// transaction are claimed.
+    pub fn report(
+        &self,
+        rows: &[Data],
+        tree: &CategoryTree,
+        year: i32,
+    ) -> Result<TaxReport, MoneyError> {
+        let mut items: Vec<(usize, TaxItem)> = Vec::new();
+        for data in rows.iter().filter(|data| data.date.year() == year) {
+            let Some(index) = self
+                .schedules
+                .iter()
+                .position(|schedule| schedule.matches(data, tree))
+            else {
+                continue;
+            };
+            let schedule = &self.schedules[index];
+            items.push((
+                index,
+                TaxItem {
+                    line: schedule.line.clone(),
+                    date: data.date,
+                    description: data.description.clone(),
+                    category: data.category.clone(),
+                    amount: data.amount,
+                    deductible: schedule.deductible(data.amount)?,
+                },
+            ));
+        }
+        items.sort_by(|(a, item_a), (b, item_b)| a.cmp(b).then(item_a.date.cmp(&item_b.date)));
+
+        let mut totals: BTreeMap<(usize, Currency), TaxLine> = BTreeMap::new();
+        for (index, item) in &items {
+            let currency = item.amount.currency();
+            let schedule = &self.schedules[*index];
+            let total = totals.entry((*index, currency)).or_insert_with(|| TaxLine {
+                line: schedule.line.clone(),
+                name: schedule.name.clone(),
+                currency,
+                count: 0,
+                amount: Money::zero(currency),
+                deductible: Money::zero(currency),
+            });
+            total.count += 1;
+            total.amount = total.amount.checked_sub(item.amount)?;
+            total.deductible = total.deductible.checked_add(item.deductible)?;
+        }
+
+        Ok(TaxReport {
+            year,
+            lines: totals.into_values().collect(),
+            items: items.into_iter().map(|(_, item)| item).collect(),
+        })
+    }
+}
+
+/// The total of one schedule line in one currency
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TaxLine {
+    pub line: String,
+    pub name: String,
+    pub currency: Currency,
+    /// Number of qualifying transactions
+    pub count: usize,
+    /// Money spent, refunds deducted
+    pub amount: Money,
+    /// The part of `amount` that can be claimed
+    pub deductible: Money,
+}
+
+/// A transaction claimed on a schedule line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TaxItem {
+    pub line: String,
+    pub date: NaiveDate,
+    pub description: String,
+    pub category: TransactionCategory,
+    /// The transaction amount, negative for expenses
+    pub amount: Money,
+    pub deductible: Money,
+}
+
+/// Year-end totals per schedule line, in the order of the schedules file, and the transactions
+/// behind them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TaxReport {
+    pub year: i32,
+    pub lines: Vec<TaxLine>,
+    pub items: Vec<TaxItem>,
+}
+
+impl TaxReport {
+    /// A standalone HTML document with both tables, laid out to be printed or saved as PDF
+    pub fn to_html(&self) -> String {
+        let title = escape_html(&format!("Tax report {}", self.year));
+        format!(
+            "<!DOCTYPE html>\n\
+             <html lang=\"en\">\n\
+             <head>\n\
+             <meta charset=\"utf-8\">\n\
+             <title>{title}</title>\n\
+             <style>\n\
+             body {{ font-family: sans-serif; font-size: 10pt; margin: 2em; }}\n\
+             table {{ border-collapse: collapse; width: 100%; margin-bottom: 2em; }}\n\
+             th, td {{ border-bottom: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; }}\n\
+             td.amount {{ text-align: right; font-variant-numeric: tabular-nums; }}\n\
+             @page {{ margin: 2cm; }}\n\
+             @media print {{ body {{ margin: 0; }} tr {{ page-break-inside: avoid; }} }}\n\
+             </style>\n\
+             </head>\n\
+             <body>\n\
+             <h1>{title}</h1>\n\
+             <h2>Totals per schedule line</h2>\n\
+             {}\n\
+             <h2>Qualifying transactions</h2>\n\
+             {}\n\
+             </body>\n\
+             </html>\n",
+            html_table(&self.lines),
+            html_table(&self.items),
+        )
+    }
+}
+
+impl Tabular for TaxLine {
+    const HEADERS: &'static [&'static str] = &[
+        "Line",
+        "Name",
+        "Currency",
+        "Transactions",
+        "Amount",
+        "Deductible",
+    ];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.line.clone(),
+            self.name.clone(),
+            self.currency.to_string(),
+            self.count.to_string(),
+            self.amount.to_string(),
+            self.deductible.to_string(),
+        ]
+    }
+}
+
+impl Tabular for TaxItem {
+    const HEADERS: &'static [&'static str] = &[
+        "Line",
+        "Date",
+        "Description",
+        "Category",
+        "Amount",
+        "Currency",
+        "Deductible",
+    ];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.line.clone(),
+            self.date.to_string(),
+            self.description.clone(),
+            self.category.to_string(),
+            self.amount.to_string(),
+            self.amount.currency().to_string(),
+            self.deductible.to_string(),
+        ]
+    }
+}
+
+#[derive(Debug)]
+pub enum TaxError {
+    Io {
+        file: String,
+        source: io::Error,
+    },
+    Toml(toml::de::Error),
+    /// A schedule without categories, tags or payees
+    NothingQualifies(String),
+    InvalidPercent {
+        line: String,
+        percent: u32,
+    },
+    /// Two schedules for the same line
+    Duplicate(String),
+    UnknownCategory {
+        line: String,
+        category: TransactionCategory,
+    },
+}
+
+impl fmt::Display for TaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaxError::Io { file, source } => write!(f, "{file}: {source}"),
+            TaxError::Toml(source) => write!(f, "invalid tax schedules file: {source}"),
+            TaxError::NothingQualifies(line) => {
+                write!(
+                    f,
+                    "schedule line {line:?} has no categories, tags or payees"
+                )
+            }
+            TaxError::InvalidPercent { line, percent } => {
+                write!(
+                    f,
+                    "schedule line {line:?}: percent must be 1 to 100, not {percent}"
+                )
+            }
+            TaxError::Duplicate(line) => write!(f, "more than one schedule for line {line:?}"),
+            TaxError::UnknownCategory { line, category } => {
+                write!(f, "schedule line {line:?}: unknown category {category:?}")
+            }
+        }
+    }
+}
+
+impl Error for TaxError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TaxError::Io { source, .. } => Some(source),
+            TaxError::Toml(source) => Some(source),
+            TaxError::NothingQualifies(_)
+            | TaxError::InvalidPercent { .. }
+            | TaxError::Duplicate(_)
+            | TaxError::UnknownCategory { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./tests/test.rs"]
+mod test;
@@ -0,0 +1,120 @@
+use super::*;
+use rstest::rstest;
+
+use crate::csv_parser::{parse_csv, Split};
+use crate::testing::cad;
+
+const TEST_FILE_PATH: &str = "src/csv_parser/tests/test_statement.csv";
+
+const SCHEDULES_TOML: &str = r#"
+[[schedule]]
+line = "33099"
+name = "Medical expenses"
+categories = ["healthcare"]
+tags = ["medical"]
+
+[[schedule]]
+line = "9945"
+name = "Business-use-of-home expenses"
+payees = ["city  tax", "STRATA FEE"]
+percent = 15
+"#;
+
+#[test]
+fn test_report() {
+    let schedules = Schedules::from_toml(SCHEDULES_TOML).unwrap();
+    let tree = CategoryTree::defaults();
+    schedules.validate(&tree).unwrap();
+    let mut rows = parse_csv(TEST_FILE_PATH).unwrap();
+    rows[2].tags = vec!["medical".to_string()];
+    rows[4].splits = vec![
+        Split {
+            category: TransactionCategory::HEALTHCARE,
+            amount: cad("-120.00"),
+            memo: None,
+        },
+        Split {
+            category: TransactionCategory::FOOD,
+            amount: cad("-80.00"),
+            memo: None,
+        },
+    ];
+    let mut last_year = rows[6].clone();
+    last_year.date = NaiveDate::from_ymd_opt(2023, 12, 28).unwrap();
+    rows.push(last_year);
+    let rows: Vec<Data> = rows.iter().flat_map(Data::allocations).collect();
+
+    let report = schedules.report(&rows, &tree, 2024).unwrap();
+
+    let lines: Vec<(&str, usize, Money, Money)> = report
+        .lines
+        .iter()
+        .map(|line| (line.line.as_str(), line.count, line.amount, line.deductible))
+        .collect();
+    assert_eq!(
+        vec![
+            ("33099", 2, cad("200.00"), cad("200.00")),
+            ("9945", 2, cad("1399.33"), cad("209.90")),
+        ],
+        lines
+    );
+    let items: Vec<(&str, Money)> = report
+        .items
+        .iter()
+        .map(|item| (item.line.as_str(), item.deductible))
+        .collect();
+    assert_eq!(
+        vec![
+            ("33099", cad("80.00")),
+            ("33099", cad("120.00")),
+            ("9945", cad("34.80")),
+            ("9945", cad("175.10")),
+        ],
+        items
+    );
+}
+
+#[test]
+fn test_html_report() {
+    let schedules = Schedules::from_toml(SCHEDULES_TOML).unwrap();
+    let rows = parse_csv(TEST_FILE_PATH).unwrap();
+
+    let html = schedules
+        .report(&rows, &CategoryTree::defaults(), 2024)
+        .unwrap()
+        .to_html();
+
+    assert!(html.starts_with("<!DOCTYPE html>\n"));
+    assert!(html.contains("<title>Tax report 2024</title>"));
+    assert!(html.contains(
+        "<tr><td class=\"amount\">9945</td><td>Business-use-of-home expenses</td><td>CAD</td>\
+         <td class=\"amount\">2</td><td class=\"amount\">1399.33</td>\
+         <td class=\"amount\">209.90</td></tr>"
+    ));
+    assert!(html.contains("<td>[CW]CITY TAX</td>"));
+}
+
+#[rstest]
+#[case("[[schedule]]\nline = \"1\"\nname = \"Gifts\"\n")]
+#[case("[[schedule]]\nline = \"1\"\nname = \"Gifts\"\ntags = [\"gift\"]\npercent = 0\n")]
+#[case(
+    "[[schedule]]\nline = \"1\"\nname = \"A\"\ntags = [\"a\"]\n\
+     [[schedule]]\nline = \" 1 \"\nname = \"B\"\ntags = [\"b\"]\n"
+)]
+#[case("[[schedule]]\nline = \"1\"\nname = \"Gifts\"\ntags = [\"gift\"]\nlimit = \"100\"\n")]
+fn test_invalid_schedules(#[case] toml: &str) {
+    assert!(Schedules::from_toml(toml).is_err());
+}
+
+#[test]
+fn test_validate_against_category_tree() {
+    let schedules = Schedules::from_toml(
+        "[[schedule]]\nline = \"1\"\nname = \"Gifts\"\ncategories = [\"gifts\"]\n",
+    )
+    .unwrap();
+
+    assert!(matches!(
+        schedules.validate(&CategoryTree::defaults()),
+        Err(TaxError::UnknownCategory { line, .. }) if line == "1"
+    ));
+}
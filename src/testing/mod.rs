@@ -0,0 +1,18 @@
+use chrono::NaiveDate;
+
+use crate::money::{Currency, Money};
+
+/// `text` as an amount in Canadian dollars
+pub fn cad(text: &str) -> Money {
+    Money::parse(text, Currency::CAD).unwrap()
+}
+
+/// `text` as an amount in euros
+pub fn eur(text: &str) -> Money {
+    Money::parse(text, Currency::EUR).unwrap()
+}
+
+/// A `%Y-%m-%d` date
+pub fn date(text: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+}
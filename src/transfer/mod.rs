@@ -0,0 +1,213 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::store::StoredTransaction;
+
+/// `Data::categorized_by` of transactions filed as transfers by the matcher
+pub const TRANSFER_RULE: &str = "transfer-matcher";
+
+/// Whether a pair of transactions is known to be a transfer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferStatus {
+    /// Counted as a transfer, both sides are filed under `TransactionCategory::ACCOUNT_TRANSFERS`
+    Confirmed,
+    /// Waiting for the user to confirm or reject it
+    Review,
+    /// Not a transfer, this pair is never suggested again
+    Rejected,
+}
+
+impl TransferStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransferStatus::Confirmed => "confirmed",
+            TransferStatus::Review => "review",
+            TransferStatus::Rejected => "rejected",
+        }
+    }
+
+    pub fn parse(str: &str) -> Option<TransferStatus> {
+        match str {
+            "confirmed" => Some(TransferStatus::Confirmed),
+            "review" => Some(TransferStatus::Review),
+            "rejected" => Some(TransferStatus::Rejected),
+            _ => None,
+        }
+    }
+}
+
+/// A debit in one account paired with the credit it produced in another
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct TransferMatch {
+    /// Store id of the transaction taking the money out
+    pub debit: i64,
+    /// Store id of the transaction bringing the money in
+    pub credit: i64,
+    /// From 0 to 1, lower with every day and cent between the two sides
+    pub confidence: f64,
+    /// `Confirmed` for exact, unambiguous matches, `Review` otherwise
+    pub status: TransferStatus,
+}
+
+/// Words in descriptions that make a transaction more likely to be a transfer
+const TRANSFER_HINTS: &[&str] = &["tf", "transfer", "xfer", "etrnsfr", "e-transfer", "interac"];
+
+/// Pairs debits with credits of the same amount in another account, like the two sides of a
+/// `[CW] TF 000123456789` transfer
+///
+/// A match is confirmed when the amounts are equal, the dates at most `confirm_days` apart, one
+/// of the descriptions reads like a transfer and neither side has any other candidate. Other
+/// candidates within `date_window` days and `amount_tolerance` percent, e.g. a transfer with a fee
+/// taken out, go to the review queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Matcher {
+    /// Largest number of days between the two sides, 3 by default
+    pub date_window: i64,
+    /// Largest difference between the two amounts, as a percentage of the debit, 0 by default
+    pub amount_tolerance: u32,
+    /// Largest number of days between the two sides of a confirmed match, 1 by default
+    pub confirm_days: i64,
+}
+
+impl Default for Matcher {
+    fn default() -> Matcher {
+        Matcher {
+            date_window: 3,
+            amount_tolerance: 0,
+            confirm_days: 1,
+        }
+    }
+}
+
+fn has_hint(description: &str) -> bool {
+    description
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric() && c != '-')
+        .any(|word| TRANSFER_HINTS.contains(&word))
+}
+
+/// A possible pairing, before conflicts between candidates are resolved
+struct Candidate {
+    debit: usize,
+    credit: usize,
+    days: i64,
+    exact: bool,
+    hinted: bool,
+    confidence: f64,
+}
+
+impl Matcher {
+    fn candidate(
+        &self,
+        debit: (usize, &StoredTransaction),
+        credit: (usize, &StoredTransaction),
+    ) -> Option<Candidate> {
+        let (debit_index, debit) = debit;
+        let (credit_index, credit) = credit;
+        if debit.account == credit.account
+            || !debit.data.amount.is_negative()
+            || !credit.data.amount.is_positive()
+            || debit.data.amount.currency() != credit.data.amount.currency()
+        {
+            return None;
+        }
+        let days = (credit.data.date - debit.data.date).num_days().abs();
+        if days > self.date_window {
+            return None;
+        }
+        let out = i128::from(debit.data.amount.minor_units()).abs();
+        let difference = (out - i128::from(credit.data.amount.minor_units())).abs();
+        if difference * 100 > i128::from(self.amount_tolerance) * out {
+            return None;
+        }
+
+        let hinted = has_hint(&debit.data.description) || has_hint(&credit.data.description);
+        let confidence = (1.0
+            - 0.1 * days as f64
+            - difference as f64 / out as f64
+            - if hinted { 0.0 } else { 0.2 })
+        .clamp(0.0, 1.0);
+        Some(Candidate {
+            debit: debit_index,
+            credit: credit_index,
+            days,
+            exact: difference == 0,
+            hinted,
+            confidence,
+        })
+    }
+
+    /// Pairs transfers among `transactions`, each transaction being used at most once
+    ///
+    /// Candidates are taken from the most to the least confident. Transactions already paired in
+    /// the store should be left out of `transactions`.
+    pub fn find(&self, transactions: &[StoredTransaction]) -> Vec<TransferMatch> {
+        self.find_except(transactions, &HashSet::new())
+    }
+
+    /// Like `find`, without suggesting any of the `(debit, credit)` id pairs in `rejected`
+    ///
+    /// Both sides of a rejected pair can still be paired with other transactions.
+    pub fn find_except(
+        &self,
+        transactions: &[StoredTransaction],
+        rejected: &HashSet<(i64, i64)>,
+    ) -> Vec<TransferMatch> {
+        let mut candidates = Vec::new();
+        for debit in transactions.iter().enumerate() {
+            for credit in transactions.iter().enumerate() {
+                if rejected.contains(&(debit.1.id, credit.1.id)) {
+                    continue;
+                }
+                if let Some(candidate) = self.candidate(debit, credit) {
+                    candidates.push(candidate);
+                }
+            }
+        }
+
+        let mut options = vec![0usize; transactions.len()];
+        for candidate in &candidates {
+            options[candidate.debit] += 1;
+            options[candidate.credit] += 1;
+        }
+
+        candidates.sort_by(|a, b| {
+            b.confidence
+                .total_cmp(&a.confidence)
+                .then(a.days.cmp(&b.days))
+                .then(a.debit.cmp(&b.debit))
+                .then(a.credit.cmp(&b.credit))
+        });
+        let mut used = vec![false; transactions.len()];
+        let mut matches = Vec::new();
+        for candidate in candidates {
+            if used[candidate.debit] || used[candidate.credit] {
+                continue;
+            }
+            used[candidate.debit] = true;
+            used[candidate.credit] = true;
+            let certain = candidate.exact
+                && candidate.hinted
+                && candidate.days <= self.confirm_days
+                && options[candidate.debit] == 1
+                && options[candidate.credit] == 1;
+            matches.push(TransferMatch {
+                debit: transactions[candidate.debit].id,
+                credit: transactions[candidate.credit].id,
+                confidence: candidate.confidence,
+                status: if certain {
+                    TransferStatus::Confirmed
+                } else {
+                    TransferStatus::Review
+                },
+            });
+        }
+        matches
+    }
+}
+
+#[cfg(test)]
+#[path = "./tests/test.rs"]
+mod test;
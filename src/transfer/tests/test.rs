@@ -0,0 +1,137 @@
+use super::*;
+
+use chrono::NaiveDate;
+
+use crate::csv_parser::Data;
+use crate::money::{Currency, Money};
+
+fn transaction(
+    id: i64,
+    account: &str,
+    day: &str,
+    amount: &str,
+    description: &str,
+) -> StoredTransaction {
+    StoredTransaction {
+        id,
+        batch_id: 1,
+        account: account.to_string(),
+        fingerprint: id.to_string(),
+        data: Data::new(
+            NaiveDate::parse_from_str(day, "%Y-%m-%d").unwrap(),
+            Money::parse(amount, Currency::CAD).unwrap(),
+            description,
+        ),
+    }
+}
+
+#[test]
+fn test_exact_match_is_confirmed() {
+    let transactions = vec![
+        transaction(
+            1,
+            "chequing",
+            "2024-06-24",
+            "-1500.00",
+            "[CW] TF 000123456789",
+        ),
+        transaction(2, "chequing", "2024-06-24", "1500.00", "REVERSAL"),
+        transaction(
+            3,
+            "savings",
+            "2024-06-24",
+            "1500.00",
+            "TRANSFER FROM CHEQUING",
+        ),
+        transaction(4, "savings", "2024-06-25", "-1500.00", "TUITION"),
+    ];
+
+    let matches = Matcher::default().find(&transactions);
+
+    assert_eq!(
+        vec![TransferMatch {
+            debit: 1,
+            credit: 3,
+            confidence: 1.0,
+            status: TransferStatus::Confirmed,
+        }],
+        matches[..1]
+    );
+    // The tuition payment is only a candidate for the reversal, in another account
+    assert_eq!(2, matches.len());
+    assert_eq!((4, 2), (matches[1].debit, matches[1].credit));
+    assert_eq!(TransferStatus::Review, matches[1].status);
+}
+
+#[test]
+fn test_uncertain_matches_go_to_review() {
+    let transactions = vec![
+        transaction(
+            1,
+            "chequing",
+            "2024-06-10",
+            "-80.00",
+            "[CW]INTERAC ETRNSFR SENT BROTHER",
+        ),
+        transaction(2, "visa", "2024-06-12", "80.00", "PAYMENT RECEIVED"),
+        transaction(3, "chequing", "2024-06-20", "-200.00", "[IB] SHAUGHNES"),
+        transaction(4, "savings", "2024-06-20", "200.00", "DEPOSIT"),
+        transaction(5, "visa", "2024-06-20", "200.00", "PAYMENT RECEIVED"),
+    ];
+
+    let matches = Matcher::default().find(&transactions);
+
+    assert_eq!(2, matches.len());
+    assert!(matches
+        .iter()
+        .all(|transfer| transfer.status == TransferStatus::Review));
+    let etransfer = matches.iter().find(|transfer| transfer.debit == 1).unwrap();
+    assert_eq!(2, etransfer.credit);
+    assert!((etransfer.confidence - 0.8).abs() < 1e-9);
+    // Two credits could be the other side of the 200.00 debit
+    let ambiguous = matches.iter().find(|transfer| transfer.debit == 3).unwrap();
+    assert_eq!(4, ambiguous.credit);
+    assert!((ambiguous.confidence - 0.8).abs() < 1e-9);
+}
+
+#[test]
+fn test_rejected_pairs_are_not_suggested_again() {
+    let transactions = vec![
+        transaction(1, "chequing", "2024-06-24", "-1500.00", "TF TO SAVINGS"),
+        transaction(2, "savings", "2024-06-24", "1500.00", "TF FROM CHEQUING"),
+        transaction(3, "savings", "2024-06-26", "1500.00", "TF FROM CHEQUING"),
+    ];
+    let rejected = HashSet::from([(1, 2)]);
+
+    let matches = Matcher::default().find_except(&transactions, &rejected);
+
+    assert_eq!(1, matches.len());
+    assert_eq!((1, 3), (matches[0].debit, matches[0].credit));
+}
+
+#[test]
+fn test_amount_tolerance_and_date_window() {
+    let transactions = vec![
+        transaction(1, "chequing", "2024-06-03", "-500.00", "TF TO USD"),
+        transaction(2, "savings", "2024-06-04", "495.00", "TF FROM CHEQUING"),
+        transaction(3, "chequing", "2024-06-03", "-60.00", "TF"),
+        transaction(4, "savings", "2024-06-13", "60.00", "TF"),
+    ];
+
+    assert!(Matcher::default().find(&transactions).is_empty());
+
+    let matcher = Matcher {
+        amount_tolerance: 1,
+        ..Matcher::default()
+    };
+    let matches = matcher.find(&transactions);
+    assert_eq!(1, matches.len());
+    assert_eq!((1, 2), (matches[0].debit, matches[0].credit));
+    assert_eq!(TransferStatus::Review, matches[0].status);
+
+    let matcher = Matcher {
+        date_window: 10,
+        ..Matcher::default()
+    };
+    assert_eq!(3, matcher.find(&transactions)[0].debit);
+}